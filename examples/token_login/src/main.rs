@@ -1,12 +1,11 @@
 use std::sync::Arc;
 
-use rand::prelude::StdRng;
-use rand::SeedableRng;
 use tracing::Level;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use ricq::client::{Connector as _, DefaultConnector, Token};
 use ricq::ext::common::after_login;
+use ricq::ext::session::{FileSessionStore, Session, SessionStore};
 use ricq::handler::DefaultHandler;
 use ricq::{Client, Device, Protocol};
 
@@ -30,13 +29,29 @@ async fn main() {
         )
         .init();
 
-    let token = tokio::fs::read_to_string("session.token")
-        .await
-        .expect("failed to read token");
-    let token: Token = serde_json::from_str(&token).expect("failed to parse token");
-    let device = Device::random_with_rng(&mut StdRng::seed_from_u64(token.uin as u64));
+    // 会话加密保存在 session.bin，登录成功和刷新 token 后自动更新
+    let secret = std::env::var("RICQ_SESSION_SECRET").expect("RICQ_SESSION_SECRET is not set");
+    let store = Arc::new(FileSessionStore::new("session.bin", secret));
+    let session = match store.load().await.expect("failed to load session") {
+        Some(session) => session,
+        None => {
+            // 从明文 session.token 迁移
+            let token = tokio::fs::read_to_string("session.token")
+                .await
+                .expect("failed to read token");
+            let token: Token = serde_json::from_str(&token).expect("failed to parse token");
+            Session {
+                device: Device::from_seed(token.uin),
+                version: Protocol::IPad.into(),
+                token,
+            }
+        }
+    };
 
-    let client = Arc::new(Client::new(device, Protocol::IPad.into(), DefaultHandler));
+    let client = Arc::new(Client::new_with_config(
+        session.config().with_session_store(store),
+        DefaultHandler,
+    ));
 
     let handle = tokio::spawn({
         let client = client.clone();
@@ -52,7 +67,7 @@ async fn main() {
 
     tokio::task::yield_now().await; // 等一下，确保连上了
     let resp = client
-        .token_login(token)
+        .token_login(session.token)
         .await
         .expect("failed to login with token");
 
@@ -0,0 +1,22 @@
+#![no_main]
+
+use std::sync::OnceLock;
+
+use bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+use ricq_core::protocol::device::Device;
+use ricq_core::protocol::transport::Transport;
+use ricq_core::protocol::version::{get_version, Protocol};
+
+fn transport() -> &'static Transport {
+    static TRANSPORT: OnceLock<Transport> = OnceLock::new();
+    TRANSPORT.get_or_init(|| {
+        let mut transport = Transport::new(Device::random(), get_version(Protocol::IPad));
+        transport.sig.d2key = Bytes::from_static(&[1; 16]);
+        transport
+    })
+}
+
+fuzz_target!(|data: &[u8]| {
+    let _ = transport().decode_packet(data);
+});
@@ -0,0 +1,33 @@
+#![no_main]
+
+use std::sync::OnceLock;
+
+use bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+use ricq_core::protocol::device::Device;
+use ricq_core::protocol::version::{get_version, Protocol};
+use ricq_core::Engine;
+
+fn engine() -> &'static Engine {
+    static ENGINE: OnceLock<Engine> = OnceLock::new();
+    ENGINE.get_or_init(|| Engine::new(Device::random(), get_version(Protocol::IPad)))
+}
+
+// 第一个字节选择解析的推送类型
+fuzz_target!(|data: &[u8]| {
+    let Some((&kind, data)) = data.split_first() else {
+        return;
+    };
+    let engine = engine();
+    let payload = Bytes::copy_from_slice(data);
+    match kind % 8 {
+        0 => drop(engine.decode_online_push_req_packet(payload)),
+        1 => drop(engine.decode_push_req_packet(payload)),
+        2 => drop(engine.decode_svc_notify(payload)),
+        3 => drop(engine.decode_force_offline(payload)),
+        4 => drop(engine.decode_msf_force_offline(payload)),
+        5 => drop(engine.decode_msf_login_notify(payload)),
+        6 => drop(engine.decode_client_register_response(payload)),
+        _ => drop(engine.decode_push_param_packet(data)),
+    }
+});
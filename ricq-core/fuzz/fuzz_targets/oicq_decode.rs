@@ -0,0 +1,19 @@
+#![no_main]
+
+use std::sync::OnceLock;
+
+use bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+use ricq_core::protocol::oicq::Codec;
+
+fn codec() -> &'static Codec {
+    static CODEC: OnceLock<Codec> = OnceLock::new();
+    CODEC.get_or_init(|| Codec {
+        wt_session_ticket_key: Bytes::from_static(&[1; 16]),
+        ..Default::default()
+    })
+}
+
+fuzz_target!(|data: &[u8]| {
+    let _ = codec().decode(data);
+});
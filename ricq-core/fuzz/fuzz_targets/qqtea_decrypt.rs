@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ricq_core::crypto::qqtea_decrypt;
+
+// 前 16 字节作为密钥
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 16 {
+        return;
+    }
+    let (key, text) = data.split_at(16);
+    let _ = qqtea_decrypt(text, key);
+});
@@ -0,0 +1,26 @@
+#![no_main]
+
+use bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+use ricq_core::binary::BinaryReader;
+use ricq_core::command::wtlogin::tlv_reader::*;
+
+// 第一个字节选择 tlv
+fuzz_target!(|data: &[u8]| {
+    let Some((&kind, data)) = data.split_first() else {
+        return;
+    };
+    let payload = Bytes::copy_from_slice(data);
+    match kind % 10 {
+        0 => drop(payload.clone().read_tlv_map(2)),
+        1 => drop(decode_t161(payload)),
+        2 => drop(decode_t119(data, &[1; 16])),
+        3 => drop(decode_t113(payload)),
+        4 => drop(read_t125(data)),
+        5 => drop(read_t11a(payload)),
+        6 => drop(read_t199(payload)),
+        7 => drop(read_t200(payload)),
+        8 => drop(read_t512(payload)),
+        _ => drop(read_t531(payload)),
+    }
+});
@@ -0,0 +1,32 @@
+#![no_main]
+
+use std::sync::OnceLock;
+
+use bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+use ricq_core::protocol::device::Device;
+use ricq_core::protocol::version::{get_version, Protocol};
+use ricq_core::Engine;
+
+fn engine() -> &'static Engine {
+    static ENGINE: OnceLock<Engine> = OnceLock::new();
+    ENGINE.get_or_init(|| {
+        let mut engine = Engine::new(Device::random(), get_version(Protocol::IPad));
+        engine.transport.sig.d2key = Bytes::from_static(&[1; 16]);
+        engine
+    })
+}
+
+// 第一个字节选择 wtlogin 回包类型
+fuzz_target!(|data: &[u8]| {
+    let Some((&kind, data)) = data.split_first() else {
+        return;
+    };
+    let engine = engine();
+    let payload = Bytes::copy_from_slice(data);
+    match kind % 3 {
+        0 => drop(engine.decode_login_response(payload)),
+        1 => drop(engine.decode_trans_emp_response(payload)),
+        _ => drop(engine.decode_exchange_emp_response(payload)),
+    }
+});
@@ -2,38 +2,97 @@ use std::collections::HashMap;
 
 use bytes::{Buf, Bytes};
 
+use crate::{RQError, RQResult};
+
+/// 读取失败时返回 `RQError::Decode`，不会因为数据截断而 panic
 pub trait BinaryReader {
-    fn read_string(&mut self) -> String;
-    fn read_string_short(&mut self) -> String;
-    fn read_bytes_short(&mut self) -> Bytes;
-    fn read_tlv_map(&mut self, tag_size: usize) -> HashMap<u16, Bytes>;
-    fn read_string_limit(&mut self, limit: usize) -> String;
+    fn read_u8(&mut self) -> RQResult<u8>;
+    fn read_u16(&mut self) -> RQResult<u16>;
+    fn read_i32(&mut self) -> RQResult<i32>;
+    fn read_u32(&mut self) -> RQResult<u32>;
+    fn read_i64(&mut self) -> RQResult<i64>;
+    fn read_bytes(&mut self, len: usize) -> RQResult<Bytes>;
+    fn skip(&mut self, len: usize) -> RQResult<()>;
+    fn read_string(&mut self) -> RQResult<String>;
+    fn read_string_short(&mut self) -> RQResult<String>;
+    fn read_bytes_short(&mut self) -> RQResult<Bytes>;
+    fn read_tlv_map(&mut self, tag_size: usize) -> RQResult<HashMap<u16, Bytes>>;
+    fn read_string_limit(&mut self, limit: usize) -> RQResult<String>;
+}
+
+fn ensure_remaining(remaining: usize, need: usize) -> RQResult<()> {
+    if remaining < need {
+        return Err(RQError::Decode(format!(
+            "unexpected eof: need {need} bytes, remaining {remaining}"
+        )));
+    }
+    Ok(())
 }
 
 impl<B> BinaryReader for B
 where
     B: Buf,
 {
-    fn read_string(&mut self) -> String {
-        let len = self.get_i32() as usize - 4;
-        String::from_utf8_lossy(&self.copy_to_bytes(len)).into_owned()
+    fn read_u8(&mut self) -> RQResult<u8> {
+        ensure_remaining(self.remaining(), 1)?;
+        Ok(self.get_u8())
+    }
+
+    fn read_u16(&mut self) -> RQResult<u16> {
+        ensure_remaining(self.remaining(), 2)?;
+        Ok(self.get_u16())
+    }
+
+    fn read_i32(&mut self) -> RQResult<i32> {
+        ensure_remaining(self.remaining(), 4)?;
+        Ok(self.get_i32())
+    }
+
+    fn read_u32(&mut self) -> RQResult<u32> {
+        ensure_remaining(self.remaining(), 4)?;
+        Ok(self.get_u32())
+    }
+
+    fn read_i64(&mut self) -> RQResult<i64> {
+        ensure_remaining(self.remaining(), 8)?;
+        Ok(self.get_i64())
+    }
+
+    fn read_bytes(&mut self, len: usize) -> RQResult<Bytes> {
+        ensure_remaining(self.remaining(), len)?;
+        Ok(self.copy_to_bytes(len))
+    }
+
+    fn skip(&mut self, len: usize) -> RQResult<()> {
+        ensure_remaining(self.remaining(), len)?;
+        self.advance(len);
+        Ok(())
     }
 
-    fn read_string_short(&mut self) -> String {
-        let len = self.get_u16() as usize;
-        String::from_utf8_lossy(&self.copy_to_bytes(len)).into_owned()
+    fn read_string(&mut self) -> RQResult<String> {
+        let len = self.read_i32()?;
+        let len = usize::try_from(len)
+            .ok()
+            .and_then(|len| len.checked_sub(4))
+            .ok_or_else(|| RQError::Decode(format!("invalid string length: {len}")))?;
+        self.read_string_limit(len)
     }
 
-    fn read_bytes_short(&mut self) -> Bytes {
-        let len = self.get_u16() as usize;
-        self.copy_to_bytes(len)
+    fn read_string_short(&mut self) -> RQResult<String> {
+        let len = self.read_u16()? as usize;
+        self.read_string_limit(len)
     }
 
-    fn read_tlv_map(&mut self, tag_size: usize) -> HashMap<u16, Bytes> {
+    fn read_bytes_short(&mut self) -> RQResult<Bytes> {
+        let len = self.read_u16()? as usize;
+        self.read_bytes(len)
+    }
+
+    fn read_tlv_map(&mut self, tag_size: usize) -> RQResult<HashMap<u16, Bytes>> {
         let mut m = HashMap::new();
         loop {
             if self.remaining() < tag_size {
-                return m;
+                return Ok(m);
             }
             let mut k = 0;
             if tag_size == 1 {
@@ -44,20 +103,83 @@ where
                 k = self.get_i32() as u16;
             }
             if k == 255 {
-                return m;
+                return Ok(m);
             }
             if self.remaining() < 2 {
-                return m;
+                return Ok(m);
             }
             let len = self.get_u16() as usize;
             if self.remaining() < len {
-                return m;
+                return Err(RQError::Decode(format!(
+                    "tlv {k:#x} truncated: need {len} bytes, remaining {}",
+                    self.remaining()
+                )));
             }
             m.insert(k, self.copy_to_bytes(len));
         }
     }
 
-    fn read_string_limit(&mut self, limit: usize) -> String {
-        String::from_utf8_lossy(&self.copy_to_bytes(limit)).into_owned()
+    fn read_string_limit(&mut self, limit: usize) -> RQResult<String> {
+        Ok(String::from_utf8_lossy(&self.read_bytes(limit)?).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{BufMut, BytesMut};
+    use proptest::collection::{hash_map, vec};
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::binary::BinaryWriter;
+
+    #[test]
+    fn test_truncated_input() {
+        let mut r: &[u8] = &[0, 5, b'a', b'b'];
+        assert!(r.read_string_short().is_err());
+        let mut r: &[u8] = &[0, 0, 0, 2];
+        assert!(r.read_string().is_err());
+        let mut r: &[u8] = &[0x01, 0x06, 0, 4, 1, 2];
+        assert!(r.read_tlv_map(2).is_err());
+        let mut r: &[u8] = &[0];
+        assert!(r.read_u16().is_err());
+
+        let mut r: &[u8] = &[0, 0, 0, 6, b'o', b'k', 0, 1, 0, 1, 9];
+        assert_eq!(r.read_string().unwrap(), "ok");
+        let m = r.read_tlv_map(2).unwrap();
+        assert_eq!(m.get(&1).map(|v| v.as_ref()), Some(&[9u8][..]));
+    }
+
+    proptest! {
+        #[test]
+        fn test_writer_reader_round_trip(
+            string in ".{0,64}",
+            short in vec(any::<u8>(), 0..256),
+            tlvs in hash_map(0u16..255, vec(any::<u8>(), 0..64), 0..8),
+        ) {
+            let mut w = BytesMut::new();
+            w.write_string(&string);
+            w.write_bytes_short(&short);
+            for (tag, value) in &tlvs {
+                w.put_u16(*tag);
+                w.write_bytes_short(value);
+            }
+            let mut r = w.freeze();
+            prop_assert_eq!(r.read_string()?, string);
+            prop_assert_eq!(r.read_bytes_short()?.to_vec(), short);
+            let m = r.read_tlv_map(2)?;
+            prop_assert_eq!(m.len(), tlvs.len());
+            for (tag, value) in &tlvs {
+                prop_assert_eq!(m.get(tag).map(|v| v.as_ref()), Some(&value[..]));
+            }
+        }
+
+        #[test]
+        fn test_read_arbitrary(data in vec(any::<u8>(), 0..64), tag_size in prop_oneof![Just(1usize), Just(2), Just(4)]) {
+            let _ = (&data[..]).read_string();
+            let _ = (&data[..]).read_string_short();
+            let _ = (&data[..]).read_bytes_short();
+            let _ = (&data[..]).read_tlv_map(tag_size);
+        }
     }
 }
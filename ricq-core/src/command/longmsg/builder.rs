@@ -2,13 +2,25 @@ use crate::pb;
 use prost::Message;
 
 impl super::super::super::Engine {
+    /// 群长消息、转发消息
     pub fn build_long_req(&self, dst_uin: i64, msg_content: Vec<u8>, msg_ukey: Vec<u8>) -> Vec<u8> {
+        self.build_long_req_with_msg_type(dst_uin, 3, msg_content, msg_ukey)
+    }
+
+    /// msg_type: 3 群，1 私聊，见 [`MultiMsgTarget::msg_type`](crate::command::multi_msg::MultiMsgTarget::msg_type)
+    pub fn build_long_req_with_msg_type(
+        &self,
+        dst_uin: i64,
+        msg_type: i32,
+        msg_content: Vec<u8>,
+        msg_ukey: Vec<u8>,
+    ) -> Vec<u8> {
         pb::longmsg::LongReqBody {
             subcmd: 1,
             term_type: 5,
             platform_type: 9,
             msg_up_req: vec![pb::longmsg::LongMsgUpReq {
-                msg_type: 3, // group
+                msg_type,
                 dst_uin,
                 msg_id: 0,
                 msg_content,
@@ -136,6 +136,49 @@ impl super::super::super::Engine {
         self.uni_packet("MessageSvc.PbSendMsg", req.to_bytes())
     }
 
+    // MessageSvc.PbSendMsg
+    pub fn build_offline_file_sending_packet(
+        &self,
+        target: i64,
+        file: pb::msg::NotOnlineFile,
+        seq: i32,
+        ran: i32,
+        time: i64,
+    ) -> Packet {
+        let sync_cookie = self.sync_cookie(time);
+        let req = pb::msg::SendMessageRequest {
+            routing_head: Some(pb::msg::RoutingHead {
+                routing_head: Some(pb::msg::routing_head::RoutingHead::Trans0x211(
+                    pb::msg::Trans0x211 {
+                        to_uin: Some(target),
+                        cc_cmd: Some(4),
+                    },
+                )),
+            }),
+            content_head: Some(pb::msg::ContentHead {
+                pkg_num: Some(1),
+                pkg_index: Some(0),
+                div_seq: Some(0),
+                ..Default::default()
+            }),
+            msg_body: Some(pb::msg::MessageBody {
+                msg_content: Some(
+                    pb::msg::SubMsgType0x4Body {
+                        not_online_file: Some(file),
+                        ..Default::default()
+                    }
+                    .encode_to_vec(),
+                ),
+                ..Default::default()
+            }),
+            msg_seq: Some(seq),
+            msg_rand: Some(ran),
+            sync_cookie: Some(sync_cookie),
+            ..Default::default()
+        };
+        self.uni_packet("MessageSvc.PbSendMsg", req.to_bytes())
+    }
+
     // MessageSvc.PbGetGroupMsg
     pub fn build_get_group_msg_request(
         &self,
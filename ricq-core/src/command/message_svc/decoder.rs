@@ -1,13 +1,28 @@
-use bytes::{Buf, Bytes};
+use bytes::Bytes;
 
-use crate::pb::msg::GetMessageResponse;
-use crate::{jce, RQError, RQResult};
+use crate::binary::BinaryReader;
+use crate::command::online_push::GroupMessagePart;
+use crate::pb::msg::{GetGroupMsgResp, GetMessageResponse};
+use crate::{jce, pb, RQError, RQResult};
 use prost::Message;
 
 impl crate::Engine {
+    // MessageSvc.PbSendMsg
+    pub fn decode_send_message_response(&self, payload: Bytes) -> RQResult<()> {
+        let rsp = pb::msg::SendMessageResponse::decode(&*payload)?;
+        if rsp.result() != 0 {
+            return Err(RQError::Other(format!(
+                "send message failed: {} {}",
+                rsp.result(),
+                rsp.err_msg()
+            )));
+        }
+        Ok(())
+    }
+
     // MessageSvc.PushNotify
     pub fn decode_svc_notify(&self, mut payload: Bytes) -> RQResult<jce::RequestPushNotify> {
-        payload.advance(4);
+        payload.skip(4)?;
         let mut req: jce::RequestPacket = jcers::from_buf(&mut payload)?;
         let mut data: jce::RequestDataVersion2 = jcers::from_buf(&mut req.s_buffer)?;
         let mut notify_data = data
@@ -16,7 +31,7 @@ impl crate::Engine {
             .ok_or_else(|| RQError::Decode("req_PushNotify".into()))?
             .remove("PushNotifyPack.RequestPushNotify")
             .ok_or_else(|| RQError::Decode("PushNotifyPack.RequestPushNotify".into()))?;
-        notify_data.advance(1);
+        notify_data.skip(1)?;
         let notify: jce::RequestPushNotify = jcers::from_buf(&mut notify_data)?;
         Ok(notify)
     }
@@ -34,7 +49,7 @@ impl crate::Engine {
             .ok_or_else(|| RQError::Decode("req_PushForceOffline".into()))?
             .remove("PushNotifyPack.RequestPushForceOffline")
             .ok_or_else(|| RQError::Decode("PushNotifyPack.RequestPushForceOffline".into()))?;
-        data.advance(1);
+        data.skip(1)?;
         let offline: jce::RequestPushForceOffline = jcers::from_buf(&mut data)?;
         Ok(offline)
     }
@@ -57,4 +72,58 @@ impl crate::Engine {
                 .collect(),
         })
     }
+
+    // MessageSvc.PbGetGroupMsg
+    pub fn decode_get_group_msg_response(
+        &self,
+        payload: Bytes,
+    ) -> RQResult<super::GroupMessageHistory> {
+        let resp = GetGroupMsgResp::decode(&*payload)?;
+        let result = resp.result.unwrap_or_default();
+        if result != 0 {
+            return Err(RQError::Other(format!(
+                "get group msg error: {} {}",
+                result,
+                resp.errmsg.unwrap_or_default()
+            )));
+        }
+        Ok(super::GroupMessageHistory {
+            group_code: resp.group_code.unwrap_or_default() as i64,
+            return_begin_seq: resp.return_begin_seq.unwrap_or_default() as i64,
+            return_end_seq: resp.return_end_seq.unwrap_or_default() as i64,
+            parts: resp
+                .msg
+                .into_iter()
+                .filter_map(history_message_part)
+                .collect(),
+        })
+    }
+}
+
+// 历史消息不一定有群名片、分片信息；已撤回的消息没有 body，直接跳过
+fn history_message_part(msg: pb::msg::Message) -> Option<GroupMessagePart> {
+    let head = msg.head?;
+    let rich_text = msg.body?.rich_text?;
+    let group_info = head.group_info.unwrap_or_default();
+    let content = msg.content.unwrap_or_default();
+    let seq = head.msg_seq?;
+    Some(GroupMessagePart {
+        seq,
+        rand: rich_text
+            .attr
+            .and_then(|attr| attr.random)
+            .unwrap_or_default(),
+        group_code: group_info.group_code.unwrap_or_default(),
+        group_name: String::from_utf8_lossy(&group_info.group_name.unwrap_or_default())
+            .into_owned(),
+        group_card: String::from_utf8_lossy(&group_info.group_card.unwrap_or_default())
+            .into_owned(),
+        from_uin: head.from_uin.unwrap_or_default(),
+        elems: rich_text.elems,
+        time: head.msg_time.unwrap_or_default(),
+        ptt: rich_text.ptt,
+        pkg_num: content.pkg_num.unwrap_or(1),
+        pkg_index: content.pkg_index.unwrap_or_default(),
+        div_seq: content.div_seq.unwrap_or(seq),
+    })
 }
@@ -1,3 +1,4 @@
+use crate::command::online_push::GroupMessagePart;
 use crate::pb;
 
 pub mod builder;
@@ -10,3 +11,12 @@ pub struct MessageSyncResponse {
     pub pub_account_cookie: Option<Vec<u8>>,
     pub msgs: Vec<pb::msg::Message>,
 }
+
+/// MessageSvc.PbGetGroupMsg 返回的群消息，分片尚未合并
+#[derive(Debug, Default, Clone)]
+pub struct GroupMessageHistory {
+    pub group_code: i64,
+    pub return_begin_seq: i64,
+    pub return_end_seq: i64,
+    pub parts: Vec<GroupMessagePart>,
+}
@@ -9,6 +9,7 @@ pub mod long_conn;
 pub mod longmsg;
 pub mod message_svc;
 pub mod multi_msg;
+pub mod offline_file_handle_svr;
 pub mod oidb_svc;
 pub mod online_push;
 pub mod pb_message_svc;
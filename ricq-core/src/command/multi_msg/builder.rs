@@ -5,7 +5,7 @@ use flate2::write::GzEncoder;
 use flate2::Compression;
 
 use crate::command::common::PbToBytes;
-use crate::command::multi_msg::{ForwardMessage, PackedMessage};
+use crate::command::multi_msg::{ForwardMessage, MultiMsgTarget, PackedMessage};
 use crate::msg::elem::RichMsg;
 use crate::msg::MessageChain;
 use crate::pb;
@@ -13,44 +13,83 @@ use crate::protocol::device::random_string;
 use crate::protocol::packet::Packet;
 
 impl super::super::super::Engine {
+    /// 下载转发消息
     pub fn build_multi_msg_apply_down_req(&self, res_id: String) -> Packet {
+        self.build_multi_msg_apply_down_req_with_bu_type(res_id, 2)
+    }
+
+    /// bu_type: 1 长消息，2 转发消息
+    pub fn build_multi_msg_apply_down_req_with_bu_type(
+        &self,
+        res_id: String,
+        bu_type: i32,
+    ) -> Packet {
         let req = pb::multimsg::MultiReqBody {
             subcmd: 2,
             term_type: 5,
             platform_type: 9,
             net_type: 3,
-            build_ver: self.transport.version.build_ver.into(),
+            build_ver: self.transport.version.build_ver.to_string(),
             multimsg_applydown_req: vec![pb::multimsg::MultiMsgApplyDownReq {
                 msg_resid: res_id.into_bytes(),
                 msg_type: 3,
                 ..Default::default()
             }],
-            bu_type: 2,
+            bu_type,
             req_channel_type: 2,
             ..Default::default()
         };
         self.uni_packet("MultiMsg.ApplyDown", req.to_bytes())
     }
 
+    /// 上传群长消息、转发消息，bu_type: 1 长消息，2 转发消息
     pub fn build_multi_msg_apply_up_req(
         &self,
         msg_size: i64,
         msg_md5: Vec<u8>,
         bu_type: i32,
         dst_uin: i64,
+    ) -> Packet {
+        self.apply_up_req(msg_size, msg_md5, bu_type, dst_uin, 3)
+    }
+
+    /// 上传群、好友的长消息、转发消息，bu_type: 1 长消息，2 转发消息
+    pub fn build_multi_msg_apply_up_req_with_target(
+        &self,
+        msg_size: i64,
+        msg_md5: Vec<u8>,
+        bu_type: i32,
+        target: MultiMsgTarget,
+    ) -> Packet {
+        self.apply_up_req(
+            msg_size,
+            msg_md5,
+            bu_type,
+            target.dst_uin(),
+            target.msg_type(),
+        )
+    }
+
+    fn apply_up_req(
+        &self,
+        msg_size: i64,
+        msg_md5: Vec<u8>,
+        bu_type: i32,
+        dst_uin: i64,
+        msg_type: i32,
     ) -> Packet {
         let req = pb::multimsg::MultiReqBody {
             subcmd: 1,
             term_type: 5,
             platform_type: 9,
             net_type: 3,
-            build_ver: self.transport.version.build_ver.into(),
+            build_ver: self.transport.version.build_ver.to_string(),
             req_channel_type: 0,
             multimsg_applyup_req: vec![pb::multimsg::MultiMsgApplyUpReq {
                 dst_uin,
                 msg_size,
                 msg_md5,
-                msg_type: 3, // group
+                msg_type,
                 ..Default::default()
             }],
             bu_type,
@@ -63,11 +102,20 @@ impl super::super::super::Engine {
         &self,
         messages: Vec<super::ForwardMessage>,
         group_code: i64,
+    ) -> Vec<u8> {
+        self.calculate_multi_msg_data(messages, MultiMsgTarget::Group(group_code))
+    }
+
+    /// 打包转发消息、长消息，返回 gzip 压缩后的 PbMultiMsgTransmit
+    pub fn calculate_multi_msg_data(
+        &self,
+        messages: Vec<super::ForwardMessage>,
+        target: MultiMsgTarget,
     ) -> Vec<u8> {
         let PackedMessage {
             mut buffer,
             filename,
-        } = self.pack_forward_msg(messages, group_code);
+        } = self.pack_forward_msg(messages, target);
         let msgs = buffer.remove(&filename).expect("msgs not found");
         let mut pb_item_list = vec![pb::msg::PbMultiMsgItem {
             file_name: Some("MultiMsg".into()),
@@ -91,19 +139,17 @@ impl super::super::super::Engine {
     fn pack_forward_msg(
         &self,
         messages: Vec<super::ForwardMessage>,
-        group_code: i64,
+        target: MultiMsgTarget,
     ) -> PackedMessage {
         let mut packed_buffers = HashMap::default();
         let msgs: Vec<pb::msg::Message> = messages
             .into_iter()
             .map(|m| match m {
-                ForwardMessage::Message(message) => {
-                    self.pack_msg(message, group_code)
-                }
+                ForwardMessage::Message(message) => self.pack_msg(message, target),
                 ForwardMessage::Forward(forward) => {
                     let t_sum = forward.nodes.len();
                     let preview = super::gen_forward_preview(&forward.nodes);
-                    let packed_message = self.pack_forward_msg(forward.nodes, group_code);
+                    let packed_message = self.pack_forward_msg(forward.nodes, target);
                     packed_buffers.extend(packed_message.buffer);
                     self.pack_msg(
                         super::MessageNode {
@@ -112,15 +158,19 @@ impl super::super::super::Engine {
                             sender_name: forward.sender_name,
                             elements: MessageChain(
                                 RichMsg {
-                                    template1: format!(
-                                        r##"<?xml version='1.0' encoding='UTF-8' standalone='yes' ?><msg serviceID="35" templateID="1" action="viewMultiMsg" brief="[聊天记录]" m_resid="" m_fileName="{}" tSum="{}" sourceMsgId="0" url="" flag="3" adverSign="0" multiMsgFlag="0"><item layout="1" advertiser_id="0" aid="0"><title size="34" maxLines="2" lineSpace="12">群聊的聊天记录</title>{}<hr hidden="false" style="0" /><summary size="26" color="#777777">查看{}条转发消息</summary></item><source name="聊天记录" icon="" action="" appid="-1" /></msg>"##,
-                                        packed_message.filename, t_sum, preview, t_sum
+                                    template1: super::gen_forward_template(
+                                        "",
+                                        &packed_message.filename,
+                                        target.forward_title(),
+                                        t_sum,
+                                        &preview,
                                     ),
                                     service_id: 35,
-                                }.into()
+                                }
+                                .into(),
                             ),
                         },
-                        group_code,
+                        target,
                     )
                 }
             })
@@ -133,24 +183,37 @@ impl super::super::super::Engine {
         }
     }
 
-    fn pack_msg(&self, node: super::MessageNode, group_code: i64) -> pb::msg::Message {
-        pb::msg::Message {
-            head: Some(pb::msg::MessageHead {
-                from_uin: Some(node.sender_id),
+    fn pack_msg(&self, node: super::MessageNode, target: MultiMsgTarget) -> pb::msg::Message {
+        let head = match target {
+            MultiMsgTarget::Group(group_code) => pb::msg::MessageHead {
                 msg_type: Some(82), // troop
                 msg_seq: Some(self.next_group_seq()),
-                msg_time: Some(node.time),
-                msg_uid: Some(0x01000000000000000 | rand::random::<u16>() as i64), // TODO ?
                 group_info: Some(pb::msg::GroupInfo {
                     group_code: Some(group_code),
                     group_card: Some(node.sender_name.into_bytes()),
                     ..Default::default()
                 }),
+                ..Default::default()
+            },
+            MultiMsgTarget::Friend(uin) => pb::msg::MessageHead {
+                to_uin: Some(uin),
+                msg_type: Some(9), // c2c
+                c2c_cmd: Some(11),
+                msg_seq: Some(self.next_friend_seq()),
+                from_nick: Some(node.sender_name),
+                ..Default::default()
+            },
+        };
+        pb::msg::Message {
+            head: Some(pb::msg::MessageHead {
+                from_uin: Some(node.sender_id),
+                msg_time: Some(node.time),
+                msg_uid: Some(0x01000000000000000 | rand::random::<u16>() as i64), // TODO ?
                 mutiltrans_head: Some(pb::msg::MutilTransHead {
                     status: Some(0),
                     msg_id: Some(1),
                 }),
-                ..Default::default()
+                ..head
             }),
             body: Some(pb::msg::MessageBody {
                 rich_text: Some(pb::msg::RichText {
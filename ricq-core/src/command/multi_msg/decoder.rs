@@ -1,5 +1,9 @@
-use bytes::Bytes;
+use std::io::Read;
 
+use bytes::{Buf, Bytes};
+use flate2::read::GzDecoder;
+
+use crate::crypto::qqtea_decrypt;
 use crate::{pb, RQError, RQResult};
 use prost::Message;
 
@@ -23,4 +27,32 @@ impl super::super::super::Engine {
             .pop()
             .ok_or(RQError::EmptyField("multimsg_applyup_rsp"))
     }
+
+    /// 解析 MultiMsg.ApplyDown 返回地址下载的数据
+    pub fn decode_multi_msg_down_data(
+        &self,
+        mut data: Bytes,
+        msg_key: &[u8],
+    ) -> RQResult<pb::msg::PbMultiMsgTransmit> {
+        if data.remaining() < 9 || data.get_u8() != 40 {
+            return Err(RQError::Decode("invalid multi msg data".into()));
+        }
+        let head_len = data.get_i32().max(0) as usize;
+        let body_len = data.get_i32().max(0) as usize;
+        if data.remaining() < head_len + body_len {
+            return Err(RQError::Decode("multi msg data too short".into()));
+        }
+        data.advance(head_len);
+        let body = qqtea_decrypt(&data[..body_len], msg_key)?;
+        let content = pb::longmsg::LongRspBody::decode(&*body)?
+            .msg_down_rsp
+            .pop()
+            .ok_or(RQError::EmptyField("msg_down_rsp"))?
+            .msg_content;
+        let mut uncompressed = Vec::new();
+        GzDecoder::new(&*content)
+            .read_to_end(&mut uncompressed)
+            .map_err(|e| RQError::Decode(format!("failed to uncompress multi msg: {e}")))?;
+        Ok(pb::msg::PbMultiMsgTransmit::decode(&*uncompressed)?)
+    }
 }
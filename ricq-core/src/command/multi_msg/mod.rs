@@ -1,31 +1,132 @@
 use std::collections::HashMap;
-use std::fmt::Write;
 
-use crate::msg::MessageChain;
+use crate::common::group_code2uin;
+use crate::msg::elem::{xml_escape, RQElem, RichMsg};
+use crate::msg::{MessageChain, FORWARD_PLACEHOLDER};
 use crate::pb;
 
 pub mod builder;
 pub mod decoder;
 
+#[derive(Debug, Clone)]
 pub enum ForwardMessage {
     Message(MessageNode),
     Forward(ForwardNode),
 }
 
+/// 转发消息、长消息的发送对象
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultiMsgTarget {
+    /// 群号
+    Group(i64),
+    /// 好友 QQ 号
+    Friend(i64),
+}
+
+impl MultiMsgTarget {
+    pub fn dst_uin(self) -> i64 {
+        match self {
+            Self::Group(group_code) => group_code2uin(group_code),
+            Self::Friend(uin) => uin,
+        }
+    }
+
+    /// 3 群，1 私聊
+    pub fn msg_type(self) -> i32 {
+        match self {
+            Self::Group(_) => 3,
+            Self::Friend(_) => 1,
+        }
+    }
+
+    /// 转发卡片的标题
+    pub fn forward_title(self) -> &'static str {
+        match self {
+            Self::Group(_) => "群聊的聊天记录",
+            Self::Friend(_) => "聊天记录",
+        }
+    }
+}
+
+/// 转发消息卡片，preview 由 [`gen_forward_preview`] 生成
+///
+/// 嵌套在转发消息中时 res_id 为空，用 file_name 查找
+pub fn gen_forward_template(
+    res_id: &str,
+    file_name: &str,
+    title: &str,
+    t_sum: usize,
+    preview: &str,
+) -> String {
+    let (res_id, file_name, title) = (xml_escape(res_id), xml_escape(file_name), xml_escape(title));
+    format!(
+        r##"<?xml version='1.0' encoding='UTF-8' standalone='yes' ?><msg serviceID="35" templateID="1" action="viewMultiMsg" brief="[聊天记录]" m_resid="{res_id}" m_fileName="{file_name}" tSum="{t_sum}" sourceMsgId="0" url="" flag="3" adverSign="0" multiMsgFlag="0"><item layout="1" advertiser_id="0" aid="0"><title size="34" maxLines="2" lineSpace="12">{title}</title>{preview}<hr hidden="false" style="0" /><summary size="26" color="#777777">查看{t_sum}条转发消息</summary></item><source name="聊天记录" icon="" action="" appid="-1" /></msg>"##
+    )
+}
+
+/// 长消息卡片
+pub fn gen_long_message_template(res_id: &str, file_name: &str, brief: &str) -> String {
+    let (res_id, file_name, brief) = (xml_escape(res_id), xml_escape(file_name), xml_escape(brief));
+    format!(
+        r##"<?xml version='1.0' encoding='UTF-8' standalone='yes' ?><msg serviceID="35" templateID="1" action="viewMultiMsg" brief="{brief}" m_resid="{res_id}" m_fileName="{file_name}" sourceMsgId="0" url="" flag="3" adverSign="0" multiMsgFlag="1"><item layout="1"><title>{brief}</title><hr hidden="false" style="0" /><summary>点击查看完整消息</summary></item><source name="聊天记录" icon="" action="" appid="-1" /></msg>"##
+    )
+}
+
+/// 发送转发消息用的消息链
+pub fn forward_message_chain(template: String) -> MessageChain {
+    let mut chain = MessageChain::default();
+    chain.push(RichMsg {
+        service_id: 35,
+        template1: template,
+    });
+    chain
+        .0
+        .push(pb::msg::elem::Elem::GeneralFlags(pb::msg::GeneralFlags {
+            pendant_id: Some(0),
+            pb_reserve: Some(vec![0x78, 0x00, 0xF8, 0x01, 0x00, 0xC8, 0x02, 0x00]),
+            ..Default::default()
+        }));
+    chain
+}
+
+/// 发送长消息用的消息链
+pub fn long_message_chain(template: String, res_id: String) -> MessageChain {
+    let mut chain = MessageChain::default();
+    chain.push(RichMsg {
+        service_id: 35,
+        template1: template,
+    });
+    chain.0.extend(vec![
+        pb::msg::elem::Elem::Text(pb::msg::Text {
+            str: Some(FORWARD_PLACEHOLDER.into()),
+            ..Default::default()
+        }),
+        pb::msg::elem::Elem::GeneralFlags(pb::msg::GeneralFlags {
+            long_text_flag: Some(1),
+            long_text_resid: Some(res_id),
+            pendant_id: Some(0),
+            pb_reserve: Some(vec![0x78, 0x00, 0xF8, 0x01, 0x00, 0xC8, 0x02, 0x00]), // TODO 15=73255?
+            ..Default::default()
+        }),
+    ]);
+    chain
+}
+
 pub fn gen_forward_preview(messages: &[ForwardMessage]) -> String {
     let mut ret = String::new();
     for msg in messages.iter().take(4) {
         ret.push_str(r##"<title size="26" color="#777777" maxLines="4" lineSpace="12">"##);
-        match msg {
-            ForwardMessage::Message(v) => write!(&mut ret, "{}: {}", v.sender_name, v.elements),
-            ForwardMessage::Forward(v) => write!(&mut ret, "{}: [转发消息]", v.sender_name),
-        }
-        .unwrap();
+        let line = match msg {
+            ForwardMessage::Message(v) => format!("{}: {}", v.sender_name, v.elements),
+            ForwardMessage::Forward(v) => format!("{}: [转发消息]", v.sender_name),
+        };
+        ret.push_str(&xml_escape(&line));
         ret.push_str("</title>");
     }
     ret
 }
 
+#[derive(Debug, Clone)]
 pub struct MessageNode {
     pub sender_id: i64,
     pub time: i32,
@@ -39,6 +140,7 @@ impl From<MessageNode> for ForwardMessage {
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct ForwardNode {
     pub sender_id: i64,
     pub time: i32,
@@ -52,7 +154,184 @@ impl From<ForwardNode> for ForwardMessage {
     }
 }
 
+/// 嵌套转发的最大层数
+const MAX_FORWARD_DEPTH: usize = 16;
+
+/// 将下载的 PbMultiMsgTransmit 转换为 ForwardMessage
+///
+/// 嵌套转发从 `pb_item_list` 中按 m_fileName 查找，找不到时保留为带 `RQElem::Forward` 的 MessageNode
+pub fn parse_multi_msg_transmit(transmit: pb::msg::PbMultiMsgTransmit) -> Vec<ForwardMessage> {
+    let items: HashMap<String, Vec<pb::msg::Message>> = transmit
+        .pb_item_list
+        .into_iter()
+        .filter_map(|item| Some((item.file_name?, item.buffer?.msg)))
+        .collect();
+    let msgs = if transmit.msg.is_empty() {
+        items.get("MultiMsg").cloned().unwrap_or_default()
+    } else {
+        transmit.msg
+    };
+    parse_multi_msgs(msgs, &items, 0)
+}
+
+fn parse_multi_msgs(
+    msgs: Vec<pb::msg::Message>,
+    items: &HashMap<String, Vec<pb::msg::Message>>,
+    depth: usize,
+) -> Vec<ForwardMessage> {
+    msgs.into_iter()
+        .map(|msg| {
+            let head = msg.head.unwrap_or_default();
+            let sender_name = head
+                .group_info
+                .and_then(|info| info.group_card)
+                .map(|card| String::from_utf8_lossy(&card).into_owned())
+                .filter(|name| !name.is_empty())
+                .or(head.from_nick)
+                .unwrap_or_default();
+            let elements = MessageChain::from(
+                msg.body
+                    .and_then(|body| body.rich_text)
+                    .map(|rich_text| rich_text.elems)
+                    .unwrap_or_default(),
+            );
+            let nested = elements.clone().into_iter().find_map(|e| match e {
+                RQElem::Forward(f) if !f.is_long && depth < MAX_FORWARD_DEPTH => {
+                    items.get(&f.file_name).cloned()
+                }
+                _ => None,
+            });
+            match nested {
+                Some(nested) => ForwardNode {
+                    sender_id: head.from_uin.unwrap_or_default(),
+                    time: head.msg_time.unwrap_or_default(),
+                    sender_name,
+                    nodes: parse_multi_msgs(nested, items, depth + 1),
+                }
+                .into(),
+                None => MessageNode {
+                    sender_id: head.from_uin.unwrap_or_default(),
+                    time: head.msg_time.unwrap_or_default(),
+                    sender_name,
+                    elements,
+                }
+                .into(),
+            }
+        })
+        .collect()
+}
+
 struct PackedMessage {
     pub filename: String,
     pub buffer: HashMap<String, Vec<pb::msg::Message>>,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use bytes::{BufMut, Bytes, BytesMut};
+    use prost::Message;
+
+    use super::*;
+    use crate::crypto::qqtea_encrypt;
+    use crate::msg::elem::Text;
+    use crate::protocol::device::Device;
+    use crate::protocol::version::Protocol;
+    use crate::Engine;
+
+    fn node(sender_id: i64, text: &str) -> ForwardMessage {
+        MessageNode {
+            sender_id,
+            time: 1,
+            sender_name: format!("user{sender_id}"),
+            elements: MessageChain::new(Text::new(text.into())),
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_decode_nested_forward() {
+        let engine = Engine::new(Device::random(), Protocol::IPad.into());
+        let msgs = vec![
+            node(1, "hello"),
+            ForwardNode {
+                sender_id: 2,
+                time: 2,
+                sender_name: "user2".into(),
+                nodes: vec![node(3, "nested")],
+            }
+            .into(),
+        ];
+        let content = engine.calculate_validation_data(msgs, 100);
+        let key = [7u8; 16];
+        let body = qqtea_encrypt(
+            &pb::longmsg::LongRspBody {
+                msg_down_rsp: vec![pb::longmsg::LongMsgDownRsp {
+                    msg_content: content,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }
+            .encode_to_vec(),
+            &key,
+        );
+        let mut data = BytesMut::new();
+        data.put_u8(40);
+        data.put_i32(2);
+        data.put_i32(body.len() as i32);
+        data.put_slice(&[0, 0]);
+        data.put_slice(&body);
+        data.put_u8(41);
+
+        let transmit = engine
+            .decode_multi_msg_down_data(data.freeze(), &key)
+            .unwrap();
+        let msgs = parse_multi_msg_transmit(transmit);
+        assert_eq!(msgs.len(), 2);
+        assert!(matches!(&msgs[0], ForwardMessage::Message(m) if m.sender_name == "user1"));
+        let ForwardMessage::Forward(forward) = &msgs[1] else {
+            panic!("expect forward node")
+        };
+        assert_eq!(forward.sender_id, 2);
+        assert!(
+            matches!(&forward.nodes[..], [ForwardMessage::Message(m)] if m.elements.to_string().trim() == "nested")
+        );
+        assert!(engine
+            .decode_multi_msg_down_data(Bytes::from_static(&[0; 3]), &key)
+            .is_err());
+    }
+
+    #[test]
+    fn test_friend_multi_msg() {
+        let engine = Engine::new(Device::random(), Protocol::IPad.into());
+        let msgs = vec![node(1, "hello")];
+        let preview = gen_forward_preview(&msgs);
+        let data = engine.calculate_multi_msg_data(msgs, MultiMsgTarget::Friend(10));
+        let mut transmit = Vec::new();
+        flate2::read::GzDecoder::new(&data[..])
+            .read_to_end(&mut transmit)
+            .unwrap();
+        let transmit = pb::msg::PbMultiMsgTransmit::decode(&transmit[..]).unwrap();
+        let head = transmit.msg[0].head.clone().unwrap();
+        assert_eq!(head.to_uin, Some(10));
+        assert!(head.group_info.is_none());
+        let msgs = parse_multi_msg_transmit(transmit);
+        assert!(matches!(&msgs[0], ForwardMessage::Message(m) if m.sender_name == "user1"));
+
+        let template = gen_forward_template("res", "file", "<聊天记录> & \"x\"", 1, &preview);
+        let forward = crate::msg::elem::Forward::from_rich_msg(&RichMsg {
+            service_id: 35,
+            template1: template,
+        })
+        .unwrap();
+        assert_eq!(forward.res_id, "res");
+        assert_eq!(forward.title, "<聊天记录> & \"x\"");
+        assert!(forward.preview[0].starts_with("user1: "));
+        assert_eq!(forward.t_sum, 1);
+        assert!(!forward.is_long);
+
+        let template = gen_long_message_template("res", "file", "<a & \"b\">");
+        assert!(template.contains(r#"brief="&lt;a &amp; &quot;b&quot;&gt;""#));
+    }
+}
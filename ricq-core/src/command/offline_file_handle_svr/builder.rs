@@ -0,0 +1,78 @@
+use crate::command::common::PbToBytes;
+use crate::highway::FileHash;
+use crate::pb;
+use crate::protocol::packet::Packet;
+
+impl super::super::super::Engine {
+    // OfflineFilleHandleSvr.pb_ftn_CMD_REQ_APPLY_UPLOAD_V3-1700
+    pub fn build_offline_file_upload_packet(
+        &self,
+        target: i64,
+        file_name: String,
+        hash: &FileHash,
+    ) -> Packet {
+        let req = pb::cmd0x346::C346ReqBody {
+            cmd: 1700,
+            seq: self.next_seq() as i32,
+            business_id: 3,
+            client_type: 104,
+            apply_upload_req_v3: Some(pb::cmd0x346::ApplyUploadReqV3 {
+                sender_uin: self.uin(),
+                recver_uin: target,
+                file_size: hash.size,
+                local_filepath: format!("/storage/emulated/0/Download/{}", file_name),
+                file_name,
+                bytes_10m_md5: hash.md5_10m.clone(),
+                sha: hash.sha1.clone(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        self.uni_packet(
+            "OfflineFilleHandleSvr.pb_ftn_CMD_REQ_APPLY_UPLOAD_V3-1700",
+            req.to_bytes(),
+        )
+    }
+
+    // OfflineFilleHandleSvr.pb_ftn_CMD_REQ_UPLOAD_SUCC-800
+    pub fn build_offline_file_upload_succ_packet(&self, target: i64, uuid: Vec<u8>) -> Packet {
+        let req = pb::cmd0x346::C346ReqBody {
+            cmd: 800,
+            seq: self.next_seq() as i32,
+            business_id: 3,
+            client_type: 104,
+            upload_succ_req: Some(pb::cmd0x346::UploadSuccReq {
+                sender_uin: self.uin(),
+                recver_uin: target,
+                uuid,
+            }),
+            ..Default::default()
+        };
+        self.uni_packet(
+            "OfflineFilleHandleSvr.pb_ftn_CMD_REQ_UPLOAD_SUCC-800",
+            req.to_bytes(),
+        )
+    }
+
+    // OfflineFilleHandleSvr.pb_ftn_CMD_REQ_APPLY_DOWNLOAD-1200
+    pub fn build_offline_file_download_packet(&self, sender_uin: i64, uuid: Vec<u8>) -> Packet {
+        let req = pb::cmd0x346::C346ReqBody {
+            cmd: 1200,
+            seq: self.next_seq() as i32,
+            business_id: 3,
+            client_type: 104,
+            apply_download_req: Some(pb::cmd0x346::ApplyDownloadReq {
+                uin: sender_uin,
+                uuid,
+                owner_type: 2,
+                need_https_url: 1,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        self.uni_packet(
+            "OfflineFilleHandleSvr.pb_ftn_CMD_REQ_APPLY_DOWNLOAD-1200",
+            req.to_bytes(),
+        )
+    }
+}
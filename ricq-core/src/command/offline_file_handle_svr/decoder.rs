@@ -0,0 +1,53 @@
+use bytes::Bytes;
+use prost::Message;
+
+use crate::{pb, RQError, RQResult};
+
+impl super::super::super::Engine {
+    // OfflineFilleHandleSvr.pb_ftn_CMD_REQ_APPLY_UPLOAD_V3-1700
+    pub fn decode_offline_file_upload_response(
+        &self,
+        payload: Bytes,
+    ) -> RQResult<pb::cmd0x346::ApplyUploadRspV3> {
+        let rsp = pb::cmd0x346::C346RspBody::decode(&*payload)?
+            .apply_upload_rsp_v3
+            .ok_or(RQError::EmptyField("apply_upload_rsp_v3"))?;
+        if rsp.ret_code != 0 {
+            return Err(RQError::Other(format!(
+                "upload offline file failed: {} {}",
+                rsp.ret_code, rsp.ret_msg
+            )));
+        }
+        Ok(rsp)
+    }
+
+    // OfflineFilleHandleSvr.pb_ftn_CMD_REQ_UPLOAD_SUCC-800
+    pub fn decode_offline_file_upload_succ_response(&self, payload: Bytes) -> RQResult<()> {
+        let rsp = pb::cmd0x346::C346RspBody::decode(&*payload)?
+            .upload_succ_rsp
+            .ok_or(RQError::EmptyField("upload_succ_rsp"))?;
+        if rsp.ret_code != 0 {
+            return Err(RQError::Other(format!(
+                "upload offline file failed: {} {}",
+                rsp.ret_code, rsp.ret_msg
+            )));
+        }
+        Ok(())
+    }
+
+    // OfflineFilleHandleSvr.pb_ftn_CMD_REQ_APPLY_DOWNLOAD-1200
+    pub fn decode_offline_file_download_response(&self, payload: Bytes) -> RQResult<String> {
+        let rsp = pb::cmd0x346::C346RspBody::decode(&*payload)?
+            .apply_download_rsp
+            .ok_or(RQError::EmptyField("apply_download_rsp"))?;
+        if rsp.ret_code != 0 {
+            return Err(RQError::Other(format!(
+                "get offline file download failed: {} {}",
+                rsp.ret_code, rsp.ret_msg
+            )));
+        }
+        rsp.download_info
+            .ok_or(RQError::EmptyField("download_info"))
+            .map(|info| info.download_url)
+    }
+}
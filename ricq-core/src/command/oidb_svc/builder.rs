@@ -2,6 +2,7 @@ use bytes::{BufMut, BytesMut};
 
 use super::*;
 use crate::command::common::PbToBytes;
+use crate::highway::FileHash;
 use crate::pb;
 use crate::protocol::packet::Packet;
 
@@ -469,6 +470,62 @@ impl super::super::super::Engine {
         let payload = self.transport.encode_oidb_packet(1750, 2, body.to_bytes());
         self.uni_packet("OidbSvc.0x6d6_2", payload)
     }
+    // OidbSvc.0x6d6_0
+    pub fn build_group_file_upload_request_packet(
+        &self,
+        group_code: i64,
+        parent_folder_id: String,
+        file_name: String,
+        hash: &FileHash,
+    ) -> Packet {
+        let body = pb::oidb::D6d6ReqBody {
+            upload_file_req: Some(pb::oidb::UploadFileReqBody {
+                group_code: Some(group_code),
+                app_id: Some(3),
+                bus_id: Some(102),
+                entrance: Some(5),
+                parent_folder_id: Some(parent_folder_id),
+                local_path: Some(format!(
+                    "/storage/emulated/0/Android/data/com.tencent.mobileqq/Tencent/QQfile_recv/{}",
+                    file_name
+                )),
+                file_name: Some(file_name),
+                int64_file_size: Some(hash.size),
+                sha: Some(hash.sha1.clone()),
+                md5: Some(hash.md5.clone()),
+                support_multi_upload: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let payload = self.transport.encode_oidb_packet(1750, 0, body.to_bytes());
+        self.uni_packet("OidbSvc.0x6d6_0", payload)
+    }
+    // OidbSvc.0x6d9_4
+    pub fn build_group_file_feed_request_packet(
+        &self,
+        group_code: u64,
+        file_id: String,
+        bus_id: u32,
+        msg_random: u32,
+    ) -> Packet {
+        let body = pb::oidb::D6d9ReqBody {
+            feeds_info_req: Some(pb::oidb::FeedsReqBody {
+                group_code: Some(group_code),
+                app_id: Some(3),
+                feeds_info_list: vec![pb::oidb::GroupFileFeedsInfo {
+                    bus_id: Some(bus_id),
+                    file_id: Some(file_id),
+                    msg_random: Some(msg_random),
+                    feed_flag: Some(1),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+        };
+        let payload = self.transport.encode_oidb_packet(1753, 4, body.to_bytes());
+        self.uni_packet("OidbSvc.0x6d9_4", payload)
+    }
     // OidbSvc.0x6d8_1
     pub fn build_group_file_count_request_packet(&self, group_code: u64) -> Packet {
         let body = pb::oidb::D6d8ReqBody {
@@ -7,7 +7,7 @@ use crate::structs::{
     GroupFileCount, GroupFileInfo, GroupFileItem, GroupFileList, GroupFolderInfo, GroupInfo,
     GroupMemberPermission,
 };
-use crate::{pb, RQResult};
+use crate::{pb, RQError, RQResult};
 use prost::Message;
 
 use super::OcrResponse;
@@ -169,7 +169,9 @@ impl super::super::super::Engine {
     ) -> RQResult<String> {
         let pkg = pb::oidb::OidbssoPkg::decode(&*payload)?;
         let resp = pb::oidb::D6d6RspBody::decode(&*pkg.bodybuffer)?;
-        let f_rsp = resp.download_file_rsp.unwrap();
+        let f_rsp = resp
+            .download_file_rsp
+            .ok_or(RQError::EmptyField("download_file_rsp"))?;
         Ok(format!(
             "http://{}/ftn_handler/{:x}/?fname={}",
             f_rsp.download_ip(),
@@ -177,6 +179,39 @@ impl super::super::super::Engine {
             filename
         ))
     }
+    // OidbSvc.0x6d6_0
+    pub fn decode_group_file_upload_response(
+        &self,
+        payload: Bytes,
+    ) -> RQResult<pb::oidb::UploadFileRspBody> {
+        let pkg = pb::oidb::OidbssoPkg::decode(&*payload)?;
+        let resp = pb::oidb::D6d6RspBody::decode(&*pkg.bodybuffer)?
+            .upload_file_rsp
+            .ok_or(RQError::EmptyField("upload_file_rsp"))?;
+        if resp.ret_code() != 0 {
+            return Err(RQError::Other(format!(
+                "upload group file failed: {} {}",
+                resp.ret_code(),
+                resp.client_wording()
+            )));
+        }
+        Ok(resp)
+    }
+    // OidbSvc.0x6d9_4
+    pub fn decode_group_file_feed_response(&self, payload: Bytes) -> RQResult<()> {
+        let pkg = pb::oidb::OidbssoPkg::decode(&*payload)?;
+        let resp = pb::oidb::D6d9RspBody::decode(&*pkg.bodybuffer)?
+            .feeds_info_rsp
+            .ok_or(RQError::EmptyField("feeds_info_rsp"))?;
+        if resp.ret_code() != 0 {
+            return Err(RQError::Other(format!(
+                "send group file feed failed: {} {}",
+                resp.ret_code(),
+                resp.client_wording()
+            )));
+        }
+        Ok(())
+    }
     // OidbSvc.0x6d8_1
     pub fn decode_group_file_count_response(&self, payload: Bytes) -> RQResult<GroupFileCount> {
         let pkg = pb::oidb::OidbssoPkg::decode(&*payload)?;
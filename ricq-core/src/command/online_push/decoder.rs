@@ -1,7 +1,8 @@
-use bytes::{Buf, Bytes};
+use bytes::Bytes;
 use jcers::Jce;
 
 use super::*;
+use crate::binary::BinaryReader;
 use crate::common::group_uin2code;
 use crate::structs::{GroupDisband, GroupLeave, GroupMemberPermission, MemberPermissionChange};
 use crate::{jce, pb, RQError, RQResult};
@@ -54,7 +55,7 @@ impl super::super::super::Engine {
         let mut msg = req
             .remove("OnlinePushPack.SvcReqPushMsg")
             .ok_or_else(|| RQError::Decode("OnlinePushPack.SvcReqPushMsg is none".into()))?;
-        msg.advance(1);
+        msg.skip(1)?;
         let mut jr = Jce::new(&mut msg);
         let uin: i64 = jr.get_by_tag(0)?;
         let msg_infos: Vec<jce::PushMessageInfo> = jr.get_by_tag(2)?;
@@ -77,11 +78,11 @@ impl super::super::super::Engine {
         // 去重暂时不做
         match info.msg_type {
             Some(34) => {
-                data.get_i32();
-                data.get_u8();
-                let target = data.get_u32() as i64;
-                let typ = data.get_u8() as i32;
-                let operator = data.get_u32() as i64;
+                data.read_i32()?;
+                data.read_u8()?;
+                let target = data.read_u32()? as i64;
+                let typ = data.read_u8()? as i32;
+                let operator = data.read_u32()? as i64;
                 match typ {
                     0x01 | 0x81 => {
                         return Ok(OnlinePushTrans {
@@ -122,15 +123,15 @@ impl super::super::super::Engine {
                 }
             }
             Some(44) => {
-                data.advance(5);
-                let var4 = data.get_u8() as i32;
+                data.skip(5)?;
+                let var4 = data.read_u8()? as i32;
                 let mut var5: i64 = 0;
-                let target = data.get_u32() as i64;
+                let target = data.read_u32()? as i64;
                 if var4 != 0 && var4 != 1 {
-                    var5 = data.get_u32() as i64;
+                    var5 = data.read_u32()? as i64;
                 }
                 if var5 == 0 && data.len() == 1 {
-                    let new_permission = if data.get_u8() == 1 {
+                    let new_permission = if data.read_u8()? == 1 {
                         GroupMemberPermission::Administrator
                     } else {
                         GroupMemberPermission::Member
@@ -1,4 +1,9 @@
-use crate::structs::{GroupDisband, GroupLeave, MemberPermissionChange};
+use serde::Deserialize;
+
+use crate::structs::{
+    GroupDisband, GroupHonor, GroupLeave, MemberHonorChange, MemberPermissionChange,
+    MemberSpecialTitleUpdate,
+};
 use crate::{jce, pb};
 
 pub mod builder;
@@ -45,3 +50,123 @@ pub struct GroupMessagePart {
     // 分片id，相同id的应该合并，且根据pkg_index排序
     pub div_seq: i32,
 }
+
+/// 解析授予头衔的灰条提示（NotifyMsgBody.opt_msg_gray_tips）
+///
+/// content 形如 `<{"cmd":"5","data":"...","text":"群主"}> 授予 <{"cmd":"1","data":"12345","text":"昵称"}> “<{"cmd":"3","data":"...","text":"头衔"}>”头衔`
+pub fn parse_special_title_tip(group_code: i64, content: &str) -> Option<MemberSpecialTitleUpdate> {
+    if !content.contains("头衔") {
+        return None;
+    }
+    let mut member_uin = 0;
+    let mut new_title = None;
+    let mut rest = content;
+    while let Some(start) = rest.find("<{") {
+        rest = &rest[start + 1..];
+        let mut objs = serde_json::Deserializer::from_str(rest).into_iter::<GrayTipObject>();
+        let Some(Ok(obj)) = objs.next() else {
+            continue;
+        };
+        rest = &rest[objs.byte_offset()..];
+        match json_string(&obj.cmd).as_deref() {
+            Some("1") => {
+                member_uin = json_string(obj.data.as_ref()?)?.parse().ok()?;
+            }
+            Some("3") => new_title = obj.text,
+            _ => {}
+        }
+    }
+    if member_uin == 0 {
+        return None;
+    }
+    Some(MemberSpecialTitleUpdate {
+        group_code,
+        member_uin,
+        new_title: new_title?,
+    })
+}
+
+/// 解析群荣誉变更的灰条提示（NotifyMsgBody.opt_general_gray_tip）
+pub fn parse_honor_tip(
+    group_code: i64,
+    tip: &pb::notify::GeneralGrayTipInfo,
+) -> Option<MemberHonorChange> {
+    let honor = match tip.templ_id {
+        1052 => GroupHonor::Performer,
+        1053 | 1054 => GroupHonor::Talkative,
+        1067 => GroupHonor::Emotion,
+        _ => return None,
+    };
+    let mut member_uin = 0;
+    let mut nick = String::new();
+    for templ in &tip.msg_templ_param {
+        match &*templ.name {
+            "uin" => member_uin = templ.value.parse().unwrap_or_default(),
+            "nick" => nick = templ.value.clone(),
+            _ => {}
+        }
+    }
+    if member_uin == 0 {
+        return None;
+    }
+    Some(MemberHonorChange {
+        group_code,
+        member_uin,
+        nick,
+        honor,
+    })
+}
+
+// 灰条提示中的 json 对象
+#[derive(Deserialize)]
+struct GrayTipObject {
+    #[serde(default)]
+    cmd: serde_json::Value,
+    data: Option<serde_json::Value>,
+    text: Option<String>,
+}
+
+// 值可以是字符串或数字
+fn json_string(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_gray_tips() {
+        let content = r#"<{"cmd":"5","data":"10000","text":"群主"}> 授予 <{"cmd":1,"data":"12345","text":"nick"}> “<{"cmd":"3","data":"","text":"大\"佬\""}>”头衔"#;
+        let update = parse_special_title_tip(100, content).unwrap();
+        assert_eq!(update.member_uin, 12345);
+        assert_eq!(update.new_title, "大\"佬\"");
+        assert!(parse_special_title_tip(100, "<{\"cmd\":\"1\"}> 撤回了一条消息").is_none());
+        let content = r#"<{"cmd":1,"data":"12345","text":"a}>b"}> 获得 <{"cmd":"3","text":"\u5934\u8854"}>头衔"#;
+        let update = parse_special_title_tip(100, content).unwrap();
+        assert_eq!(update.member_uin, 12345);
+        assert_eq!(update.new_title, "头衔");
+
+        let tip = pb::notify::GeneralGrayTipInfo {
+            templ_id: 1053,
+            msg_templ_param: vec![
+                pb::notify::TemplParam {
+                    name: "uin".into(),
+                    value: "12345".into(),
+                },
+                pb::notify::TemplParam {
+                    name: "nick".into(),
+                    value: "nick".into(),
+                },
+            ],
+            ..Default::default()
+        };
+        let change = parse_honor_tip(100, &tip).unwrap();
+        assert_eq!(change.honor, GroupHonor::Talkative);
+        assert_eq!(change.member_uin, 12345);
+    }
+}
@@ -26,7 +26,7 @@ impl super::super::super::Engine {
                 src_term: Some(5),
                 platform_type: Some(9),
                 bu_type: Some(4),
-                build_ver: Some(self.transport.version.build_ver.into()),
+                build_ver: Some(self.transport.version.build_ver.as_bytes().to_vec()),
                 inner_ip: Some(0),
                 // TODO ?
                 voice_length: Some(voice_length),
@@ -88,7 +88,7 @@ impl super::super::super::Engine {
                 req_platform_type: Some(9),
                 inner_ip: Some(0),
                 bu_type: Some(4), // 3?
-                build_ver: Some(self.transport.version.build_ver.into()),
+                build_ver: Some(self.transport.version.build_ver.as_bytes().to_vec()),
                 codec: Some(0),
                 // 11=file_key, 14=2, 15=1 ?
                 ..Default::default()
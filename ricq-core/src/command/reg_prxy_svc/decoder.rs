@@ -1,5 +1,6 @@
-use bytes::{Buf, Bytes};
+use bytes::Bytes;
 
+use crate::binary::BinaryReader;
 use crate::structs::OtherClientInfo;
 use crate::{jce, RQError, RQResult};
 
@@ -20,7 +21,7 @@ impl super::super::super::Engine {
             .ok_or_else(|| {
                 RQError::Decode("RegisterProxySvcPack.SvcRespParam is none".to_string())
             })?;
-        reader.advance(1);
+        reader.skip(1)?;
         let rsp: jce::SvcRespParam = jcers::from_buf(&mut reader).map_err(RQError::from)?;
         Ok(rsp
             .online_infos
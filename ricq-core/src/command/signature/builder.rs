@@ -11,7 +11,7 @@ impl super::super::super::Engine {
             plf: Some(sig_act::Platform {
                 platform: Some(109),
                 osver: Some(self.transport.device.version.release.to_owned()),
-                mqqver: Some(self.transport.version.sort_version_name.into()),
+                mqqver: Some(self.transport.version.sort_version_name.to_string()),
             }),
             auth_req: Some(sig_act::SigauthReq {
                 uin_disable: Some(self.uin() as u64),
@@ -1,6 +1,7 @@
-use bytes::{Buf, Bytes};
+use bytes::Bytes;
 use jcers::Jce;
 
+use crate::binary::BinaryReader;
 use crate::{jce, RQError, RQResult};
 
 impl super::super::super::Engine {
@@ -20,7 +21,7 @@ impl super::super::super::Engine {
         let mut b = a
             .remove("QQService.SvcRespRegister")
             .ok_or_else(|| RQError::Decode("missing QQService.SvcRespRegister".into()))?;
-        b.advance(1);
+        b.skip(1)?;
         jcers::from_buf(&mut b).map_err(RQError::from)
     }
 
@@ -40,7 +41,7 @@ impl super::super::super::Engine {
         let mut msg = req
             .remove("QQService.SvcRspGetDevLoginInfo")
             .ok_or_else(|| RQError::Decode("missing QQService.SvcRspGetDevLoginInfo".into()))?;
-        msg.advance(1);
+        msg.skip(1)?;
         let mut rsp = Jce::new(&mut msg);
         let d: Vec<jce::SvcDevLoginInfo> = rsp.get_by_tag(4).map_err(RQError::from)?;
         if !d.is_empty() {
@@ -74,4 +75,21 @@ impl super::super::super::Engine {
             .ok_or_else(|| RQError::Decode("missing QQService.RequestMSFForceOffline".into()))?;
         jcers::from_buf(&mut data).map_err(RQError::from)
     }
+
+    // StatSvc.SvcReqMSFLoginNotify
+    pub fn decode_msf_login_notify(
+        &self,
+        mut payload: Bytes,
+    ) -> RQResult<jce::SvcReqMSFLoginNotify> {
+        let mut request: jce::RequestPacket = jcers::from_buf(&mut payload)?;
+        let mut data: jce::RequestDataVersion2 = jcers::from_buf(&mut request.s_buffer)?;
+        let mut data = data
+            .map
+            .remove("SvcReqMSFLoginNotify")
+            .ok_or_else(|| RQError::Decode("missing SvcReqMSFLoginNotify".into()))?
+            .remove("QQService.SvcReqMSFLoginNotify")
+            .ok_or_else(|| RQError::Decode("missing QQService.SvcReqMSFLoginNotify".into()))?;
+        data.skip(1)?;
+        jcers::from_buf(&mut data).map_err(RQError::from)
+    }
 }
@@ -50,7 +50,7 @@ impl super::super::super::Engine {
                 touin: Some(target),
                 service: Some(16),
                 platform: Some(2),
-                qqver: Some(self.transport.version.build_ver.into()),
+                qqver: Some(self.transport.version.build_ver.to_string()),
                 build: Some(4945),
                 ..Default::default()
             }
@@ -1,4 +1,4 @@
-use bytes::{BufMut, BytesMut};
+use bytes::{BufMut, Bytes, BytesMut};
 
 use crate::binary::BinaryWriter;
 use crate::command::wtlogin::builder::utils::*;
@@ -10,6 +10,16 @@ use crate::protocol::{
 };
 
 impl super::super::super::Engine {
+    /// tlv 0x544 签名使用的 salt，sub_command 为 wtlogin.login 的子命令
+    pub fn t544_salt(&self, sub_command: u32) -> Bytes {
+        let mut w = BytesMut::new();
+        w.put_u64(self.uin() as u64);
+        w.write_bytes_short(&self.transport.sig.guid);
+        w.write_bytes_short(self.transport.version.sdk_version.as_bytes());
+        w.put_u32(sub_command);
+        w.freeze()
+    }
+
     // wtlogin.trans_emp
     pub fn build_qrcode_fetch_request_packet(&self) -> Packet {
         let transport = &self.transport;
@@ -30,9 +40,9 @@ impl super::super::super::Engine {
                     16, // app id ?
                     transport.version.sub_app_id,
                     &transport.sig.guid,
-                    transport.version.apk_id,
-                    transport.version.sort_version_name,
-                    transport.version.apk_sign,
+                    &transport.version.apk_id,
+                    &transport.version.sort_version_name,
+                    &transport.version.apk_sign,
                 ));
                 w.put_slice(&t1b(0, 0, 3, 4, 72, 2, 2));
                 w.put_slice(&t1d(transport.version.misc_bitmap));
@@ -120,13 +130,19 @@ impl super::super::super::Engine {
     }
 
     // wtlogin.login
-    pub fn build_qrcode_login_packet(&self, t106: &[u8], t16a: &[u8], t318: &[u8]) -> Packet {
+    pub fn build_qrcode_login_packet(
+        &self,
+        t106: &[u8],
+        t16a: &[u8],
+        t318: &[u8],
+        t544_energy: Option<&[u8]>,
+    ) -> Packet {
         let seq = self.next_seq();
         let transport = &self.transport;
         let req = self.build_oicq_request_packet(self.uin(), 0x0810, &{
             let mut w = BytesMut::new();
             w.put_u16(9);
-            w.put_u16(24);
+            w.put_u16(if t544_energy.is_some() { 25 } else { 24 });
 
             w.put_slice(&t18(16, self.uin() as u32));
             w.put_slice(&t1(self.uin() as u32, &transport.device.ip_address));
@@ -146,7 +162,7 @@ impl super::super::super::Engine {
                 transport.version.main_sig_map,
             ));
             w.put_slice(&t107(0));
-            w.put_slice(&t142(transport.version.apk_id));
+            w.put_slice(&t142(&transport.version.apk_id));
             w.put_slice(&t144(
                 &transport.device.imei,
                 &transport.device.gen_pb_data(),
@@ -167,8 +183,8 @@ impl super::super::super::Engine {
             w.put_slice(&t145(&transport.sig.guid));
             w.put_slice(&t147(
                 16,
-                transport.version.sort_version_name,
-                transport.version.apk_sign,
+                &transport.version.sort_version_name,
+                &transport.version.apk_sign,
             ));
             w.put_slice(&{
                 let mut w = Vec::new();
@@ -209,7 +225,7 @@ impl super::super::super::Engine {
             }
             w.put_slice(&t177(
                 transport.version.build_time,
-                transport.version.sdk_version,
+                &transport.version.sdk_version,
             ));
             w.put_slice(&t516());
             w.put_slice(&t521(8));
@@ -221,6 +237,9 @@ impl super::super::super::Engine {
                 w.write_bytes_short(t318);
                 w
             });
+            if let Some(energy) = t544_energy {
+                w.put_slice(&t544(energy));
+            }
             w
         });
         Packet {
@@ -325,13 +344,13 @@ impl super::super::super::Engine {
     }
 
     // wtlogin.login
-    pub fn build_sms_code_submit_packet(&self, code: &str) -> Packet {
+    pub fn build_sms_code_submit_packet(&self, code: &str, t544_energy: Option<&[u8]>) -> Packet {
         let seq = self.next_seq();
         let transport = &self.transport;
         let req = self.build_oicq_request_packet(self.uin(), 0x810, &{
             let mut w = BytesMut::new();
             w.put_u16(7);
-            w.put_u16(7);
+            w.put_u16(if t544_energy.is_some() { 8 } else { 7 });
 
             w.put_slice(&t8(2052));
             w.put_slice(&t104(&transport.sig.t104));
@@ -343,6 +362,9 @@ impl super::super::super::Engine {
             w.put_slice(&t17c(code));
             w.put_slice(&t401(&transport.sig.g));
             w.put_slice(&t198());
+            if let Some(energy) = t544_energy {
+                w.put_slice(&t544(energy));
+            }
             w
         });
         Packet {
@@ -357,13 +379,13 @@ impl super::super::super::Engine {
     }
 
     // wtlogin.login
-    pub fn build_ticket_submit_packet(&self, ticket: &str) -> Packet {
+    pub fn build_ticket_submit_packet(&self, ticket: &str, t544_energy: Option<&[u8]>) -> Packet {
         let seq = self.next_seq();
         let transport = &self.transport;
         let req = self.build_oicq_request_packet(self.uin(), 0x810, &{
             let mut w = BytesMut::new();
             w.put_u16(2);
-            w.put_u16(4);
+            w.put_u16(if t544_energy.is_some() { 5 } else { 4 });
 
             w.put_slice(&t193(ticket));
             w.put_slice(&t8(2052));
@@ -372,6 +394,9 @@ impl super::super::super::Engine {
                 transport.version.misc_bitmap,
                 transport.version.sub_sig_map,
             ));
+            if let Some(energy) = t544_energy {
+                w.put_slice(&t544(energy));
+            }
             w
         });
         Packet {
@@ -429,7 +454,7 @@ impl super::super::super::Engine {
                 &transport.device.brand,
                 &transport.sig.tgtgt_key,
             ));
-            w.put_slice(&t142(transport.version.apk_id));
+            w.put_slice(&t142(&transport.version.apk_id));
             w.put_slice(&t145(&transport.sig.guid));
             w.put_slice(&t16a(&transport.sig.srm_token));
             w.put_slice(&t141(&transport.device.sim_info, &transport.device.apn));
@@ -452,12 +477,12 @@ impl super::super::super::Engine {
             ]));
             w.put_slice(&t147(
                 16,
-                transport.version.sort_version_name,
-                transport.version.apk_sign,
+                &transport.version.sort_version_name,
+                &transport.version.apk_sign,
             ));
             w.put_slice(&t177(
                 transport.version.build_time,
-                transport.version.sdk_version,
+                &transport.version.sdk_version,
             ));
             w.put_slice(&t400(
                 &transport.sig.g,
@@ -535,19 +560,19 @@ impl super::super::super::Engine {
                 &h,
             ));
             w.put_slice(&t143(&transport.sig.d2));
-            w.put_slice(&t142(transport.version.apk_id));
+            w.put_slice(&t142(&transport.version.apk_id));
             w.put_slice(&t154(seq));
             w.put_slice(&t18(16, self.uin() as u32));
             w.put_slice(&t141(&transport.device.sim_info, &transport.device.apn));
             w.put_slice(&t8(2052));
             w.put_slice(&t147(
                 16,
-                transport.version.sort_version_name,
-                transport.version.apk_sign,
+                &transport.version.sort_version_name,
+                &transport.version.apk_sign,
             ));
             w.put_slice(&t177(
                 transport.version.build_time,
-                transport.version.sdk_version,
+                &transport.version.sdk_version,
             ));
             w.put_slice(&t187(&transport.device.mac_address));
             w.put_slice(&t188(&transport.device.android_id));
@@ -583,14 +608,20 @@ impl super::super::super::Engine {
     }
 
     // wtlogin.login
-    pub fn build_login_packet(&self, password_md5: &[u8], allow_slider: bool) -> Packet {
+    pub fn build_login_packet(
+        &self,
+        password_md5: &[u8],
+        allow_slider: bool,
+        t544_energy: Option<&[u8]>,
+    ) -> Packet {
         let seq = self.next_seq();
         let transport = &self.transport;
         let req = self.build_oicq_request_packet(self.uin(), 0x0810, &{
             let mut w = BytesMut::new();
             w.put_u16(9);
 
-            w.put_u16(if allow_slider { 0x17 } else { 0x16 });
+            let tlv_count = if allow_slider { 0x17 } else { 0x16 };
+            w.put_u16(tlv_count + t544_energy.is_some() as u16);
 
             w.put_slice(&t18(16, self.uin() as u32));
             w.put_slice(&t1(self.uin() as u32, &transport.device.ip_address));
@@ -615,7 +646,7 @@ impl super::super::super::Engine {
                 transport.version.main_sig_map,
             ));
             w.put_slice(&t107(0));
-            w.put_slice(&t142(transport.version.apk_id));
+            w.put_slice(&t142(&transport.version.apk_id));
             w.put_slice(&t144(
                 &transport.device.imei,
                 &transport.device.gen_pb_data(),
@@ -635,8 +666,8 @@ impl super::super::super::Engine {
             w.put_slice(&t145(&transport.sig.guid));
             w.put_slice(&t147(
                 16,
-                transport.version.sort_version_name,
-                transport.version.apk_sign,
+                &transport.version.sort_version_name,
+                &transport.version.apk_sign,
             ));
             w.put_slice(&t154(seq));
             w.put_slice(&t141(&transport.device.sim_info, &transport.device.apn));
@@ -672,11 +703,14 @@ impl super::super::super::Engine {
             ));
             w.put_slice(&t177(
                 transport.version.build_time,
-                transport.version.sdk_version,
+                &transport.version.sdk_version,
             ));
             w.put_slice(&t516());
             w.put_slice(&t521(0));
             w.put_slice(&t525(&t536(&[0x01, 0x00])));
+            if let Some(energy) = t544_energy {
+                w.put_slice(&t544(energy));
+            }
 
             w.freeze()
         });
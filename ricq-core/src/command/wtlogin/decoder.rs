@@ -9,28 +9,31 @@ impl super::super::super::Engine {
         if payload.len() < 48 {
             return Err(RQError::Decode("invalid payload length".into()));
         }
-        payload.advance(5); // trans req head
-        payload.get_u8();
-        payload.get_u16();
-        let cmd = payload.get_u16();
-        payload.advance(21);
-        payload.get_u8();
-        payload.get_u16();
-        payload.get_u16();
-        payload.get_i32();
-        payload.get_i64();
-        let len = payload.remaining() - 1;
-        let mut body = payload.copy_to_bytes(len);
+        payload.skip(5)?; // trans req head
+        payload.read_u8()?;
+        payload.read_u16()?;
+        let cmd = payload.read_u16()?;
+        payload.skip(21)?;
+        payload.read_u8()?;
+        payload.read_u16()?;
+        payload.read_u16()?;
+        payload.read_i32()?;
+        payload.read_i64()?;
+        let len = payload
+            .remaining()
+            .checked_sub(1)
+            .ok_or_else(|| RQError::Decode("invalid payload length".into()))?;
+        let mut body = payload.read_bytes(len)?;
         if cmd == 0x31 {
-            body.get_u16();
-            body.get_i32();
-            let code = body.get_u8();
+            body.read_u16()?;
+            body.read_i32()?;
+            let code = body.read_u8()?;
             if code != 0 {
                 return Err(RQError::Decode(format!("body code: {code}")));
             }
-            let sig = body.read_bytes_short();
-            body.get_u16();
-            let mut m = body.read_tlv_map(2);
+            let sig = body.read_bytes_short()?;
+            body.read_u16()?;
+            let mut m = body.read_tlv_map(2)?;
             if m.contains_key(&0x17) {
                 return Ok(QRCodeState::ImageFetch(QRCodeImageFetch {
                     image_data: m
@@ -41,19 +44,19 @@ impl super::super::super::Engine {
             }
         }
         if cmd == 0x12 {
-            let mut a_var_len = body.get_u16();
+            let mut a_var_len = body.read_u16()?;
             if a_var_len != 0 {
                 a_var_len -= 1; // 阴间的位移操作
-                if body.get_u8() == 2 {
-                    body.get_i64(); //uin?
+                if body.read_u8()? == 2 {
+                    body.read_i64()?; //uin?
                     a_var_len -= 8;
                 }
             }
             if a_var_len > 0 {
-                body.advance(a_var_len as usize);
+                body.skip(a_var_len as usize)?;
             }
-            body.get_i32();
-            let code = body.get_u8();
+            body.read_i32()?;
+            let code = body.read_u8()?;
             if code != 0 {
                 return match code {
                     0x30 => Ok(QRCodeState::WaitingForScan),
@@ -63,10 +66,10 @@ impl super::super::super::Engine {
                     _ => Err(RQError::Decode("invalid body code".to_string())),
                 };
             }
-            let uin = body.get_i64();
-            body.get_i32(); // sig create time
-            body.get_u16();
-            let mut m = body.read_tlv_map(2);
+            let uin = body.read_i64()?;
+            body.read_i32()?; // sig create time
+            body.read_u16()?;
+            let mut m = body.read_tlv_map(2)?;
             return Ok(QRCodeState::Confirmed(QRCodeConfirmed {
                 uin,
                 tmp_pwd: m
@@ -89,19 +92,19 @@ impl super::super::super::Engine {
     }
 
     pub fn decode_login_response(&self, mut reader: Bytes) -> RQResult<LoginResponse> {
-        let _sub_command = reader.get_u16(); // sub command
-        let status = reader.get_u8();
+        let _sub_command = reader.read_u16()?; // sub command
+        let status = reader.read_u8()?;
         // TODO status=213 不能执行下面的步骤 panic
-        reader.get_u16();
-        let tlv_map = reader.read_tlv_map(2);
+        reader.read_u16()?;
+        let tlv_map = reader.read_tlv_map(2)?;
         LoginResponse::decode(status, tlv_map, &self.transport.sig.tgtgt_key)
     }
 
     pub fn decode_exchange_emp_response(&self, mut payload: Bytes) -> RQResult<LoginResponse> {
-        let sub_command = payload.get_u16();
-        let status = payload.get_u8();
-        payload.get_u16();
-        let tlv_map = payload.read_tlv_map(2);
+        let sub_command = payload.read_u16()?;
+        let status = payload.read_u8()?;
+        payload.read_u16()?;
+        let tlv_map = payload.read_tlv_map(2)?;
         if status != 0 {
             return Err(RQError::Decode(format!(
                 "decode_exchange_emp_response status: {status}"
@@ -2,7 +2,7 @@
 use std::collections::HashMap;
 use std::time::UNIX_EPOCH;
 
-use bytes::{Buf, Bytes};
+use bytes::Bytes;
 
 use crate::binary::BinaryReader;
 use crate::command::wtlogin::tlv_reader::*;
@@ -125,13 +125,13 @@ impl LoginResponse {
                 let mut t119 = tlv_map
                     .remove(&0x119)
                     .map(|v| decode_t119(&v, encrypt_key))
-                    .ok_or_else(|| RQError::Decode("missing 0x119".to_string()))?;
+                    .ok_or_else(|| RQError::Decode("missing 0x119".to_string()))??;
                 LoginResponse::Success(LoginSuccess {
-                    rollback_sig: tlv_map.remove(&0x161).map(decode_t161),
+                    rollback_sig: tlv_map.remove(&0x161).map(decode_t161).transpose()?,
                     rand_seed: tlv_map.remove(&0x403),
                     ksid: t119.remove(&0x108),
-                    account_info: t119.remove(&0x11a).map(read_t11a),
-                    t512: t119.remove(&0x512).map(read_t512),
+                    account_info: t119.remove(&0x11a).map(read_t11a).transpose()?,
+                    t512: t119.remove(&0x512).map(read_t512).transpose()?,
                     t402: tlv_map.remove(&0x402),
                     wt_session_ticket_key: t119.remove(&0x134),
                     srm_token: t119.remove(&0x16a),
@@ -153,26 +153,30 @@ impl LoginResponse {
                 verify_url: tlv_map
                     .remove(&0x192)
                     .map(|v| String::from_utf8_lossy(&v).into_owned()),
-                image_captcha: tlv_map.remove(&0x165).map(|mut img_data| {
-                    let sign_len = img_data.get_u16();
-                    img_data.get_u16();
-                    let image_sign = img_data.copy_to_bytes(sign_len as usize);
-                    ImageCaptcha {
-                        sign: image_sign,
-                        image: img_data,
-                    }
-                }),
+                image_captcha: tlv_map
+                    .remove(&0x165)
+                    .map(|mut img_data| -> RQResult<ImageCaptcha> {
+                        let sign_len = img_data.read_u16()?;
+                        img_data.read_u16()?;
+                        let image_sign = img_data.read_bytes(sign_len as usize)?;
+                        Ok(ImageCaptcha {
+                            sign: image_sign,
+                            image: img_data,
+                        })
+                    })
+                    .transpose()?,
             }),
             40 => LoginResponse::AccountFrozen,
             160 | 239 => {
                 let t174 = tlv_map.remove(&0x174);
                 let t178 = tlv_map.remove(&0x178);
                 let sms_phone = if t174.is_some() {
-                    t178.map(|mut v| {
-                        let country_code = v.read_string_short();
-                        let phone_number = v.read_string_short();
-                        format!("+{} {}", country_code, phone_number)
+                    t178.map(|mut v| -> RQResult<String> {
+                        let country_code = v.read_string_short()?;
+                        let phone_number = v.read_string_short()?;
+                        Ok(format!("+{} {}", country_code, phone_number))
                     })
+                    .transpose()?
                 } else {
                     None
                 };
@@ -201,9 +205,9 @@ impl LoginResponse {
                 let mut _title = "".into();
                 let mut message = "".into();
                 if let Some(mut v) = tlv_map.remove(&0x146) {
-                    v.advance(4);
-                    _title = v.read_string_short();
-                    message = v.read_string_short();
+                    v.skip(4)?;
+                    _title = v.read_string_short()?;
+                    message = v.read_string_short()?;
                 }
                 LoginResponse::UnknownStatus(LoginUnknownStatus {
                     status,
@@ -1,9 +1,10 @@
 use std::collections::HashMap;
 
-use bytes::{Buf, BufMut, Bytes, BytesMut};
+use bytes::{BufMut, Bytes, BytesMut};
 
 use crate::binary::BinaryReader;
 use crate::crypto::qqtea_decrypt;
+use crate::RQResult;
 
 #[derive(Debug, Clone)]
 pub struct T161 {
@@ -49,72 +50,72 @@ pub struct T531 {
     pub no_pic_sig: Bytes,
 }
 
-pub fn decode_t161(mut data: Bytes) -> T161 {
-    data.advance(2);
-    let mut m = data.read_tlv_map(2);
-    T161 {
+pub fn decode_t161(mut data: Bytes) -> RQResult<T161> {
+    data.skip(2)?;
+    let mut m = data.read_tlv_map(2)?;
+    Ok(T161 {
         rollback_sig: m.remove(&0x172),
-    }
+    })
 }
 
-pub fn decode_t119(data: &[u8], ek: &[u8]) -> HashMap<u16, Bytes> {
-    let mut reader = Bytes::from(qqtea_decrypt(data, ek));
-    reader.advance(2);
+pub fn decode_t119(data: &[u8], ek: &[u8]) -> RQResult<HashMap<u16, Bytes>> {
+    let mut reader = Bytes::from(qqtea_decrypt(data, ek)?);
+    reader.skip(2)?;
     reader.read_tlv_map(2)
 }
 
-pub fn decode_t113(mut data: Bytes) -> T113 {
-    T113 {
-        uin: data.get_i32(),
-    }
+pub fn decode_t113(mut data: Bytes) -> RQResult<T113> {
+    Ok(T113 {
+        uin: data.read_i32()?,
+    })
 }
 
 pub fn decode_t186(_: &[u8]) {}
 
 // not used
-pub fn read_t125(data: &[u8]) -> T125 {
+pub fn read_t125(data: &[u8]) -> RQResult<T125> {
     let mut reader = Bytes::from(data.to_owned());
-    let open_id = reader.read_bytes_short();
-    let open_key = reader.read_bytes_short();
-    T125 { open_id, open_key }
-}
-
-pub fn read_t11a(mut data: Bytes) -> T11A {
-    let face = data.get_u16();
-    let age = data.get_u8();
-    let gender = data.get_u8();
-    let limit = data.get_u8() as usize;
-    let nick = data.read_string_limit(limit);
-    T11A {
+    let open_id = reader.read_bytes_short()?;
+    let open_key = reader.read_bytes_short()?;
+    Ok(T125 { open_id, open_key })
+}
+
+pub fn read_t11a(mut data: Bytes) -> RQResult<T11A> {
+    let face = data.read_u16()?;
+    let age = data.read_u8()?;
+    let gender = data.read_u8()?;
+    let limit = data.read_u8()? as usize;
+    let nick = data.read_string_limit(limit)?;
+    Ok(T11A {
         face,
         age,
         gender,
         nick,
-    }
+    })
 }
 
-pub fn read_t199(mut data: Bytes) -> T199 {
-    let open_id = data.read_bytes_short();
-    let pay_token = data.read_bytes_short();
-    T199 { open_id, pay_token }
+pub fn read_t199(mut data: Bytes) -> RQResult<T199> {
+    let open_id = data.read_bytes_short()?;
+    let pay_token = data.read_bytes_short()?;
+    Ok(T199 { open_id, pay_token })
 }
 
-pub fn read_t200(mut data: Bytes) -> T200 {
-    let pf = data.read_bytes_short();
-    let pf_key = data.read_bytes_short();
-    T200 { pf, pf_key }
+pub fn read_t200(mut data: Bytes) -> RQResult<T200> {
+    let pf = data.read_bytes_short()?;
+    let pf_key = data.read_bytes_short()?;
+    Ok(T200 { pf, pf_key })
 }
 
-pub fn read_t512(mut reader: Bytes) -> T512 {
-    let length = reader.get_u16() as usize;
+pub fn read_t512(mut reader: Bytes) -> RQResult<T512> {
+    let length = reader.read_u16()? as usize;
 
     let mut ps_key_map: HashMap<String, Bytes> = HashMap::with_capacity(length);
     let mut pt4_token_map: HashMap<String, Bytes> = HashMap::with_capacity(length);
 
     for _ in 0..length {
-        let domain = reader.read_string_short();
-        let ps_key = reader.read_bytes_short();
-        let ps4_token = reader.read_bytes_short();
+        let domain = reader.read_string_short()?;
+        let ps_key = reader.read_bytes_short()?;
+        let ps4_token = reader.read_bytes_short()?;
 
         if !ps_key.is_empty() {
             ps_key_map.insert(domain.clone(), ps_key);
@@ -124,25 +125,27 @@ pub fn read_t512(mut reader: Bytes) -> T512 {
             pt4_token_map.insert(domain, ps4_token);
         }
     }
-    T512 {
+    Ok(T512 {
         ps_key_map,
         pt4_token_map,
-    }
+    })
 }
 
-pub fn read_t531(mut data: Bytes) -> T531 {
-    let mut m = data.read_tlv_map(2);
+pub fn read_t531(mut data: Bytes) -> RQResult<T531> {
+    let mut m = data.read_tlv_map(2)?;
     let mut a1 = BytesMut::new();
     let mut no_pic_sig = Bytes::new();
-    if [0x16a, 0x16a, 0x10c].iter().all(|v| m.contains_key(v)) {
-        a1.put_slice(&m.remove(&0x106).expect("0x106 not found"));
-        a1.put_slice(&m.remove(&0x10c).expect("0x10c not found"));
-        no_pic_sig = m.remove(&0x16a).expect("0x16a not found");
+    if let (Some(t106), Some(t10c), Some(t16a)) =
+        (m.remove(&0x106), m.remove(&0x10c), m.remove(&0x16a))
+    {
+        a1.put_slice(&t106);
+        a1.put_slice(&t10c);
+        no_pic_sig = t16a;
     }
-    T531 {
+    Ok(T531 {
         a1: a1.freeze(),
         no_pic_sig,
-    }
+    })
 }
 
 pub fn select(a: Option<&Bytes>, b: &[u8]) -> Bytes {
@@ -769,6 +769,13 @@ pub fn t536(login_extra_data: &[u8]) -> Bytes {
     buf.freeze()
 }
 
+pub fn t544(energy: &[u8]) -> Bytes {
+    let mut buf = BytesMut::new();
+    buf.put_u16(0x544);
+    buf.write_bytes_short(energy);
+    buf.freeze()
+}
+
 pub fn guid_flag() -> u32 {
     let mut flag: u32 = 0;
     flag |= 1 << 24 & 0xFF000000;
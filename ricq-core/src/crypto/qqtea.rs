@@ -4,6 +4,8 @@ use rand::{thread_rng, RngCore};
 
 use tea::{GenericArray, Tea16};
 
+use crate::{RQError, RQResult};
+
 pub fn qqtea_encrypt(text: &[u8], key: &[u8]) -> Vec<u8> {
     let fill_count = 9 - (text.len() + 1) % 8;
 
@@ -46,7 +48,14 @@ pub fn qqtea_encrypt(text: &[u8], key: &[u8]) -> Vec<u8> {
     plaintext
 }
 
-pub fn qqtea_decrypt(text: &[u8], key: &[u8]) -> Vec<u8> {
+/// 密文或密钥长度不合法时返回 Decode 错误
+pub fn qqtea_decrypt(text: &[u8], key: &[u8]) -> RQResult<Vec<u8>> {
+    if key.len() != 16 {
+        return Err(RQError::Decode(format!("qqtea key length {}", key.len())));
+    }
+    if text.len() < 16 || !text.len().is_multiple_of(8) {
+        return Err(RQError::Decode(format!("qqtea text length {}", text.len())));
+    }
     let mut work_block: Vec<u64> = vec![0; text.len() / 8];
 
     BigEndian::read_u64_into(text, &mut work_block);
@@ -78,8 +87,11 @@ pub fn qqtea_decrypt(text: &[u8], key: &[u8]) -> Vec<u8> {
 
     let begin_pos = ((result[0] as usize) & 7) + 3;
     let end_pos = result.len() - 7;
+    if begin_pos > end_pos {
+        return Err(RQError::Decode("qqtea padding".into()));
+    }
 
-    result[begin_pos..end_pos].to_owned()
+    Ok(result[begin_pos..end_pos].to_owned())
 }
 
 mod tea {
@@ -175,3 +187,32 @@ mod tea {
         BigEndian::write_u64(text, n);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn test_qqtea_round_trip(text in vec(any::<u8>(), 0..512), key in any::<[u8; 16]>()) {
+            let encrypted = qqtea_encrypt(&text, &key);
+            prop_assert_eq!(encrypted.len() % 8, 0);
+            prop_assert_eq!(qqtea_decrypt(&encrypted, &key).unwrap(), text);
+        }
+
+        #[test]
+        fn test_qqtea_decrypt_arbitrary(text in vec(any::<u8>(), 0..128), key in vec(any::<u8>(), 0..20)) {
+            let _ = qqtea_decrypt(&text, &key);
+        }
+    }
+
+    #[test]
+    fn test_qqtea_decrypt_invalid() {
+        assert!(qqtea_decrypt(&[0; 16], &[0; 15]).is_err());
+        assert!(qqtea_decrypt(&[0; 15], &[0; 16]).is_err());
+        assert!(qqtea_decrypt(&[0; 20], &[0; 16]).is_err());
+    }
+}
@@ -58,4 +58,6 @@ pub enum RQError {
     GetFileCountFailed,
     #[error("failed to get file list: {0}")]
     GetFileListFailed(String),
+    #[error("send queue is full")]
+    SendQueueFull,
 }
@@ -28,6 +28,34 @@ pub struct BdhInput {
     pub send_echo: bool,
 }
 
+/// 上传文件的大小和摘要
+#[derive(Default, Debug, Clone)]
+pub struct FileHash {
+    pub size: i64,
+    pub md5: Vec<u8>,
+    pub sha1: Vec<u8>,
+    /// 前 10 MiB 的 md5，离线文件使用
+    pub md5_10m: Vec<u8>,
+}
+
+/// 群文件、离线文件上传的 ext 参数
+#[derive(Default, Debug, Clone)]
+pub struct FileUploadExtInput {
+    /// 离线文件为 0
+    pub group_code: i64,
+    /// 群文件为群号，离线文件为好友 QQ 号
+    pub receiver: i64,
+    pub bus_id: i32,
+    pub name: String,
+    pub size: i64,
+    pub md5: Vec<u8>,
+    pub sha1: Vec<u8>,
+    pub file_id: Vec<u8>,
+    pub upload_key: Vec<u8>,
+    pub host: String,
+    pub port: i32,
+}
+
 impl Session {
     fn next_seq(&self) -> i32 {
         self.seq.fetch_add(2, Ordering::Relaxed)
@@ -101,6 +129,52 @@ impl Session {
         pb::RspDataHighwayHead::decode(&*payload).map_err(Into::into)
     }
 
+    /// 群文件 (command_id 71)、离线文件 (command_id 69) 上传时的 req_extendinfo
+    pub fn build_file_upload_ext(&self, input: FileUploadExtInput) -> Vec<u8> {
+        let is_group = input.group_code != 0;
+        pb::exciting::FileUploadExt {
+            unknown1: Some(100),
+            unknown2: Some(if is_group { 1 } else { 2 }),
+            unknown3: is_group.then_some(0),
+            unknown200: (!is_group).then_some(1),
+            entry: Some(pb::exciting::FileUploadEntry {
+                busi_buff: Some(pb::exciting::ExcitingBusiInfo {
+                    bus_id: Some(input.bus_id),
+                    sender_uin: Some(self.uin),
+                    receiver_uin: Some(input.receiver),
+                    group_code: Some(input.group_code),
+                }),
+                file_entry: Some(pb::exciting::ExcitingFileEntry {
+                    file_size: Some(input.size),
+                    md5: Some(input.md5),
+                    sha1: Some(input.sha1),
+                    file_id: Some(input.file_id),
+                    upload_key: Some(input.upload_key),
+                }),
+                client_info: Some(pb::exciting::ExcitingClientInfo {
+                    client_type: Some(2),
+                    app_id: Some(self.app_id.to_string()),
+                    terminal_type: Some(2),
+                    client_ver: Some("9e9c09dc".into()),
+                    unknown: Some(4),
+                }),
+                file_name_info: Some(pb::exciting::ExcitingFileNameInfo {
+                    file_name: Some(input.name),
+                }),
+                host: Some(pb::exciting::ExcitingHostConfig {
+                    hosts: vec![pb::exciting::ExcitingHostInfo {
+                        url: Some(pb::exciting::ExcitingUrlInfo {
+                            unknown: Some(1),
+                            host: Some(input.host),
+                        }),
+                        port: Some(input.port),
+                    }],
+                }),
+            }),
+        }
+        .encode_to_vec()
+    }
+
     pub fn build_heartbreak(&self) -> Bytes {
         pb::ReqDataHighwayHead {
             msg_basehead: Some(self.build_basehead("PicUp.Echo".into(), 4096, 0, 2052)),
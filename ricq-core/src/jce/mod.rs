@@ -1058,7 +1058,7 @@ mod tests {
         let key = decode_hex("F0441F5FF42DA58FDCF7949ABA62D411").expect("failed to decode hex");
 
         let data = decode_hex(SSO_ADDRESS_RESP).expect("failed to decode_hex");
-        let mut de_rsp = Bytes::from(qqtea_decrypt(&data, &key));
+        let mut de_rsp = Bytes::from(qqtea_decrypt(&data, &key).expect("failed to decrypt"));
 
         de_rsp.advance(4);
         let mut request_packet: RequestPacket =
@@ -0,0 +1,609 @@
+//! CQ 码（go-cqhttp）与 mirai 码的解析和序列化
+//!
+//! 无法识别或参数不完整的码会原样作为文本保留。
+//!
+//! 回复使用 `[CQ:reply,seq=..]`（go-cqhttp 的自定义回复格式）；`id` 在 go-cqhttp 中是 message_id，
+//! 无法对应到 seq，因此不会被解析。
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::hex::{decode_hex, encode_hex};
+use crate::msg::elem::*;
+use crate::msg::repr::strip_placeholders;
+use crate::msg::{MessageChain, MessageChainBuilder};
+
+impl MessageChain {
+    /// 解析 CQ 码，例如 `hello[CQ:at,qq=123]`
+    ///
+    /// 图片、视频需要带有 [`MessageChain::to_cq_code`] 生成的完整参数才能解析，否则作为文本保留
+    pub fn from_cq_code(s: &str) -> Self {
+        let mut builder = MessageChainBuilder::new();
+        let mut rest = s;
+        while let Some(start) = rest.find("[CQ:") {
+            builder.push_str(&cq_unescape(&rest[..start]));
+            rest = &rest[start..];
+            let Some(end) = rest.find(']') else { break };
+            push_cq_code(&mut builder, &rest[4..end], &rest[..=end]);
+            rest = &rest[end + 1..];
+        }
+        builder.push_str(&cq_unescape(rest));
+        builder.build()
+    }
+
+    /// 序列化为 CQ 码，匿名信息和无法识别的元素会被忽略
+    pub fn to_cq_code(&self) -> String {
+        let mut s = String::new();
+        if let Some(reply) = self.reply() {
+            write_cq_code(
+                &mut s,
+                "reply",
+                &[
+                    ("seq", reply.reply_seq.to_string()),
+                    ("qq", reply.sender.to_string()),
+                    ("time", reply.time.to_string()),
+                    ("text", reply.elements.to_string().trim_end().to_owned()),
+                ],
+            );
+        }
+        for elem in strip_placeholders(self.clone()) {
+            match elem {
+                RQElem::Text(e) => s.push_str(&cq_escape(&e.content, false)),
+                RQElem::At(e) if e.target == 0 => {
+                    write_cq_code(&mut s, "at", &[("qq", "all".into())])
+                }
+                RQElem::At(e) => write_cq_code(&mut s, "at", &[("qq", e.target.to_string())]),
+                RQElem::Face(e) => write_cq_code(&mut s, "face", &[("id", e.index.to_string())]),
+                RQElem::MarketFace(e) => write_cq_code(
+                    &mut s,
+                    "mface",
+                    &[
+                        ("name", e.name),
+                        ("id", encode_hex(&e.face_id)),
+                        ("tab_id", e.tab_id.to_string()),
+                        ("item_type", e.item_type.to_string()),
+                        ("sub_type", e.sub_type.to_string()),
+                        ("media_type", e.media_type.to_string()),
+                        ("key", encode_hex(&e.encrypt_key)),
+                        ("magic_value", e.magic_value),
+                    ],
+                ),
+                RQElem::Dice(e) => write_cq_code(&mut s, "dice", &[("value", e.value.to_string())]),
+                RQElem::FingerGuessing(e) => {
+                    write_cq_code(&mut s, "rps", &[("value", rps_value(&e).to_string())])
+                }
+                RQElem::LightApp(e) => write_cq_code(&mut s, "json", &[("data", e.content)]),
+                RQElem::RichMsg(e) => write_cq_code(
+                    &mut s,
+                    "xml",
+                    &[("data", e.template1), ("resid", e.service_id.to_string())],
+                ),
+                RQElem::FriendImage(e) => write_cq_code(&mut s, "image", &friend_image_params(e)),
+                RQElem::GroupImage(e) => write_cq_code(&mut s, "image", &group_image_params(e)),
+                RQElem::FlashImage(e) => {
+                    let mut params = match e {
+                        FlashImage::FriendImage(e) => friend_image_params(e),
+                        FlashImage::GroupImage(e) => group_image_params(e),
+                    };
+                    params.insert(1, ("type", "flash".into()));
+                    write_cq_code(&mut s, "image", &params)
+                }
+                RQElem::VideoFile(e) => write_cq_code(
+                    &mut s,
+                    "video",
+                    &[
+                        ("file", format!("{}.video", encode_hex(&e.md5))),
+                        ("md5", encode_hex(&e.md5)),
+                        ("uuid", encode_hex(&e.uuid)),
+                        ("size", e.size.to_string()),
+                        ("thumb_md5", encode_hex(&e.thumb_md5)),
+                        ("thumb_size", e.thumb_size.to_string()),
+                    ],
+                ),
+                RQElem::Forward(e) => write_cq_code(&mut s, "forward", &[("id", e.res_id)]),
+                RQElem::GroupFile(_) | RQElem::Other(_) => {}
+            }
+        }
+        s
+    }
+
+    /// 解析 mirai 码，例如 `hello[mirai:at:123]`
+    ///
+    /// 图片、商城表情需要上传或额外信息，作为文本保留
+    pub fn from_mirai_code(s: &str) -> Self {
+        let mut builder = MessageChainBuilder::new();
+        let mut text = String::new();
+        let mut rest = s;
+        loop {
+            if rest.starts_with("[mirai:") {
+                if let Some(end) = find_unescaped(rest, ']') {
+                    builder.push_str(&text);
+                    text.clear();
+                    push_mirai_code(&mut builder, &rest[7..end], &rest[..=end]);
+                    rest = &rest[end + 1..];
+                    continue;
+                }
+            }
+            let mut chars = rest.chars();
+            match chars.next() {
+                Some('\\') => match chars.next() {
+                    Some(c) => text.push(mirai_unescape_char(c)),
+                    None => text.push('\\'),
+                },
+                Some(c) => text.push(c),
+                None => break,
+            }
+            rest = chars.as_str();
+        }
+        builder.push_str(&text);
+        builder.build()
+    }
+
+    /// 序列化为 mirai 码，引用回复、匿名信息、视频、转发和无法识别的元素会被忽略
+    pub fn to_mirai_code(&self) -> String {
+        let mut s = String::new();
+        for elem in strip_placeholders(self.clone()) {
+            match elem {
+                RQElem::Text(e) => s.push_str(&mirai_escape(&e.content)),
+                RQElem::At(e) if e.target == 0 => s.push_str("[mirai:atall]"),
+                RQElem::At(e) => write_mirai_code(&mut s, "at", &[e.target.to_string()]),
+                RQElem::Face(e) => write_mirai_code(&mut s, "face", &[e.index.to_string()]),
+                RQElem::MarketFace(e) => {
+                    write_mirai_code(&mut s, "marketface", &[e.tab_id.to_string(), e.name])
+                }
+                RQElem::Dice(e) => write_mirai_code(&mut s, "dice", &[e.value.to_string()]),
+                RQElem::FingerGuessing(e) => {
+                    let name = match e {
+                        FingerGuessing::Rock => "rock",
+                        FingerGuessing::Scissors => "scissors",
+                        FingerGuessing::Paper => "paper",
+                    };
+                    write_mirai_code(&mut s, "rps", &[name.into()])
+                }
+                RQElem::LightApp(e) => write_mirai_code(&mut s, "app", &[e.content]),
+                RQElem::RichMsg(e) => {
+                    write_mirai_code(&mut s, "service", &[e.service_id.to_string(), e.template1])
+                }
+                RQElem::FriendImage(e) => {
+                    write_mirai_code(&mut s, "image", &[calculate_image_resource_id(&e.md5)])
+                }
+                RQElem::GroupImage(e) => {
+                    write_mirai_code(&mut s, "image", &[calculate_image_resource_id(&e.md5)])
+                }
+                RQElem::FlashImage(e) => {
+                    let md5 = match &e {
+                        FlashImage::FriendImage(e) => &e.md5,
+                        FlashImage::GroupImage(e) => &e.md5,
+                    };
+                    write_mirai_code(&mut s, "flash", &[calculate_image_resource_id(md5)])
+                }
+                RQElem::VideoFile(_)
+                | RQElem::Forward(_)
+                | RQElem::GroupFile(_)
+                | RQElem::Other(_) => {}
+            }
+        }
+        s
+    }
+}
+
+fn push_cq_code(builder: &mut MessageChainBuilder, code: &str, raw: &str) {
+    let mut parts = code.split(',');
+    let kind = parts.next().unwrap_or_default();
+    let params: HashMap<&str, String> = parts
+        .filter_map(|p| p.split_once('='))
+        .map(|(k, v)| (k, cq_unescape(v)))
+        .collect();
+    let param = |key: &str| params.get(key).map(String::as_str);
+    if kind == "reply" {
+        match param("seq").and_then(|seq| seq.parse().ok()) {
+            Some(reply_seq) => {
+                builder.push(Reply {
+                    reply_seq,
+                    sender: param("qq")
+                        .and_then(|qq| qq.parse().ok())
+                        .unwrap_or_default(),
+                    time: param("time")
+                        .and_then(|t| t.parse().ok())
+                        .unwrap_or_default(),
+                    elements: MessageChain::new(Text::new(
+                        param("text").unwrap_or_default().into(),
+                    )),
+                    ..Default::default()
+                });
+            }
+            None => {
+                builder.push_str(raw);
+            }
+        }
+        return;
+    }
+    let parsed = match kind {
+        "at" => match param("qq") {
+            Some("all") => Some(RQElem::At(At {
+                target: 0,
+                display: "@全体成员".into(),
+            })),
+            Some(qq) => qq.parse().ok().map(|qq| RQElem::At(At::new(qq))),
+            None => None,
+        },
+        "face" => param("id")
+            .and_then(|id| id.parse().ok())
+            .map(|id| RQElem::Face(Face::new(id))),
+        "mface" => parse_cq_mface(&params),
+        "dice" => match param("value") {
+            Some(v) => v
+                .parse()
+                .ok()
+                .filter(|v| (1..=6).contains(v))
+                .map(|v| RQElem::Dice(Dice::new(v))),
+            None => Some(RQElem::Dice(Dice::new(
+                rand::random::<i32>().rem_euclid(6) + 1,
+            ))),
+        },
+        "rps" => match param("value") {
+            Some(v) => v.parse().ok().and_then(rps_from_value),
+            None => rps_from_value(rand::random::<u8>() % 3),
+        }
+        .map(RQElem::FingerGuessing),
+        "json" => param("data").map(|data| RQElem::LightApp(LightApp::new(data.into()))),
+        "xml" => param("data").map(|data| {
+            RQElem::RichMsg(RichMsg {
+                service_id: param("resid").and_then(|id| id.parse().ok()).unwrap_or(1),
+                template1: data.into(),
+            })
+        }),
+        "image" => parse_cq_image(&params),
+        "video" => parse_cq_video(&params),
+        _ => None,
+    };
+    match parsed {
+        Some(elem) => {
+            builder.push(elem);
+        }
+        None => {
+            builder.push_str(raw);
+        }
+    }
+}
+
+fn parse_cq_mface(params: &HashMap<&str, String>) -> Option<RQElem> {
+    let param = |key: &str| params.get(key).map(String::as_str);
+    Some(RQElem::MarketFace(MarketFace {
+        name: param("name")?.to_owned(),
+        face_id: parse_hex(param("id")?)?,
+        tab_id: param("tab_id")?.parse().ok()?,
+        item_type: param("item_type")?.parse().ok()?,
+        sub_type: param("sub_type")?.parse().ok()?,
+        media_type: param("media_type")?.parse().ok()?,
+        encrypt_key: parse_hex(param("key")?)?,
+        magic_value: param("magic_value")?.to_owned(),
+    }))
+}
+
+fn parse_cq_video(params: &HashMap<&str, String>) -> Option<RQElem> {
+    let param = |key: &str| params.get(key).map(String::as_str);
+    Some(RQElem::VideoFile(VideoFile {
+        md5: parse_hex(param("md5")?)?,
+        name: format!("{}.mp4", param("md5")?),
+        uuid: parse_hex(param("uuid")?)?,
+        size: param("size")?.parse().ok()?,
+        thumb_md5: parse_hex(param("thumb_md5")?)?,
+        thumb_size: param("thumb_size")?.parse().ok()?,
+    }))
+}
+
+fn parse_cq_image(params: &HashMap<&str, String>) -> Option<RQElem> {
+    let param = |key: &str| params.get(key).map(String::as_str);
+    let md5 = parse_hex(param("md5")?)?;
+    let size = param("size")?.parse().ok()?;
+    let width = param("width")
+        .and_then(|w| w.parse().ok())
+        .unwrap_or_default();
+    let height = param("height")
+        .and_then(|h| h.parse().ok())
+        .unwrap_or_default();
+    let image_type = param("image_type")
+        .and_then(|t| t.parse().ok())
+        .unwrap_or(1000);
+    let flash = param("type") == Some("flash");
+    if let Some(file_id) = param("file_id") {
+        let image = GroupImage {
+            file_path: calculate_image_resource_id(&md5),
+            file_id: file_id.parse().ok()?,
+            size,
+            width,
+            height,
+            md5,
+            image_type,
+            ..Default::default()
+        };
+        return Some(if flash {
+            RQElem::FlashImage(image.flash())
+        } else {
+            RQElem::GroupImage(image)
+        });
+    }
+    let res_id = param("res_id")?;
+    let image = FriendImage {
+        res_id: res_id.into(),
+        file_path: res_id.into(),
+        download_path: res_id.into(),
+        md5,
+        size,
+        width,
+        height,
+        image_type,
+        ..Default::default()
+    };
+    Some(if flash {
+        RQElem::FlashImage(image.flash())
+    } else {
+        RQElem::FriendImage(image)
+    })
+}
+
+fn friend_image_params(e: FriendImage) -> Vec<(&'static str, String)> {
+    let url = e.url();
+    vec![
+        ("file", format!("{}.image", encode_hex(&e.md5))),
+        ("url", url),
+        ("md5", encode_hex(&e.md5)),
+        ("size", e.size.to_string()),
+        ("width", e.width.to_string()),
+        ("height", e.height.to_string()),
+        ("image_type", e.image_type.to_string()),
+        ("res_id", e.res_id),
+    ]
+}
+
+fn group_image_params(e: GroupImage) -> Vec<(&'static str, String)> {
+    vec![
+        ("file", format!("{}.image", encode_hex(&e.md5))),
+        ("url", e.url()),
+        ("md5", encode_hex(&e.md5)),
+        ("size", e.size.to_string()),
+        ("width", e.width.to_string()),
+        ("height", e.height.to_string()),
+        ("image_type", e.image_type.to_string()),
+        ("file_id", e.file_id.to_string()),
+    ]
+}
+
+fn push_mirai_code(builder: &mut MessageChainBuilder, code: &str, raw: &str) {
+    let (kind, args) = match find_unescaped(code, ':') {
+        Some(i) => (&code[..i], split_unescaped(&code[i + 1..])),
+        None => (code, Vec::new()),
+    };
+    let arg = |i: usize| args.get(i).map(String::as_str);
+    let parsed = match kind {
+        "at" => arg(0)
+            .and_then(|qq| qq.parse().ok())
+            .map(|qq| RQElem::At(At::new(qq))),
+        "atall" => Some(RQElem::At(At {
+            target: 0,
+            display: "@全体成员".into(),
+        })),
+        "face" => arg(0)
+            .and_then(|id| id.parse().ok())
+            .map(|id| RQElem::Face(Face::new(id))),
+        "dice" => arg(0)
+            .and_then(|v| v.parse().ok())
+            .filter(|v| (1..=6).contains(v))
+            .map(|v| RQElem::Dice(Dice::new(v))),
+        "rps" => match arg(0) {
+            Some("rock") => Some(FingerGuessing::Rock),
+            Some("scissors") => Some(FingerGuessing::Scissors),
+            Some("paper") => Some(FingerGuessing::Paper),
+            _ => None,
+        }
+        .map(RQElem::FingerGuessing),
+        "app" => arg(0).map(|content| RQElem::LightApp(LightApp::new(content.into()))),
+        "service" => match (arg(0).and_then(|id| id.parse().ok()), arg(1)) {
+            (Some(service_id), Some(template1)) => Some(RQElem::RichMsg(RichMsg {
+                service_id,
+                template1: template1.into(),
+            })),
+            _ => None,
+        },
+        _ => None,
+    };
+    match parsed {
+        Some(elem) => {
+            builder.push(elem);
+        }
+        None => {
+            builder.push_str(raw);
+        }
+    }
+}
+
+fn rps_value(e: &FingerGuessing) -> u8 {
+    match e {
+        FingerGuessing::Rock => 0,
+        FingerGuessing::Scissors => 1,
+        FingerGuessing::Paper => 2,
+    }
+}
+
+fn rps_from_value(v: u8) -> Option<FingerGuessing> {
+    match v {
+        0 => Some(FingerGuessing::Rock),
+        1 => Some(FingerGuessing::Scissors),
+        2 => Some(FingerGuessing::Paper),
+        _ => None,
+    }
+}
+
+fn parse_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    decode_hex(s).ok()
+}
+
+fn write_cq_code(s: &mut String, kind: &str, params: &[(&str, String)]) {
+    write!(s, "[CQ:{kind}").ok();
+    for (k, v) in params {
+        write!(s, ",{k}={}", cq_escape(v, true)).ok();
+    }
+    s.push(']');
+}
+
+fn cq_escape(s: &str, param: bool) -> String {
+    let s = s
+        .replace('&', "&amp;")
+        .replace('[', "&#91;")
+        .replace(']', "&#93;");
+    if param {
+        s.replace(',', "&#44;")
+    } else {
+        s
+    }
+}
+
+fn cq_unescape(s: &str) -> String {
+    s.replace("&#44;", ",")
+        .replace("&#91;", "[")
+        .replace("&#93;", "]")
+        .replace("&amp;", "&")
+}
+
+fn write_mirai_code(s: &mut String, kind: &str, args: &[String]) {
+    write!(s, "[mirai:{kind}").ok();
+    for (i, arg) in args.iter().enumerate() {
+        s.push(if i == 0 { ':' } else { ',' });
+        s.push_str(&mirai_escape(arg));
+    }
+    s.push(']');
+}
+
+fn mirai_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' | '[' | ']' | ':' | ',' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn mirai_unescape_char(c: char) -> char {
+    match c {
+        'n' => '\n',
+        'r' => '\r',
+        c => c,
+    }
+}
+
+/// 查找第一个未被 `\` 转义的字符
+fn find_unescaped(s: &str, target: char) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == target {
+            return Some(i);
+        }
+    }
+    None
+}
+
+/// 按未转义的 `,` 分割参数并反转义
+fn split_unescaped(s: &str) -> Vec<String> {
+    let mut args = vec![String::new()];
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(c) = chars.next() {
+                    args.last_mut().unwrap().push(mirai_unescape_char(c));
+                }
+            }
+            ',' => args.push(String::new()),
+            c => args.last_mut().unwrap().push(c),
+        }
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cq_code() {
+        let chain = MessageChain::from_cq_code(
+            "[CQ:reply,seq=7,qq=10000,time=1,text=hi]a&#91;b&#93;[CQ:at,qq=123][CQ:at,qq=all][CQ:face,id=1][CQ:dice,value=3][CQ:rps,value=2][CQ:json,data={\"a\":1&#44;\"b\":2}][CQ:unknown,x=1]",
+        );
+        assert_eq!(chain.reply().unwrap().reply_seq, 7);
+        let elems: Vec<_> = chain.clone().into_iter().collect();
+        assert!(matches!(&elems[0], RQElem::Text(t) if t.content == "a[b]"));
+        assert!(matches!(&elems[1], RQElem::At(at) if at.target == 123));
+        assert!(matches!(&elems[2], RQElem::At(at) if at.target == 0));
+        assert!(matches!(&elems[3], RQElem::Face(f) if f.index == 1));
+        assert!(matches!(&elems[4], RQElem::Dice(d) if d.value == 3));
+        assert!(matches!(
+            &elems[6],
+            RQElem::FingerGuessing(FingerGuessing::Paper)
+        ));
+        assert!(matches!(&elems[8], RQElem::LightApp(app) if app.content == "{\"a\":1,\"b\":2}"));
+        assert!(matches!(&elems[9], RQElem::Text(t) if t.content == "[CQ:unknown,x=1]"));
+
+        let code = chain.to_cq_code();
+        assert!(code.starts_with("[CQ:reply,seq=7,qq=10000,time=1,text=hi]a&#91;b&#93;[CQ:at,qq=123][CQ:at,qq=all][CQ:face,id=1][CQ:dice,value=3][CQ:rps,value=2][CQ:json,data={\"a\":1&#44;\"b\":2}]"));
+
+        // go-cqhttp 的 id 是 message_id，不是 seq
+        let chain = MessageChain::from_cq_code("[CQ:reply,id=7]hi");
+        assert!(chain.reply().is_none());
+        assert_eq!(chain.plain_text(), "[CQ:reply,id=7]hi");
+
+        let mut chain = MessageChain::default();
+        chain.push(GroupImage {
+            md5: vec![0xab; 16],
+            size: 100,
+            file_id: 42,
+            width: 10,
+            height: 20,
+            image_type: 1000,
+            ..Default::default()
+        });
+        let code = chain.to_cq_code();
+        let back = MessageChain::from_cq_code(&code);
+        assert_eq!(back.to_cq_code(), code);
+        assert!(matches!(back.into_iter().next(), Some(RQElem::GroupImage(i)) if i.file_id == 42));
+    }
+
+    #[test]
+    fn test_mirai_code() {
+        let s = "a\\[b\\:c[mirai:at:123][mirai:atall][mirai:face:1][mirai:rps:rock][mirai:service:1,<x a\\=\"1\\,2\"/>][mirai:image:{AB}.png]";
+        let chain = MessageChain::from_mirai_code(s);
+        let elems: Vec<_> = chain.clone().into_iter().collect();
+        assert!(matches!(&elems[0], RQElem::Text(t) if t.content == "a[b:c"));
+        assert!(matches!(&elems[1], RQElem::At(at) if at.target == 123));
+        assert!(matches!(&elems[2], RQElem::At(at) if at.target == 0));
+        assert!(matches!(&elems[3], RQElem::Face(f) if f.index == 1));
+        assert!(matches!(
+            &elems[4],
+            RQElem::FingerGuessing(FingerGuessing::Rock)
+        ));
+        assert!(matches!(&elems[6], RQElem::RichMsg(r) if r.template1 == "<x a=\"1,2\"/>"));
+        assert!(matches!(&elems[7], RQElem::Text(t) if t.content == "[mirai:image:{AB}.png]"));
+
+        let mut chain = MessageChain::default();
+        chain.push(Text::new("x[y]:z,\n".into()));
+        chain.push(At::new(1));
+        chain.push(Dice::new(6));
+        let code = chain.to_mirai_code();
+        assert_eq!(code, "x\\[y\\]\\:z\\,\\n[mirai:at:1][mirai:dice:6]");
+        assert_eq!(MessageChain::from_mirai_code(&code).to_mirai_code(), code);
+    }
+}
@@ -1,8 +1,10 @@
+use serde::{Deserialize, Serialize};
+
 use crate::msg::{MessageChainBuilder, MessageElem, PushBuilder};
 use crate::pb::msg;
 use crate::pb::msg::AnonymousGroupMessage;
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct Anonymous {
     // 用于禁言
     pub anon_id: Vec<u8>,
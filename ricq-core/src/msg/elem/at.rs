@@ -1,13 +1,14 @@
 use std::fmt;
 
 use bytes::{Buf, BufMut};
+use serde::{Deserialize, Serialize};
 
 use crate::msg::{MessageChainBuilder, PushBuilder};
 use crate::msg::{MessageElem, PushElem};
 use crate::pb::msg;
 use crate::{push_builder_impl, to_elem_vec_impl};
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct At {
     pub target: i64,
     pub display: String,
@@ -41,10 +42,22 @@ impl PushElem for At {
     }
 }
 
+/// attr6 中 At 目标的位置
+const ATTR6_TARGET: std::ops::Range<usize> = 7..11;
+
+/// attr6 足够长、可以解析出目标时才是 At，否则按普通文本处理，避免截断的数据被当作 @全体成员
+pub(crate) fn is_at(e: &msg::Text) -> bool {
+    e.attr6_buf().len() >= ATTR6_TARGET.end
+}
+
 impl From<msg::Text> for At {
     fn from(e: msg::Text) -> Self {
-        let (_, mut attr6) = e.attr6_buf().split_at(7);
-        let target = attr6.get_u32() as i64;
+        // 长度不足时目标为 0，调用前应先用 is_at 检查
+        let target = e
+            .attr6_buf()
+            .get(ATTR6_TARGET)
+            .map(|mut b| b.get_u32() as i64)
+            .unwrap_or_default();
         Self {
             target,
             display: e.str.unwrap_or_default(),
@@ -1,13 +1,14 @@
 use std::fmt;
 
 use prost::Message;
+use serde::{Deserialize, Serialize};
 
 use crate::msg::{MessageChainBuilder, PushBuilder};
 use crate::msg::{MessageElem, PushElem};
 use crate::pb::msg;
 use crate::{push_builder_impl, to_elem_vec_impl};
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct Face {
     pub index: i32,
     pub name: String,
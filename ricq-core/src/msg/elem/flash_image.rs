@@ -1,5 +1,7 @@
 use std::fmt;
 
+use serde::{Deserialize, Serialize};
+
 use crate::command::common::PbToBytes;
 use crate::msg::elem::{FriendImage, GroupImage};
 use crate::msg::{MessageChainBuilder, PushBuilder};
@@ -7,7 +9,8 @@ use crate::msg::{MessageElem, PushElem};
 use crate::pb::msg;
 use crate::{push_builder_impl, to_elem_vec_impl};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum FlashImage {
     FriendImage(FriendImage),
     GroupImage(GroupImage),
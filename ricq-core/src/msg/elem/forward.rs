@@ -0,0 +1,91 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::msg::elem::RichMsg;
+use crate::msg::{MessageChainBuilder, PushBuilder};
+use crate::msg::{MessageElem, PushElem};
+use crate::{push_builder_impl, to_elem_vec_impl};
+
+/// 合并转发或长消息卡片，内容需要用 `Client::resolve_forward` 下载
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct Forward {
+    /// 下载用的 res_id，嵌套在转发消息中的转发为空
+    pub res_id: String,
+    /// 嵌套转发在 `PbMultiMsgTransmit.pb_item_list` 中的文件名
+    pub file_name: String,
+    /// 卡片标题，例如“群聊的聊天记录”
+    pub title: String,
+    /// 预览，每条为“昵称: 内容”
+    pub preview: Vec<String>,
+    pub brief: String,
+    pub summary: String,
+    /// 转发的消息条数，长消息为 0
+    pub t_sum: i32,
+    /// 是否为长消息（multiMsgFlag="1"）
+    pub is_long: bool,
+    /// 原始 RichMsg，用于原样转发
+    pub service_id: i32,
+    pub template1: String,
+}
+
+impl Forward {
+    /// 从 action="viewMultiMsg" 的 RichMsg 中识别
+    pub fn from_rich_msg(rich: &RichMsg) -> Option<Self> {
+        let msg = rich.parse().ok()?;
+        if !msg.is_view_multi_msg() {
+            return None;
+        }
+        let res_id = msg.attr("m_resid").unwrap_or_default().to_owned();
+        let file_name = msg.attr("m_fileName").unwrap_or_default().to_owned();
+        if res_id.is_empty() && file_name.is_empty() {
+            return None;
+        }
+        let mut titles = msg.items.iter().flat_map(|i| i.titles()).map(String::from);
+        Some(Self {
+            res_id,
+            file_name,
+            title: titles.next().unwrap_or_default(),
+            preview: titles.collect(),
+            summary: msg
+                .items
+                .iter()
+                .find_map(|i| i.summary())
+                .unwrap_or_default()
+                .to_owned(),
+            t_sum: msg
+                .attr("tSum")
+                .and_then(|t| t.parse().ok())
+                .unwrap_or_default(),
+            is_long: msg.attr("multiMsgFlag") == Some("1"),
+            brief: msg.brief,
+            service_id: rich.service_id,
+            template1: rich.template1.clone(),
+        })
+    }
+}
+
+impl PushElem for Forward {
+    fn push_to(elem: Self, vec: &mut Vec<MessageElem>) {
+        RichMsg::push_to(
+            RichMsg {
+                service_id: elem.service_id,
+                template1: elem.template1,
+            },
+            vec,
+        );
+    }
+}
+
+impl fmt::Display for Forward {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_long {
+            write!(f, "[LongMessage: {}]", self.res_id)
+        } else {
+            write!(f, "[Forward: {}]", self.res_id)
+        }
+    }
+}
+
+to_elem_vec_impl!(Forward);
+push_builder_impl!(Forward);
@@ -0,0 +1,109 @@
+use std::fmt;
+
+use bytes::{Buf, BufMut};
+use prost::Message;
+use serde::{Deserialize, Serialize};
+
+use crate::msg::{MessageChainBuilder, PushBuilder};
+use crate::msg::{MessageElem, PushElem};
+use crate::pb::msg;
+use crate::{push_builder_impl, to_elem_vec_impl};
+
+/// 群文件消息，上传群文件后由服务器发出，可以用 `Client::get_group_file_download` 获取下载链接
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct GroupFile {
+    pub name: String,
+    pub size: i64,
+    /// 文件 id，形如 `/xxxx-xxxx`
+    pub file_id: String,
+    pub bus_id: i32,
+}
+
+impl GroupFile {
+    /// TransElem elem_type 为 24，elem_value 为 `0x01 | u16 长度 | ObjMsg`
+    pub fn from_trans_elem(e: &msg::TransElem) -> Option<Self> {
+        if e.elem_type() != 24 {
+            return None;
+        }
+        let mut value = e.elem_value();
+        if value.remaining() < 3 || value.get_u8() != 1 {
+            return None;
+        }
+        let len = value.get_u16() as usize;
+        let obj = msg::ObjMsg::decode(value.get(..len)?).ok()?;
+        let file = obj.msg_content_info.into_iter().next()?.msg_file?;
+        Some(Self {
+            name: file.file_name,
+            size: file.file_size,
+            file_id: String::from_utf8_lossy(&file.file_path).into_owned(),
+            bus_id: file.bus_id,
+        })
+    }
+}
+
+impl PushElem for GroupFile {
+    fn push_to(elem: Self, vec: &mut Vec<MessageElem>) {
+        let obj = msg::ObjMsg {
+            msg_type: 6,
+            msg_content_info: vec![msg::MsgContentInfo {
+                msg_file: Some(msg::MsgFile {
+                    bus_id: elem.bus_id,
+                    file_path: elem.file_id.into_bytes(),
+                    file_size: elem.size,
+                    file_name: elem.name,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+        .encode_to_vec();
+        let mut value = Vec::with_capacity(obj.len() + 3);
+        value.put_u8(1);
+        value.put_u16(obj.len() as u16);
+        value.put_slice(&obj);
+        vec.push(MessageElem::TransElemInfo(msg::TransElem {
+            elem_type: Some(24),
+            elem_value: Some(value),
+        }));
+    }
+}
+
+impl fmt::Display for GroupFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[GroupFile: {}]", self.name)
+    }
+}
+
+to_elem_vec_impl!(GroupFile);
+push_builder_impl!(GroupFile);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::elem::RQElem;
+
+    #[test]
+    fn test_group_file_round_trip() {
+        let file = GroupFile {
+            name: "a.txt".into(),
+            size: 1024,
+            file_id: "/abc-def".into(),
+            bus_id: 102,
+        };
+        let mut elems: Vec<MessageElem> = file.into();
+        let RQElem::GroupFile(file) = RQElem::from(elems.remove(0)) else {
+            panic!("expect group file")
+        };
+        assert_eq!(file.name, "a.txt");
+        assert_eq!(file.size, 1024);
+        assert_eq!(file.file_id, "/abc-def");
+        assert_eq!(file.bus_id, 102);
+
+        let truncated = msg::TransElem {
+            elem_type: Some(24),
+            elem_value: Some(vec![1, 0, 10, 0]),
+        };
+        assert!(GroupFile::from_trans_elem(&truncated).is_none());
+    }
+}
@@ -2,15 +2,17 @@ use std::fmt;
 use std::io::{Read, Write};
 
 use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
 
-use super::fmt_extract_attr;
+use super::fmt_attr;
 use crate::msg::{MessageChainBuilder, PushBuilder};
 use crate::msg::{MessageElem, PushElem};
 use crate::pb::msg;
-use crate::{push_builder_impl, to_elem_vec_impl};
+use crate::{push_builder_impl, to_elem_vec_impl, RQError, RQResult};
 
 // Some of the share card message will be a LightApp with pkg id `com.tencent.structmsg`
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct LightApp {
     pub content: String,
 }
@@ -19,6 +21,158 @@ impl LightApp {
     pub fn new(content: String) -> Self {
         Self { content }
     }
+
+    /// 解析 content 中的 json
+    pub fn parse(&self) -> RQResult<LightAppContent> {
+        serde_json::from_str(&self.content).map_err(|e| RQError::Decode(format!("light app: {e}")))
+    }
+
+    pub fn from_content(content: &LightAppContent) -> Self {
+        Self {
+            content: serde_json::to_string(content).unwrap_or_default(),
+        }
+    }
+
+    /// 小程序卡片 (`meta.detail_1`)
+    pub fn mini_program(&self) -> Option<MiniProgram> {
+        self.parse().ok()?.mini_program()
+    }
+
+    /// 音乐分享卡片 (`meta.music`)
+    pub fn music(&self) -> Option<MusicShare> {
+        self.parse().ok()?.music()
+    }
+
+    pub fn from_mini_program(mini_program: &MiniProgram) -> Self {
+        Self::from_content(&LightAppContent::from_mini_program(mini_program))
+    }
+
+    pub fn from_music(music: &MusicShare) -> Self {
+        Self::from_content(&LightAppContent::from_music(music))
+    }
+}
+
+/// LightApp 的 json 内容，未识别的字段保存在 `extra` 中
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LightAppContent {
+    #[serde(default)]
+    pub app: String,
+    #[serde(default)]
+    pub view: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub ver: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub desc: String,
+    #[serde(default)]
+    pub prompt: String,
+    #[serde(default)]
+    pub meta: Map<String, Value>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub config: Map<String, Value>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// 小程序
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MiniProgram {
+    #[serde(default, rename = "appid")]
+    pub app_id: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub desc: String,
+    #[serde(default)]
+    pub icon: String,
+    #[serde(default)]
+    pub preview: String,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default, rename = "qqdocurl")]
+    pub qq_doc_url: String,
+    #[serde(default)]
+    pub host: MiniProgramHost,
+}
+
+/// 分享小程序的用户
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MiniProgramHost {
+    #[serde(default)]
+    pub uin: i64,
+    #[serde(default)]
+    pub nick: String,
+}
+
+/// 音乐分享，LightApp 和 RichMsg 共用
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MusicShare {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub desc: String,
+    /// 封面
+    #[serde(default)]
+    pub preview: String,
+    /// 点击卡片跳转的链接
+    #[serde(default)]
+    pub jump_url: String,
+    /// 音频链接
+    #[serde(default)]
+    pub music_url: String,
+    /// 来源，例如 `QQ音乐`
+    #[serde(default)]
+    pub tag: String,
+    #[serde(default)]
+    pub source_icon: String,
+    #[serde(default, rename = "appid")]
+    pub app_id: i64,
+}
+
+impl LightAppContent {
+    fn meta_object<T: for<'de> Deserialize<'de>>(&self, key: &str) -> Option<T> {
+        serde_json::from_value(self.meta.get(key)?.clone()).ok()
+    }
+
+    pub fn mini_program(&self) -> Option<MiniProgram> {
+        self.meta_object("detail_1")
+    }
+
+    pub fn music(&self) -> Option<MusicShare> {
+        self.meta_object("music")
+    }
+
+    pub fn from_mini_program(mini_program: &MiniProgram) -> Self {
+        let mut meta = Map::new();
+        meta.insert("detail_1".into(), json!(mini_program));
+        Self {
+            app: "com.tencent.miniapp_01".into(),
+            view: "view_8C8E89B49BE609866298ADDFF2DBABA4".into(),
+            ver: "1.0.0.19".into(),
+            desc: mini_program.title.clone(),
+            prompt: format!("[QQ小程序]{}", mini_program.desc),
+            meta,
+            ..Default::default()
+        }
+    }
+
+    pub fn from_music(music: &MusicShare) -> Self {
+        let mut meta = Map::new();
+        meta.insert("music".into(), json!(music));
+        Self {
+            app: "com.tencent.structmsg".into(),
+            view: "music".into(),
+            ver: "0.0.0.1".into(),
+            desc: "音乐".into(),
+            prompt: format!("[分享]{}", music.title),
+            meta,
+            config: json!({"type": "normal", "forward": 1})
+                .as_object()
+                .cloned()
+                .unwrap_or_default(),
+            ..Default::default()
+        }
+    }
 }
 
 impl PushElem for LightApp {
@@ -47,7 +201,7 @@ impl From<msg::LightApp> for LightApp {
                     .ok();
                 uncompressed
             };
-            if !content.is_empty() && content.len() < 1024 ^ 3 {
+            if !content.is_empty() && content.len() < 1024 * 1024 {
                 return Self {
                     content: String::from_utf8_lossy(&content).into_owned(),
                 };
@@ -60,15 +214,56 @@ impl From<msg::LightApp> for LightApp {
 impl fmt::Display for LightApp {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.write_str("[LightApp:")?;
-        fmt_extract_attr(f, &self.content, "app", r#""app":""#, "\"")?;
-        fmt_extract_attr(f, &self.content, "prompt", r#""prompt":""#, "\"")?;
-        fmt_extract_attr(f, &self.content, "desc", r#""desc":""#, "\"")?;
-        fmt_extract_attr(f, &self.content, "url", r#""jumpUrl":""#, "\"")?;
-        fmt_extract_attr(f, &self.content, "title", r#""title":""#, "\"")?;
-        fmt_extract_attr(f, &self.content, "tag", r#""tag":""#, "\"")?;
+        if let Ok(content) = self.parse() {
+            // meta 中通常只有一个对象，例如 news、music、detail_1
+            let detail = content.meta.values().find_map(Value::as_object);
+            let field = |key: &str| detail.and_then(|d| d.get(key)).and_then(Value::as_str);
+            fmt_attr(f, "app", Some(&content.app))?;
+            fmt_attr(f, "prompt", Some(&content.prompt))?;
+            fmt_attr(f, "desc", field("desc"))?;
+            fmt_attr(f, "url", field("jumpUrl").or_else(|| field("qqdocurl")))?;
+            fmt_attr(f, "title", field("title"))?;
+            fmt_attr(f, "tag", field("tag"))?;
+        }
         f.write_str("]")
     }
 }
 
 to_elem_vec_impl!(LightApp);
 push_builder_impl!(LightApp);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_light_app() {
+        let app = LightApp::new(
+            r#"{"app":"com.tencent.miniapp_01","view":"view_8C8E89B49BE609866298ADDFF2DBABA4","prompt":"[QQ小程序]哔哩哔哩","meta":{"detail_1":{"appid":"1109937557","title":"哔哩哔哩","desc":"\"video\"","qqdocurl":"https://b23.tv/x","host":{"uin":10000,"nick":"n"},"shareTemplateId":"8C8E89B49BE609866298ADDFF2DBABA4"}},"extra":{"uin":10000}}"#.into(),
+        );
+        let mini = app.mini_program().unwrap();
+        assert_eq!(mini.app_id, "1109937557");
+        assert_eq!(mini.desc, "\"video\"");
+        assert_eq!(mini.host.uin, 10000);
+        assert!(app.music().is_none());
+        assert!(app.to_string().contains("url='https://b23.tv/x'"));
+
+        // 未识别的字段在重新序列化后保留
+        let content = app.parse().unwrap();
+        assert!(content.extra.contains_key("extra"));
+        let again = LightApp::from_content(&content).parse().unwrap();
+        assert_eq!(again, content);
+
+        let music = MusicShare {
+            title: "song".into(),
+            jump_url: "https://example.com".into(),
+            tag: "QQ音乐".into(),
+            app_id: 100497308,
+            ..Default::default()
+        };
+        let app = LightApp::from_music(&music);
+        assert_eq!(app.music(), Some(music));
+        assert_eq!(app.parse().unwrap().view, "music");
+        assert!(LightApp::new("not json".into()).parse().is_err());
+    }
+}
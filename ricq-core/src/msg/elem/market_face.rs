@@ -1,4 +1,5 @@
 use derivative;
+use serde::{Deserialize, Serialize};
 
 use crate::msg::{MessageChainBuilder, PushBuilder};
 use crate::msg::{MessageElem, PushElem};
@@ -6,7 +7,7 @@ use crate::pb::msg;
 use crate::{push_builder_impl, to_elem_vec_impl};
 
 // 不需要实现 Display，因为后面一定会跟 Text
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct MarketFace {
     pub name: String,
     pub face_id: Vec<u8>,
@@ -56,7 +57,7 @@ impl From<msg::MarketFace> for MarketFace {
     }
 }
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct Dice {
     pub value: i32, // range: [1, 6]
 }
@@ -103,8 +104,9 @@ impl PushElem for Dice {
     }
 }
 
-#[derive(Debug, Clone, derivative::Derivative)]
+#[derive(Debug, Clone, derivative::Derivative, Serialize, Deserialize)]
 #[derivative(Default)]
+#[serde(rename_all = "snake_case")]
 pub enum FingerGuessing {
     #[derivative(Default)]
     Rock,
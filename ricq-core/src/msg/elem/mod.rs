@@ -1,8 +1,11 @@
 use std::fmt;
 
 use prost::Message;
+use serde::{Deserialize, Serialize};
 
+pub(crate) use at::is_at;
 pub use group_image::calculate_image_resource_id;
+pub(crate) use rich_msg::xml_escape;
 pub(crate) use text::flush_builder;
 
 pub use crate::msg::elem::{
@@ -10,22 +13,28 @@ pub use crate::msg::elem::{
     at::At,
     face::Face,
     flash_image::FlashImage,
+    forward::Forward,
     friend_image::FriendImage,
+    group_file::GroupFile,
     group_image::GroupImage,
-    light_app::LightApp,
+    light_app::{LightApp, LightAppContent, MiniProgram, MiniProgramHost, MusicShare},
     market_face::{Dice, FingerGuessing, MarketFace},
     reply::Reply,
-    rich_msg::RichMsg,
+    rich_msg::{RichMsg, StructItem, StructMsg, StructSource, XmlElement},
     text::Text,
     video_file::VideoFile,
 };
+use crate::msg::{MessageChainBuilder, MessageElem, PushBuilder, PushElem};
 use crate::pb::msg;
+use crate::{push_builder_impl, to_elem_vec_impl};
 
 mod anonymous;
 mod at;
 mod face;
 mod flash_image;
+mod forward;
 mod friend_image;
+mod group_file;
 mod group_image;
 mod light_app;
 mod market_face;
@@ -34,7 +43,9 @@ mod rich_msg;
 mod text;
 mod video_file;
 
-#[derive(Debug, Clone)]
+/// 序列化格式为 `{"type": "text", "data": {...}}`，`Other` 的 data 为 base64 编码的 protobuf
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
 pub enum RQElem {
     At(at::At),
     Text(text::Text),
@@ -48,6 +59,9 @@ pub enum RQElem {
     GroupImage(group_image::GroupImage),
     FlashImage(flash_image::FlashImage),
     VideoFile(video_file::VideoFile),
+    Forward(forward::Forward),
+    GroupFile(group_file::GroupFile),
+    #[serde(with = "other_elem")]
     Other(Box<msg::elem::Elem>),
 }
 
@@ -56,7 +70,7 @@ impl From<msg::elem::Elem> for RQElem {
         match elem {
             msg::elem::Elem::Text(e) => {
                 // TODO guild at
-                if !e.attr6_buf().is_empty() {
+                if at::is_at(&e) {
                     RQElem::At(at::At::from(e))
                 } else {
                     RQElem::Text(text::Text::from(e))
@@ -97,12 +111,24 @@ impl From<msg::elem::Elem> for RQElem {
                 }
             }
             msg::elem::Elem::LightApp(e) => RQElem::LightApp(light_app::LightApp::from(e)),
-            msg::elem::Elem::RichMsg(e) => RQElem::RichMsg(rich_msg::RichMsg::from(e)),
+            msg::elem::Elem::RichMsg(e) => {
+                let rich = rich_msg::RichMsg::from(e);
+                match forward::Forward::from_rich_msg(&rich) {
+                    Some(forward) => RQElem::Forward(forward),
+                    None => RQElem::RichMsg(rich),
+                }
+            }
             msg::elem::Elem::VideoFile(e) => RQElem::VideoFile(video_file::VideoFile::from(e)),
             msg::elem::Elem::NotOnlineImage(e) => {
                 RQElem::FriendImage(friend_image::FriendImage::from(e))
             }
             msg::elem::Elem::CustomFace(e) => RQElem::GroupImage(group_image::GroupImage::from(e)),
+            msg::elem::Elem::TransElemInfo(ref e) => {
+                match group_file::GroupFile::from_trans_elem(e) {
+                    Some(file) => RQElem::GroupFile(file),
+                    None => RQElem::Other(Box::new(elem)),
+                }
+            }
             _ => RQElem::Other(Box::new(elem)),
         }
     }
@@ -119,28 +145,71 @@ impl fmt::Display for RQElem {
             RQElem::FlashImage(e) => fmt::Display::fmt(e, f),
             RQElem::LightApp(e) => fmt::Display::fmt(e, f),
             RQElem::RichMsg(e) => fmt::Display::fmt(e, f),
+            RQElem::Forward(e) => fmt::Display::fmt(e, f),
+            RQElem::GroupFile(e) => fmt::Display::fmt(e, f),
             _ => return Ok(()),
         }?;
         f.write_str(" ")
     }
 }
 
-/// Extract a field from xml / json and write to formatter.
-fn fmt_extract_attr(
-    f: &mut fmt::Formatter,
-    i: &str,
-    name: &str,
-    begin: &str,
-    end: &str,
-) -> fmt::Result {
-    if let Some(v) = i
-        .rsplit_once(begin)
-        .and_then(|v| v.1.split_once(end))
-        .map(|v| v.0)
-    {
-        write!(f, " {name}='{v}'")?;
+/// 卡片消息 Display 中的属性，值为空时省略
+pub(crate) fn fmt_attr(f: &mut fmt::Formatter, name: &str, value: Option<&str>) -> fmt::Result {
+    match value {
+        Some(v) if !v.is_empty() => write!(f, " {name}='{v}'"),
+        _ => Ok(()),
+    }
+}
+
+impl PushElem for RQElem {
+    fn push_to(elem: Self, vec: &mut Vec<MessageElem>) {
+        match elem {
+            RQElem::At(e) => At::push_to(e, vec),
+            RQElem::Text(e) => Text::push_to(e, vec),
+            RQElem::Face(e) => Face::push_to(e, vec),
+            RQElem::MarketFace(e) => MarketFace::push_to(e, vec),
+            RQElem::Dice(e) => Dice::push_to(e, vec),
+            RQElem::FingerGuessing(e) => FingerGuessing::push_to(e, vec),
+            RQElem::LightApp(e) => LightApp::push_to(e, vec),
+            RQElem::RichMsg(e) => RichMsg::push_to(e, vec),
+            RQElem::FriendImage(e) => FriendImage::push_to(e, vec),
+            RQElem::GroupImage(e) => GroupImage::push_to(e, vec),
+            RQElem::FlashImage(e) => FlashImage::push_to(e, vec),
+            RQElem::VideoFile(e) => VideoFile::push_to(e, vec),
+            RQElem::Forward(e) => Forward::push_to(e, vec),
+            RQElem::GroupFile(e) => GroupFile::push_to(e, vec),
+            RQElem::Other(e) => vec.push(*e),
+        }
+    }
+}
+
+to_elem_vec_impl!(RQElem);
+push_builder_impl!(RQElem);
+
+/// `RQElem::Other` 以 base64 编码的 protobuf 保存
+mod other_elem {
+    use prost::Message;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    use crate::pb::msg;
+
+    pub fn serialize<S: Serializer>(elem: &msg::elem::Elem, s: S) -> Result<S::Ok, S::Error> {
+        let buf = msg::Elem {
+            elem: Some(elem.clone()),
+        }
+        .encode_to_vec();
+        s.serialize_str(&base64::encode(buf))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Box<msg::elem::Elem>, D::Error> {
+        let s = String::deserialize(d)?;
+        let buf = base64::decode(s).map_err(de::Error::custom)?;
+        msg::Elem::decode(&*buf)
+            .map_err(de::Error::custom)?
+            .elem
+            .map(Box::new)
+            .ok_or_else(|| de::Error::custom("empty elem"))
     }
-    Ok(())
 }
 
 macro_rules! impl_from {
@@ -164,6 +233,8 @@ impl_from!(RichMsg, rich_msg::RichMsg);
 impl_from!(FriendImage, friend_image::FriendImage);
 impl_from!(GroupImage, group_image::GroupImage);
 impl_from!(FlashImage, flash_image::FlashImage);
+impl_from!(Forward, forward::Forward);
+impl_from!(GroupFile, group_file::GroupFile);
 impl_from!(Other, Box<msg::elem::Elem>);
 
 impl From<String> for RQElem {
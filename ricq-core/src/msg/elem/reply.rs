@@ -1,16 +1,43 @@
 use std::fmt;
 
+use prost::Message;
+use serde::{Deserialize, Serialize};
+
 use crate::msg::{MessageChainBuilder, MessageElem, PushBuilder};
 use crate::pb::msg;
+use crate::structs::{FriendMessage, GroupMessage, GroupTempMessage};
 
 use super::super::MessageChain;
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct Reply {
     pub reply_seq: i32,
     pub sender: i64,
     pub time: i32,
     pub elements: MessageChain,
+    /// 原消息的 rand，群消息需要填写，否则部分客户端无法定位原消息
+    #[serde(default)]
+    pub rand: i32,
+}
+
+impl Reply {
+    /// 引用的原消息内容，去掉其中的引用回复和匿名信息
+    pub fn new(reply_seq: i32, rand: i32, sender: i64, time: i32, elements: &MessageChain) -> Self {
+        Self {
+            reply_seq,
+            sender,
+            time,
+            elements: MessageChain(
+                elements
+                    .0
+                    .iter()
+                    .filter(|e| !matches!(e, MessageElem::SrcMsg(_) | MessageElem::AnonGroupMsg(_)))
+                    .cloned()
+                    .collect(),
+            ),
+            rand,
+        }
+    }
 }
 
 impl From<Reply> for MessageElem {
@@ -22,7 +49,15 @@ impl From<Reply> for MessageElem {
             flag: Some(1),
             elems: e.elements.into(),
             rich_msg: Some(vec![]),
-            pb_reserve: Some(vec![]),
+            pb_reserve: Some(if e.rand != 0 {
+                msg::SourceMsgResvAttr {
+                    orig_uids: vec![e.rand as u32 as u64],
+                    ..Default::default()
+                }
+                .encode_to_vec()
+            } else {
+                vec![]
+            }),
             src_msg: Some(vec![]),
             troop_name: Some(vec![]),
             ..Default::default()
@@ -44,14 +79,54 @@ impl PushBuilder for Reply {
 impl From<msg::SourceMsg> for Reply {
     fn from(e: msg::SourceMsg) -> Self {
         Self {
-            reply_seq: e.orig_seqs[0],
+            reply_seq: e.orig_seqs.first().copied().unwrap_or_default(),
             time: e.time(),
             sender: e.sender_uin(),
+            rand: msg::SourceMsgResvAttr::decode(e.pb_reserve())
+                .ok()
+                .and_then(|r| r.orig_uids.first().copied())
+                .unwrap_or_default() as i32,
             elements: MessageChain::from(e.elems),
         }
     }
 }
 
+impl From<&GroupMessage> for Reply {
+    fn from(m: &GroupMessage) -> Self {
+        Self::new(
+            m.seqs.first().copied().unwrap_or_default(),
+            m.rands.first().copied().unwrap_or_default(),
+            m.from_uin,
+            m.time,
+            &m.elements,
+        )
+    }
+}
+
+impl From<&FriendMessage> for Reply {
+    fn from(m: &FriendMessage) -> Self {
+        Self::new(
+            m.seqs.first().copied().unwrap_or_default(),
+            m.rands.first().copied().unwrap_or_default(),
+            m.from_uin,
+            m.time,
+            &m.elements,
+        )
+    }
+}
+
+impl From<&GroupTempMessage> for Reply {
+    fn from(m: &GroupTempMessage) -> Self {
+        Self::new(
+            m.seqs.first().copied().unwrap_or_default(),
+            m.rands.first().copied().unwrap_or_default(),
+            m.from_uin,
+            m.time,
+            &m.elements,
+        )
+    }
+}
+
 impl fmt::Display for Reply {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "[Reply: {}]", self.reply_seq)
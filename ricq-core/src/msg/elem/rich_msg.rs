@@ -4,19 +4,37 @@ use std::io::{Read, Write};
 use flate2::read::ZlibDecoder;
 use flate2::write::ZlibEncoder;
 use flate2::Compression;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
 
-use super::fmt_extract_attr;
+use super::fmt_attr;
+use super::light_app::MusicShare;
 use crate::msg::{MessageChainBuilder, PushBuilder};
 use crate::msg::{MessageElem, PushElem};
 use crate::pb::msg;
-use crate::{push_builder_impl, to_elem_vec_impl};
+use crate::{push_builder_impl, to_elem_vec_impl, RQError, RQResult};
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct RichMsg {
     pub service_id: i32,
     pub template1: String,
 }
 
+impl RichMsg {
+    /// 解析 template1 中的结构化消息
+    pub fn parse(&self) -> RQResult<StructMsg> {
+        StructMsg::parse(&self.template1)
+    }
+
+    pub fn from_struct_msg(msg: &StructMsg) -> Self {
+        Self {
+            service_id: msg.service_id,
+            template1: msg.to_xml(),
+        }
+    }
+}
+
 impl From<msg::RichMsg> for RichMsg {
     fn from(e: msg::RichMsg) -> Self {
         let data = e.template1.unwrap_or_default();
@@ -30,7 +48,7 @@ impl From<msg::RichMsg> for RichMsg {
                     .ok();
                 uncompressed
             };
-            if !content.is_empty() && content.len() < 1024 ^ 3 {
+            if !content.is_empty() && content.len() < 1024 * 1024 {
                 return Self {
                     service_id: e.service_id.unwrap_or_default(),
                     template1: String::from_utf8_lossy(&content).into_owned(),
@@ -58,14 +76,484 @@ impl PushElem for RichMsg {
 impl fmt::Display for RichMsg {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.write_str("[RichMsg:")?;
-        fmt_extract_attr(f, &self.template1, "brief", " brief=\"", "\"")?;
-        fmt_extract_attr(f, &self.template1, "title", "<title>", "</title>")?;
-        fmt_extract_attr(f, &self.template1, "summary", "<summary>", "</summary>")?;
-        fmt_extract_attr(f, &self.template1, "url", " url=\"", "\"")?;
-        fmt_extract_attr(f, &self.template1, "name", " name=\"", "\"")?;
+        if let Ok(msg) = self.parse() {
+            let item = msg.items.first();
+            fmt_attr(f, "brief", Some(&msg.brief))?;
+            fmt_attr(f, "title", item.and_then(|i| i.title()))?;
+            fmt_attr(f, "summary", item.and_then(|i| i.summary()))?;
+            fmt_attr(f, "url", Some(&msg.url))?;
+            fmt_attr(f, "name", msg.source.as_ref().map(|s| s.name.as_str()))?;
+        }
         f.write_str("]")
     }
 }
 
+/// 结构化消息 (`<msg serviceID="..." templateID="...">`)，分享卡片、合并转发等使用
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct StructMsg {
+    pub service_id: i32,
+    pub template_id: i32,
+    pub action: String,
+    pub brief: String,
+    pub url: String,
+    /// msg 上的其他属性，例如 m_resid、tSum
+    pub attrs: Vec<(String, String)>,
+    pub items: Vec<StructItem>,
+    pub source: Option<StructSource>,
+}
+
+/// `<item layout="...">`，包含 title、summary、picture、audio 等元素
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct StructItem {
+    pub layout: i32,
+    pub attrs: Vec<(String, String)>,
+    pub children: Vec<XmlElement>,
+}
+
+/// `<source name="..." icon="..." />`，卡片底部的来源
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct StructSource {
+    pub name: String,
+    pub icon: String,
+    pub url: String,
+    pub action: String,
+    pub appid: i64,
+    pub attrs: Vec<(String, String)>,
+}
+
+/// 通用 xml 元素，只保留元素、属性和文本
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct XmlElement {
+    pub name: String,
+    pub attrs: Vec<(String, String)>,
+    pub text: String,
+    pub children: Vec<XmlElement>,
+}
+
+const XML_HEADER: &str = "<?xml version='1.0' encoding='UTF-8' standalone='yes' ?>";
+
+impl XmlElement {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_attr(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.attrs.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn with_text(mut self, text: impl Into<String>) -> Self {
+        self.text = text.into();
+        self
+    }
+
+    pub fn with_child(mut self, child: XmlElement) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        find_attr(&self.attrs, name)
+    }
+
+    /// 第一个名为 name 的子元素
+    pub fn child(&self, name: &str) -> Option<&XmlElement> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    pub fn parse(xml: &str) -> RQResult<Self> {
+        let mut reader = Reader::from_str(xml);
+        let mut stack: Vec<XmlElement> = Vec::new();
+        loop {
+            let event = reader
+                .read_event()
+                .map_err(|e| RQError::Decode(format!("xml: {e}")))?;
+            match event {
+                Event::Start(e) => stack.push(start_element(&e)?),
+                Event::Empty(e) => {
+                    let element = start_element(&e)?;
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(element),
+                        None => return Ok(element),
+                    }
+                }
+                Event::End(_) => {
+                    let element = stack
+                        .pop()
+                        .ok_or_else(|| RQError::Decode("xml: unexpected end tag".into()))?;
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(element),
+                        None => return Ok(element),
+                    }
+                }
+                Event::Text(e) => {
+                    if let Some(current) = stack.last_mut() {
+                        let text = e
+                            .unescape()
+                            .map(|t| t.into_owned())
+                            .unwrap_or_else(|_| String::from_utf8_lossy(&e).into_owned());
+                        current.text.push_str(&text);
+                    }
+                }
+                Event::CData(e) => {
+                    if let Some(current) = stack.last_mut() {
+                        current.text.push_str(&String::from_utf8_lossy(&e));
+                    }
+                }
+                Event::Eof => return Err(RQError::Decode("xml: unexpected eof".into())),
+                _ => {}
+            }
+        }
+    }
+
+    fn write_to(&self, out: &mut String) {
+        out.push('<');
+        out.push_str(&self.name);
+        write_attrs(
+            out,
+            self.attrs.iter().map(|(k, v)| (k.as_str(), v.as_str())),
+        );
+        if self.text.is_empty() && self.children.is_empty() {
+            out.push_str(" />");
+            return;
+        }
+        out.push('>');
+        out.push_str(&xml_escape(&self.text));
+        for child in &self.children {
+            child.write_to(out);
+        }
+        out.push_str("</");
+        out.push_str(&self.name);
+        out.push('>');
+    }
+
+    pub fn to_xml(&self) -> String {
+        let mut out = String::new();
+        self.write_to(&mut out);
+        out
+    }
+}
+
+impl StructItem {
+    pub fn new(layout: i32) -> Self {
+        Self {
+            layout,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_child(mut self, child: XmlElement) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        find_attr(&self.attrs, name)
+    }
+
+    pub fn child(&self, name: &str) -> Option<&XmlElement> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    /// 所有 title 的文本，合并转发卡片第一个是标题，其余是预览
+    pub fn titles(&self) -> impl Iterator<Item = &str> {
+        self.children
+            .iter()
+            .filter(|c| c.name == "title")
+            .map(|c| c.text.as_str())
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.titles().next()
+    }
+
+    pub fn summary(&self) -> Option<&str> {
+        self.child("summary").map(|c| c.text.as_str())
+    }
+
+    /// 封面，`<picture cover="..." />` 或 `<audio cover="..." />`
+    pub fn cover(&self) -> Option<&str> {
+        self.children
+            .iter()
+            .filter(|c| c.name == "picture" || c.name == "audio")
+            .find_map(|c| c.attr("cover"))
+    }
+}
+
+impl StructMsg {
+    pub fn new(service_id: i32, template_id: i32, action: impl Into<String>) -> Self {
+        Self {
+            service_id,
+            template_id,
+            action: action.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn parse(xml: &str) -> RQResult<Self> {
+        let root = XmlElement::parse(xml)?;
+        if root.name != "msg" {
+            return Err(RQError::Decode(format!(
+                "struct msg: unexpected root <{}>",
+                root.name
+            )));
+        }
+        let mut msg = StructMsg::default();
+        for (k, v) in root.attrs {
+            match k.as_str() {
+                "serviceID" => msg.service_id = v.parse().unwrap_or_default(),
+                "templateID" => msg.template_id = v.parse().unwrap_or_default(),
+                "action" => msg.action = v,
+                "brief" => msg.brief = v,
+                "url" => msg.url = v,
+                _ => msg.attrs.push((k, v)),
+            }
+        }
+        for child in root.children {
+            match child.name.as_str() {
+                "item" => {
+                    let mut item = StructItem {
+                        children: child.children,
+                        ..Default::default()
+                    };
+                    for (k, v) in child.attrs {
+                        match k.as_str() {
+                            "layout" => item.layout = v.parse().unwrap_or_default(),
+                            _ => item.attrs.push((k, v)),
+                        }
+                    }
+                    msg.items.push(item);
+                }
+                "source" => {
+                    let mut source = StructSource::default();
+                    for (k, v) in child.attrs {
+                        match k.as_str() {
+                            "name" => source.name = v,
+                            "icon" => source.icon = v,
+                            "url" => source.url = v,
+                            "action" => source.action = v,
+                            "appid" => source.appid = v.parse().unwrap_or_default(),
+                            _ => source.attrs.push((k, v)),
+                        }
+                    }
+                    msg.source = Some(source);
+                }
+                _ => {}
+            }
+        }
+        Ok(msg)
+    }
+
+    pub fn to_xml(&self) -> String {
+        let mut out = String::from(XML_HEADER);
+        out.push_str("<msg");
+        let (service_id, template_id) = (self.service_id.to_string(), self.template_id.to_string());
+        write_attrs(
+            &mut out,
+            [
+                ("serviceID", service_id.as_str()),
+                ("templateID", template_id.as_str()),
+                ("action", self.action.as_str()),
+                ("brief", self.brief.as_str()),
+                ("url", self.url.as_str()),
+            ]
+            .into_iter()
+            .chain(self.attrs.iter().map(|(k, v)| (k.as_str(), v.as_str()))),
+        );
+        out.push('>');
+        for item in &self.items {
+            out.push_str("<item");
+            let layout = item.layout.to_string();
+            write_attrs(
+                &mut out,
+                std::iter::once(("layout", layout.as_str()))
+                    .chain(item.attrs.iter().map(|(k, v)| (k.as_str(), v.as_str()))),
+            );
+            out.push('>');
+            for child in &item.children {
+                child.write_to(&mut out);
+            }
+            out.push_str("</item>");
+        }
+        if let Some(source) = &self.source {
+            out.push_str("<source");
+            let appid = source.appid.to_string();
+            write_attrs(
+                &mut out,
+                [
+                    ("name", source.name.as_str()),
+                    ("icon", source.icon.as_str()),
+                    ("url", source.url.as_str()),
+                    ("action", source.action.as_str()),
+                    ("appid", appid.as_str()),
+                ]
+                .into_iter()
+                .chain(source.attrs.iter().map(|(k, v)| (k.as_str(), v.as_str()))),
+            );
+            out.push_str(" />");
+        }
+        out.push_str("</msg>");
+        out
+    }
+
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        find_attr(&self.attrs, name)
+    }
+
+    pub fn with_attr(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.attrs.push((name.into(), value.into()));
+        self
+    }
+
+    /// 是否为合并转发/长消息卡片，内容可以用 `Forward` 读取
+    pub fn is_view_multi_msg(&self) -> bool {
+        self.action == "viewMultiMsg"
+    }
+
+    /// 音乐分享卡片 (serviceID 2)
+    pub fn music(&self) -> Option<MusicShare> {
+        let item = self.items.iter().find(|i| i.child("audio").is_some())?;
+        let audio = item.child("audio")?;
+        let source = self.source.clone().unwrap_or_default();
+        Some(MusicShare {
+            title: item.title().unwrap_or_default().into(),
+            desc: item.summary().unwrap_or_default().into(),
+            preview: audio.attr("cover").unwrap_or_default().into(),
+            jump_url: self.url.clone(),
+            music_url: audio.attr("src").unwrap_or_default().into(),
+            tag: source.name,
+            source_icon: source.icon,
+            app_id: source.appid,
+        })
+    }
+
+    pub fn from_music(music: &MusicShare) -> Self {
+        let mut msg = Self::new(2, 1, "web")
+            .with_attr("sourceMsgId", "0")
+            .with_attr("flag", "0")
+            .with_attr("adverSign", "0")
+            .with_attr("multiMsgFlag", "0");
+        msg.brief = format!("[分享]{}", music.title);
+        msg.url = music.jump_url.clone();
+        msg.items.push(
+            StructItem::new(2)
+                .with_child(
+                    XmlElement::new("audio")
+                        .with_attr("cover", &music.preview)
+                        .with_attr("src", &music.music_url),
+                )
+                .with_child(XmlElement::new("title").with_text(&music.title))
+                .with_child(XmlElement::new("summary").with_text(&music.desc)),
+        );
+        msg.source = Some(StructSource {
+            name: music.tag.clone(),
+            icon: music.source_icon.clone(),
+            action: "app".into(),
+            appid: music.app_id,
+            ..Default::default()
+        });
+        msg
+    }
+}
+
+fn find_attr<'a>(attrs: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    attrs
+        .iter()
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v.as_str())
+}
+
+fn start_element(e: &BytesStart) -> RQResult<XmlElement> {
+    let mut element = XmlElement::new(String::from_utf8_lossy(e.name().as_ref()));
+    for attr in e.attributes().with_checks(false) {
+        let attr = attr.map_err(|e| RQError::Decode(format!("xml: {e}")))?;
+        let value = attr
+            .unescape_value()
+            .map(|v| v.into_owned())
+            .unwrap_or_else(|_| String::from_utf8_lossy(&attr.value).into_owned());
+        element.attrs.push((
+            String::from_utf8_lossy(attr.key.as_ref()).into_owned(),
+            value,
+        ));
+    }
+    Ok(element)
+}
+
+fn write_attrs<'a>(out: &mut String, attrs: impl Iterator<Item = (&'a str, &'a str)>) {
+    for (k, v) in attrs {
+        out.push(' ');
+        out.push_str(k);
+        out.push_str("=\"");
+        out.push_str(&xml_escape(v));
+        out.push('"');
+    }
+}
+
+pub(crate) fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
 to_elem_vec_impl!(RichMsg);
 push_builder_impl!(RichMsg);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_struct_msg_round_trip() {
+        let music = MusicShare {
+            title: "a <b> & \"c\"".into(),
+            desc: "desc".into(),
+            preview: "https://example.com/p.jpg?a=1&b=2".into(),
+            jump_url: "https://example.com/j".into(),
+            music_url: "https://example.com/m.mp3".into(),
+            tag: "QQ音乐".into(),
+            source_icon: "".into(),
+            app_id: 100497308,
+        };
+        let msg = StructMsg::from_music(&music);
+        let xml = msg.to_xml();
+        let parsed = StructMsg::parse(&xml).unwrap();
+        assert_eq!(parsed, msg);
+        assert_eq!(parsed.music().unwrap(), music);
+        assert_eq!(parsed.brief, "[分享]a <b> & \"c\"");
+        assert!(RichMsg::from_struct_msg(&msg)
+            .to_string()
+            .contains("title='a <b> & \"c\"'"));
+    }
+
+    #[test]
+    fn test_parse_struct_msg() {
+        let msg = StructMsg::parse(
+            r#"<?xml version="1.0" encoding="utf-8"?><msg serviceID="1" templateID="12345" action="web" brief="[分享] news" url="https://example.com/?a=1&amp;b=2"><item layout="2"><picture cover="https://example.com/c.png"/><title>news</title><summary>some summary</summary></item><source name="app" icon="" action="" appid="-1"/></msg>"#,
+        )
+        .unwrap();
+        assert_eq!(msg.service_id, 1);
+        assert_eq!(msg.template_id, 12345);
+        assert_eq!(msg.url, "https://example.com/?a=1&b=2");
+        let item = &msg.items[0];
+        assert_eq!(item.layout, 2);
+        assert_eq!(item.title(), Some("news"));
+        assert_eq!(item.summary(), Some("some summary"));
+        assert_eq!(item.cover(), Some("https://example.com/c.png"));
+        assert_eq!(msg.source.as_ref().unwrap().appid, -1);
+        assert!(msg.music().is_none());
+        assert!(StructMsg::parse("<msg serviceID=\"1\">").is_err());
+        assert!(StructMsg::parse("{}").is_err());
+    }
+}
@@ -1,10 +1,12 @@
 use std::{fmt, mem};
 
+use serde::{Deserialize, Serialize};
+
 use crate::msg::{MessageChainBuilder, MessageElem, PushBuilder, PushElem};
 use crate::pb::msg;
 use crate::to_elem_vec_impl;
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct Text {
     pub content: String,
 }
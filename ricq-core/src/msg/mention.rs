@@ -0,0 +1,224 @@
+use crate::msg::elem::{is_at, At, Face, RQElem};
+use crate::msg::{MessageChain, MessageElem};
+
+/// [`MessageChain::args`] 拆分出的参数
+#[derive(Debug, Clone)]
+pub enum MessageArg {
+    Text(String),
+    At(At),
+    Face(Face),
+}
+
+impl MessageArg {
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Self::Text(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// At 的目标，@全体成员 为 0
+    pub fn as_at(&self) -> Option<i64> {
+        match self {
+            Self::At(at) => Some(at.target),
+            _ => None,
+        }
+    }
+}
+
+impl MessageChain {
+    /// 不含引用回复、匿名信息的元素
+    fn content_elems(&self) -> impl Iterator<Item = RQElem> + '_ {
+        self.0
+            .iter()
+            .filter(|e| !matches!(e, MessageElem::SrcMsg(_) | MessageElem::AnonGroupMsg(_)))
+            .map(|e| RQElem::from(e.clone()))
+    }
+
+    /// 纯文本内容，忽略 At、表情、图片等元素
+    pub fn plain_text(&self) -> String {
+        self.content_elems()
+            .filter_map(|e| match e {
+                RQElem::Text(t) => Some(t.content),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// 被 At 的 QQ 号，按出现顺序去重，不包含 @全体成员
+    pub fn mentions(&self) -> Vec<i64> {
+        let mut uins = Vec::new();
+        for e in self.content_elems() {
+            if let RQElem::At(at) = e {
+                if at.target != 0 && !uins.contains(&at.target) {
+                    uins.push(at.target);
+                }
+            }
+        }
+        uins
+    }
+
+    /// 是否 @全体成员
+    pub fn mentions_all(&self) -> bool {
+        self.content_elems()
+            .any(|e| matches!(e, RQElem::At(at) if at.target == 0))
+    }
+
+    /// 是否 At 了 uin，不考虑 @全体成员，uin 为 0 时总是 false
+    pub fn is_mentioned(&self, uin: i64) -> bool {
+        uin != 0
+            && self
+                .content_elems()
+                .any(|e| matches!(e, RQElem::At(at) if at.target == uin))
+    }
+
+    /// 去掉开头 At uin 的元素及其后的空白，开头不是 At uin 时返回 None
+    ///
+    /// 开头的空白文本会被跳过，引用回复、匿名信息保留
+    pub fn strip_prefix_mention(&self, uin: i64) -> Option<MessageChain> {
+        let mut elems = Vec::with_capacity(self.0.len());
+        let mut iter = self.0.iter();
+        let mut found = false;
+        for e in iter.by_ref() {
+            if matches!(e, MessageElem::SrcMsg(_) | MessageElem::AnonGroupMsg(_)) {
+                elems.push(e.clone());
+                continue;
+            }
+            match RQElem::from(e.clone()) {
+                RQElem::Text(t) if t.content.trim().is_empty() => {}
+                RQElem::At(at) if at.target == uin => {
+                    found = true;
+                    break;
+                }
+                _ => return None,
+            }
+        }
+        if !found {
+            return None;
+        }
+        let mut trimming = true;
+        for e in iter {
+            if trimming {
+                match e {
+                    MessageElem::Text(t) if !is_at(t) => {
+                        let rest = t.str().trim_start();
+                        if rest.is_empty() {
+                            continue;
+                        }
+                        let mut t = t.clone();
+                        t.str = Some(rest.to_owned());
+                        elems.push(MessageElem::Text(t));
+                        trimming = false;
+                        continue;
+                    }
+                    _ => trimming = false,
+                }
+            }
+            elems.push(e.clone());
+        }
+        Some(MessageChain(elems))
+    }
+
+    /// 按空白拆分为参数，At 和表情单独作为一个参数
+    ///
+    /// 双引号内的文本不拆分，例如 `echo "a b"` 拆分为 `echo`、`a b`
+    pub fn args(&self) -> Vec<MessageArg> {
+        let mut args = Vec::new();
+        let mut buf = String::new();
+        let mut quoted = false;
+        // 引号内可能为空字符串，用 pending 记录是否需要输出
+        let mut pending = false;
+        let flush = |buf: &mut String, pending: &mut bool, args: &mut Vec<MessageArg>| {
+            if *pending || !buf.is_empty() {
+                args.push(MessageArg::Text(std::mem::take(buf)));
+            }
+            *pending = false;
+        };
+        for e in self.content_elems() {
+            match e {
+                RQElem::Text(t) => {
+                    for c in t.content.chars() {
+                        match c {
+                            '"' => {
+                                quoted = !quoted;
+                                pending = true;
+                            }
+                            c if c.is_whitespace() && !quoted => {
+                                flush(&mut buf, &mut pending, &mut args)
+                            }
+                            c => buf.push(c),
+                        }
+                    }
+                }
+                RQElem::At(at) => {
+                    flush(&mut buf, &mut pending, &mut args);
+                    args.push(MessageArg::At(at));
+                }
+                RQElem::Face(face) => {
+                    flush(&mut buf, &mut pending, &mut args);
+                    args.push(MessageArg::Face(face));
+                }
+                _ => {}
+            }
+        }
+        flush(&mut buf, &mut pending, &mut args);
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::elem::{Reply, Text};
+
+    fn chain() -> MessageChain {
+        let mut chain = MessageChain::default();
+        chain.with_reply(Reply::default());
+        chain.push(Text::new(" ".into()));
+        chain.push(At::new(10000));
+        chain.push(Text::new("  echo \"a b\" x".into()));
+        chain.push(At::new(20000));
+        chain.push(Face::new(1));
+        chain.push(At::new(0));
+        chain.push(At::new(10000));
+        chain
+    }
+
+    #[test]
+    fn test_mentions() {
+        let chain = chain();
+        assert_eq!(chain.plain_text(), "   echo \"a b\" x");
+        assert_eq!(chain.mentions(), vec![10000, 20000]);
+        assert!(chain.mentions_all());
+        assert!(chain.is_mentioned(20000));
+        assert!(!chain.is_mentioned(30000));
+        assert!(!chain.is_mentioned(0));
+
+        assert!(chain.strip_prefix_mention(20000).is_none());
+        let stripped = chain.strip_prefix_mention(10000).unwrap();
+        assert!(stripped.reply().is_some());
+        assert!(stripped.plain_text().starts_with("echo"));
+        assert_eq!(stripped.mentions(), vec![20000, 10000]);
+
+        let args = stripped.args();
+        let texts: Vec<_> = args.iter().filter_map(MessageArg::as_text).collect();
+        assert_eq!(texts, vec!["echo", "a b", "x"]);
+        assert_eq!(args[3].as_at(), Some(20000));
+        assert!(matches!(&args[4], MessageArg::Face(f) if f.index == 1));
+        assert_eq!(args[5].as_at(), Some(0));
+        assert_eq!(args.len(), 7);
+    }
+
+    #[test]
+    fn test_truncated_at() {
+        // attr6 过短时按普通文本处理，不能当作 @全体成员
+        let chain = MessageChain(vec![MessageElem::Text(crate::pb::msg::Text {
+            str: Some("@someone".into()),
+            attr6_buf: Some(vec![0, 1, 0, 0, 0, 8, 0]),
+            ..Default::default()
+        })]);
+        assert!(!chain.mentions_all());
+        assert!(chain.mentions().is_empty());
+        assert_eq!(chain.plain_text(), "@someone");
+    }
+}
@@ -5,9 +5,18 @@ use elem::*;
 
 use crate::pb::msg;
 
+mod code;
 pub mod elem;
 mod fragment;
 mod macros;
+mod mention;
+mod repr;
+mod split;
+
+pub use mention::MessageArg;
+pub(crate) use repr::FORWARD_PLACEHOLDER;
+pub use repr::MESSAGE_CHAIN_VERSION;
+pub use split::{SendMode, SendPlan, MAX_MESSAGE_SIZE, MAX_SPLIT_PARTS};
 
 pub type MessageElem = msg::elem::Elem;
 
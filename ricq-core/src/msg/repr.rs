@@ -0,0 +1,201 @@
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::msg::elem::{Anonymous, RQElem, Reply};
+use crate::msg::MessageChain;
+
+/// [`MessageChain`] 序列化格式的版本号，格式不兼容时递增
+pub const MESSAGE_CHAIN_VERSION: u32 = 1;
+
+const VIDEO_PLACEHOLDER: &str = "你的QQ暂不支持查看视频短片，请期待后续版本。";
+const FLASH_IMAGE_PLACEHOLDER: &str = "[闪照]请使用新版手机QQ查看闪照。";
+pub(crate) const FORWARD_PLACEHOLDER: &str = "你的QQ暂不支持查看[转发多条消息]，请期待后续版本。";
+
+/// [`MessageChain`] 的序列化格式
+///
+/// ```json
+/// {"version": 1, "reply": {...}, "elems": [{"type": "text", "data": {"content": "hello"}}]}
+/// ```
+#[derive(Serialize, Deserialize)]
+struct MessageChainRepr {
+    version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    anonymous: Option<Anonymous>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reply: Option<Reply>,
+    elems: Vec<RQElem>,
+}
+
+impl Serialize for MessageChain {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        MessageChainRepr {
+            version: MESSAGE_CHAIN_VERSION,
+            anonymous: self.anonymous(),
+            reply: self.reply(),
+            elems: strip_placeholders(self.clone()),
+        }
+        .serialize(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for MessageChain {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let repr = MessageChainRepr::deserialize(d)?;
+        if repr.version > MESSAGE_CHAIN_VERSION {
+            return Err(de::Error::custom(format!(
+                "unsupported message chain version: {}",
+                repr.version
+            )));
+        }
+        let mut chain: MessageChain = repr.elems.into_iter().collect();
+        if let Some(reply) = repr.reply {
+            chain.with_reply(reply);
+        }
+        if let Some(anonymous) = repr.anonymous {
+            chain.with_anonymous(anonymous);
+        }
+        Ok(chain)
+    }
+}
+
+/// 去掉发送时自动附带的兼容文本，反序列化后重新 push 时会再次生成
+pub(crate) fn strip_placeholders(chain: MessageChain) -> Vec<RQElem> {
+    let mut elems: Vec<RQElem> = Vec::new();
+    // 每个元素最多只带一个兼容文本，去掉后不再匹配后面的文本
+    let mut stripped = false;
+    for elem in chain {
+        match (&elem, elems.last()) {
+            (RQElem::Text(text), Some(prev))
+                if !stripped && is_placeholder(prev, &text.content) =>
+            {
+                stripped = true;
+                continue;
+            }
+            (RQElem::VideoFile(_), Some(RQElem::Text(text)))
+                if text.content == VIDEO_PLACEHOLDER =>
+            {
+                elems.pop();
+            }
+            _ => {}
+        }
+        stripped = false;
+        elems.push(elem);
+    }
+    elems
+}
+
+fn is_placeholder(prev: &RQElem, text: &str) -> bool {
+    match prev {
+        RQElem::MarketFace(face) => face.name == text,
+        RQElem::Dice(_) => matches!(text, "[骰子]" | "[随机骰子]"),
+        RQElem::FingerGuessing(_) => text == "[猜拳]",
+        RQElem::FlashImage(_) => text == FLASH_IMAGE_PLACEHOLDER,
+        RQElem::Forward(_) => text == FORWARD_PLACEHOLDER,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::elem::*;
+    use crate::msg::MessageElem;
+    use crate::pb::msg;
+
+    #[test]
+    fn test_round_trip() {
+        let mut chain = MessageChain::default();
+        chain.push(Text::new("hello".into()));
+        chain.push(At::new(12345));
+        chain.push(Face::new(1));
+        chain.push(Face::new(300));
+        chain.push(Dice::new(3));
+        chain.push(FingerGuessing::Paper);
+        chain.push(MarketFace {
+            name: "[xx]".into(),
+            face_id: vec![1, 2, 3],
+            ..Default::default()
+        });
+        chain.push(LightApp::new("{\"app\":\"test\"}".into()));
+        chain.push(RichMsg {
+            service_id: 35,
+            template1: "<msg/>".into(),
+        });
+        chain.push(GroupImage::default().flash());
+        chain.push(VideoFile {
+            name: "d41d8cd98f00b204e9800998ecf8427e.mp4".into(),
+            md5: vec![
+                0xd4, 0x1d, 0x8c, 0xd9, 0x8f, 0x00, 0xb2, 0x04, 0xe9, 0x80, 0x09, 0x98, 0xec, 0xf8,
+                0x42, 0x7e,
+            ],
+            ..Default::default()
+        });
+        chain.push(RQElem::Other(Box::new(MessageElem::GeneralFlags(
+            msg::GeneralFlags {
+                pendant_id: Some(1),
+                ..Default::default()
+            },
+        ))));
+        chain.with_reply(Reply {
+            reply_seq: 1,
+            sender: 10000,
+            time: 100,
+            elements: MessageChain::new(Text::new("origin".into())),
+            ..Default::default()
+        });
+        chain.with_anonymous(Anonymous {
+            nick: "anon".into(),
+            ..Default::default()
+        });
+
+        let value = serde_json::to_value(&chain).unwrap();
+        assert_eq!(value["version"], 1);
+        assert_eq!(value["elems"][0]["type"], "text");
+        assert_eq!(value["elems"][4]["type"], "dice");
+        assert!(value["elems"][11]["data"].is_string());
+        assert_eq!(value["elems"].as_array().unwrap().len(), 12);
+
+        let back: MessageChain = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(back.0, chain.0);
+        assert_eq!(serde_json::to_value(&back).unwrap(), value);
+    }
+
+    #[test]
+    fn test_keep_user_text() {
+        let mut chain = MessageChain::default();
+        chain.push(Dice::new(3));
+        chain.push(Text::new("[骰子]".into()));
+        chain.push(GroupImage::default().flash());
+        chain.push(Text::new("[闪照]".into()));
+        let value = serde_json::to_value(&chain).unwrap();
+        let types: Vec<_> = value["elems"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["type"].as_str().unwrap())
+            .collect();
+        assert_eq!(types, vec!["dice", "text", "flash_image", "text"]);
+        let back: MessageChain = serde_json::from_value(value).unwrap();
+        assert_eq!(back.0, chain.0);
+
+        // 只去掉完全相同的兼容文本
+        let template = crate::command::multi_msg::gen_long_message_template("id", "f", "brief");
+        let mut chain = crate::command::multi_msg::long_message_chain(template, "id".into());
+        chain.push(Text::new("你的QQ暂不支持查看这条消息".into()));
+        let elems = strip_placeholders(chain);
+        assert!(matches!(&elems[0], RQElem::Forward(f) if f.is_long));
+        let texts: Vec<_> = elems
+            .iter()
+            .filter_map(|e| match e {
+                RQElem::Text(t) => Some(t.content.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(texts, vec!["你的QQ暂不支持查看这条消息"]);
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let result = serde_json::from_str::<MessageChain>(r#"{"version":99,"elems":[]}"#);
+        assert!(result.is_err());
+    }
+}
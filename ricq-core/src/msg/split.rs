@@ -0,0 +1,238 @@
+use prost::encoding::encoded_len_varint;
+
+use crate::msg::elem::{is_at, RQElem};
+use crate::msg::{MessageChain, MessageElem};
+use crate::pb::msg;
+
+/// 单条消息 elems 编码后的最大长度，超过后服务器会拒绝或截断
+pub const MAX_MESSAGE_SIZE: usize = 4500;
+/// 自动模式下最多拆分为几条消息，更多时改为长消息
+pub const MAX_SPLIT_PARTS: usize = 3;
+/// 长消息卡片 brief 的最大字数
+const MAX_BRIEF_CHARS: usize = 30;
+
+/// 发送消息的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SendMode {
+    /// 未超过大小限制时直接发送，否则在拆分后不超过 [`MAX_SPLIT_PARTS`] 条时拆分发送，
+    /// 仍然过大时作为长消息上传
+    #[default]
+    Auto,
+    /// 直接发送
+    Direct,
+    /// 拆分为多条消息，无法拆分时作为长消息上传
+    Split,
+    /// 作为长消息上传
+    LongMessage,
+}
+
+/// [`MessageChain::plan_send`] 的结果
+#[derive(Debug, Clone)]
+pub enum SendPlan {
+    Direct(MessageChain),
+    Split(Vec<MessageChain>),
+    LongMessage(MessageChain),
+}
+
+fn elem_len(e: &MessageElem) -> usize {
+    // RichText.elems 为 repeated Elem，tag 占 1 字节
+    let len = e.encoded_len();
+    1 + encoded_len_varint(len as u64) + len
+}
+
+// 每条消息都需要带上的元素，引用回复只放在第一条
+fn is_prefix(e: &MessageElem) -> bool {
+    matches!(e, MessageElem::AnonGroupMsg(_))
+}
+
+impl MessageChain {
+    /// 编码后的大小
+    pub fn encoded_len(&self) -> usize {
+        self.0.iter().map(elem_len).sum()
+    }
+
+    /// 消息摘要，用作长消息卡片的 brief，图片等元素显示为 `[图片]`
+    pub fn brief(&self) -> String {
+        let mut brief = String::new();
+        for e in self.clone() {
+            match e {
+                RQElem::Text(t) => brief.push_str(&t.content),
+                RQElem::At(at) => brief.push_str(&at.display),
+                RQElem::Face(face) => brief.push_str(&format!("[{}]", face.name)),
+                RQElem::MarketFace(_) | RQElem::Dice(_) | RQElem::FingerGuessing(_) => {
+                    brief.push_str("[表情]")
+                }
+                RQElem::GroupImage(_) | RQElem::FriendImage(_) => brief.push_str("[图片]"),
+                RQElem::FlashImage(_) => brief.push_str("[闪照]"),
+                RQElem::VideoFile(_) => brief.push_str("[视频]"),
+                RQElem::Forward(_) => brief.push_str("[聊天记录]"),
+                RQElem::GroupFile(f) => brief.push_str(&format!("[文件]{}", f.name)),
+                RQElem::LightApp(_) | RQElem::RichMsg(_) => brief.push_str("[卡片]"),
+                RQElem::Other(_) => {}
+            }
+        }
+        let brief = brief.split_whitespace().collect::<Vec<_>>().join(" ");
+        if brief.chars().count() > MAX_BRIEF_CHARS {
+            let mut s: String = brief.chars().take(MAX_BRIEF_CHARS).collect();
+            s.push('…');
+            s
+        } else {
+            brief
+        }
+    }
+
+    /// 拆分为编码后不超过 max_size 的多条消息，优先在文本元素之间拆分，单个文本过长时在换行处拆分
+    ///
+    /// 图片等无法拆分的元素超过限制时返回 None
+    pub fn split(&self, max_size: usize) -> Option<Vec<MessageChain>> {
+        let prefix: Vec<MessageElem> = self.0.iter().filter(|e| is_prefix(e)).cloned().collect();
+        let prefix_len: usize = prefix.iter().map(elem_len).sum();
+        let budget = max_size.checked_sub(prefix_len)?;
+        let mut parts = Vec::new();
+        let mut current: Vec<MessageElem> = Vec::new();
+        let mut current_len = 0;
+        let flush = |current: &mut Vec<MessageElem>,
+                     current_len: &mut usize,
+                     parts: &mut Vec<MessageChain>| {
+            if !current.is_empty() {
+                let mut elems = prefix.clone();
+                elems.append(current);
+                parts.push(MessageChain(elems));
+                *current_len = 0;
+            }
+        };
+        for e in self.0.iter().filter(|e| !is_prefix(e)) {
+            let len = elem_len(e);
+            if current_len + len <= budget {
+                current.push(e.clone());
+                current_len += len;
+                continue;
+            }
+            let text = match e {
+                MessageElem::Text(t) if !is_at(t) => t.str(),
+                _ => {
+                    if len > budget {
+                        return None;
+                    }
+                    flush(&mut current, &mut current_len, &mut parts);
+                    current.push(e.clone());
+                    current_len = len;
+                    continue;
+                }
+            };
+            let mut rest = text;
+            while !rest.is_empty() {
+                // 预留 Elem、Text 的 tag 和长度
+                let available = budget.saturating_sub(current_len + 16);
+                let cut = split_text_at(rest, available);
+                if cut == 0 {
+                    if current.is_empty() {
+                        return None;
+                    }
+                    flush(&mut current, &mut current_len, &mut parts);
+                    continue;
+                }
+                let elem = MessageElem::Text(msg::Text {
+                    str: Some(rest[..cut].to_owned()),
+                    ..Default::default()
+                });
+                current_len += elem_len(&elem);
+                current.push(elem);
+                rest = &rest[cut..];
+                if !rest.is_empty() {
+                    flush(&mut current, &mut current_len, &mut parts);
+                }
+            }
+        }
+        flush(&mut current, &mut current_len, &mut parts);
+        Some(parts)
+    }
+
+    /// 根据大小选择发送方式
+    pub fn plan_send(self, mode: SendMode) -> SendPlan {
+        let fits = self.encoded_len() <= MAX_MESSAGE_SIZE;
+        match mode {
+            SendMode::Direct => SendPlan::Direct(self),
+            SendMode::LongMessage => SendPlan::LongMessage(self),
+            _ if fits => SendPlan::Direct(self),
+            SendMode::Split => match self.split(MAX_MESSAGE_SIZE) {
+                Some(parts) => SendPlan::Split(parts),
+                None => SendPlan::LongMessage(self),
+            },
+            SendMode::Auto => match self.split(MAX_MESSAGE_SIZE) {
+                Some(parts) if parts.len() <= MAX_SPLIT_PARTS => SendPlan::Split(parts),
+                _ => SendPlan::LongMessage(self),
+            },
+        }
+    }
+}
+
+/// 不超过 max_bytes 的切分位置，尽量落在后半段的换行处
+fn split_text_at(s: &str, max_bytes: usize) -> usize {
+    if s.len() <= max_bytes {
+        return s.len();
+    }
+    let mut cut = max_bytes;
+    while !s.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    match s[..cut].rfind('\n') {
+        Some(i) if i + 1 > cut / 2 => i + 1,
+        _ => cut,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::elem::{At, GroupImage, Reply, Text};
+
+    #[test]
+    fn test_split() {
+        let mut chain = MessageChain::default();
+        chain.with_reply(Reply::default());
+        chain.push(At::new(10000));
+        chain.push(Text::new(format!(
+            "{}\n{}",
+            "一".repeat(1000),
+            "b".repeat(2000)
+        )));
+        chain.push(GroupImage::default());
+        assert!(chain.encoded_len() > MAX_MESSAGE_SIZE);
+        assert!(chain.brief().starts_with("@10000一"));
+        assert!(chain.brief().ends_with('…'));
+
+        let parts = chain.split(MAX_MESSAGE_SIZE).unwrap();
+        assert_eq!(parts.len(), 2);
+        assert!(parts.iter().all(|p| p.encoded_len() <= MAX_MESSAGE_SIZE));
+        assert!(parts[0].reply().is_some());
+        assert!(parts[1].reply().is_none());
+        // 在换行处拆分，内容不丢失
+        assert!(parts[0].plain_text().ends_with('\n'));
+        let text: String = parts.iter().map(|p| p.plain_text()).collect();
+        assert_eq!(text, format!("{}\n{}", "一".repeat(1000), "b".repeat(2000)));
+        assert!(
+            matches!(chain.clone().plan_send(SendMode::Auto), SendPlan::Split(p) if p.len() == 2)
+        );
+
+        let long = MessageChain::new(Text::new("a".repeat(MAX_MESSAGE_SIZE * 4)));
+        assert_eq!(long.split(MAX_MESSAGE_SIZE).unwrap().len(), 5);
+        assert!(matches!(
+            long.clone().plan_send(SendMode::Auto),
+            SendPlan::LongMessage(_)
+        ));
+        assert!(matches!(
+            long.plan_send(SendMode::Split),
+            SendPlan::Split(_)
+        ));
+
+        let short = MessageChain::new(Text::new("hello".into()));
+        assert!(matches!(
+            short.plan_send(SendMode::Auto),
+            SendPlan::Direct(_)
+        ));
+        assert!(MessageChain::new(Text::new("a".repeat(100)))
+            .split(10)
+            .is_none());
+    }
+}
@@ -19,6 +19,7 @@ add_includes!(
     cmd0x3bb,
     cmd0x6ff,
     cmd0x899,
+    exciting,
     longmsg,
     msf,
     msg,
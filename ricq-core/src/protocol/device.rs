@@ -1,9 +1,11 @@
 use bytes::Bytes;
 use rand::distributions::DistString;
-use rand::{distributions::Alphanumeric, Rng, RngCore};
+use rand::{distributions::Alphanumeric, Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 use serde::{Deserialize, Serialize};
 
-use crate::hex::encode_hex;
+use crate::hex::{decode_hex, encode_hex};
+use crate::{RQError, RQResult};
 
 //系统版本
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -59,6 +61,13 @@ impl Device {
         Self::random_with_rng(&mut rand::thread_rng())
     }
 
+    /// 根据 uin 生成固定的设备信息，同一个账号每次生成的设备相同
+    ///
+    /// 使用 ChaCha20，结果不随 rand 版本变化
+    pub fn from_seed(uin: i64) -> Self {
+        Self::random_with_rng(&mut ChaCha20Rng::seed_from_u64(uin as u64))
+    }
+
     pub fn random_with_rng<RNG: RngCore>(rng: &mut RNG) -> Self {
         Self {
             display: format!("RICQ.{}.001", rng.gen_range(100000..999999)),
@@ -103,6 +112,239 @@ impl Device {
     }
 }
 
+/// 设备文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceFormat {
+    /// go-cqhttp / MiraiGo 的 device.json
+    GoCqHttp,
+    /// mirai 的 device.json (deviceInfoVersion 2)
+    Mirai,
+}
+
+impl Device {
+    /// 从 device.json 导入，自动识别 go-cqhttp、mirai (v1/v2) 和 ricq 自身的格式
+    pub fn from_json(s: &str) -> RQResult<Self> {
+        let mut value: serde_json::Value =
+            serde_json::from_str(s).map_err(|e| RQError::Decode(format!("device json: {e}")))?;
+        // mirai v2 的设备信息在 data 中
+        if value.get("deviceInfoVersion").is_some() {
+            value = value
+                .get_mut("data")
+                .map(serde_json::Value::take)
+                .ok_or(RQError::EmptyField("data"))?;
+        }
+        let file: DeviceFile = serde_json::from_value(value)
+            .map_err(|e| RQError::Decode(format!("device json: {e}")))?;
+        file.try_into()
+    }
+
+    /// 导出为 go-cqhttp 或 mirai 的 device.json
+    pub fn to_json(&self, format: DeviceFormat) -> RQResult<String> {
+        let value = match format {
+            DeviceFormat::GoCqHttp => serde_json::json!({
+                "display": self.display,
+                "product": self.product,
+                "device": self.device,
+                "board": self.board,
+                "model": self.model,
+                "finger_print": self.finger_print,
+                "boot_id": self.boot_id,
+                "proc_version": self.proc_version,
+                "imei": self.imei,
+                "brand": self.brand,
+                "bootloader": self.bootloader,
+                "base_band": self.base_band,
+                "version": self.version,
+                "sim_info": self.sim_info,
+                "os_type": self.os_type,
+                "mac_address": self.mac_address,
+                "ip_address": self.ip_address,
+                "wifi_bssid": self.wifi_bssid,
+                "wifi_ssid": self.wifi_ssid,
+                "imsi_md5": encode_hex(&self.imsi_md5),
+                "android_id": self.android_id,
+                "apn": self.apn,
+                "vendor_name": self.vendor_name,
+                "vendor_os_name": self.vendor_os_name,
+            }),
+            DeviceFormat::Mirai => serde_json::json!({
+                "deviceInfoVersion": 2,
+                "data": {
+                    "display": self.display,
+                    "product": self.product,
+                    "device": self.device,
+                    "board": self.board,
+                    "brand": self.brand,
+                    "model": self.model,
+                    "bootloader": self.bootloader,
+                    "fingerprint": self.finger_print,
+                    "bootId": self.boot_id,
+                    "procVersion": self.proc_version,
+                    "baseBand": self.base_band,
+                    "version": self.version,
+                    "simInfo": self.sim_info,
+                    "osType": self.os_type,
+                    "macAddress": self.mac_address,
+                    "wifiBSSID": self.wifi_bssid,
+                    "wifiSSID": self.wifi_ssid,
+                    "imsiMd5": encode_hex(&self.imsi_md5),
+                    "imei": self.imei,
+                    "apn": self.apn,
+                    "androidId": self.android_id,
+                }
+            }),
+        };
+        serde_json::to_string_pretty(&value)
+            .map_err(|e| RQError::Other(format!("device json: {e}")))
+    }
+}
+
+// 兼容 go-cqhttp (snake_case)、mirai (camelCase) 的字段名，
+// mirai v1 的字符串字段是 utf-8 字节数组
+#[derive(Deserialize)]
+struct DeviceFile {
+    display: Text,
+    product: Text,
+    device: Text,
+    board: Text,
+    model: Text,
+    #[serde(alias = "fingerprint")]
+    finger_print: Text,
+    #[serde(alias = "bootId")]
+    boot_id: Text,
+    #[serde(alias = "procVersion")]
+    proc_version: Text,
+    imei: Text,
+    brand: Text,
+    bootloader: Text,
+    #[serde(default, alias = "baseBand")]
+    base_band: Text,
+    #[serde(alias = "os_version")]
+    version: OSVersionFile,
+    #[serde(default, alias = "simInfo")]
+    sim_info: Option<Text>,
+    #[serde(default, alias = "osType")]
+    os_type: Option<Text>,
+    #[serde(default, alias = "macAddress")]
+    mac_address: Option<Text>,
+    #[serde(default, alias = "ipAddress")]
+    ip_address: Option<Binary>,
+    #[serde(default, alias = "wifiBSSID")]
+    wifi_bssid: Option<Text>,
+    #[serde(default, alias = "wifiSSID")]
+    wifi_ssid: Option<Text>,
+    #[serde(alias = "imsiMd5")]
+    imsi_md5: Binary,
+    #[serde(default, alias = "androidId")]
+    android_id: Option<Text>,
+    #[serde(default)]
+    apn: Option<Text>,
+    #[serde(default, alias = "vendorName")]
+    vendor_name: Option<Text>,
+    #[serde(default, alias = "vendorOsName")]
+    vendor_os_name: Option<Text>,
+}
+
+#[derive(Deserialize)]
+struct OSVersionFile {
+    incremental: Text,
+    release: Text,
+    codename: Text,
+    sdk: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Text {
+    String(String),
+    Bytes(Vec<u8>),
+}
+
+impl Default for Text {
+    fn default() -> Self {
+        Text::String(String::new())
+    }
+}
+
+impl From<Text> for String {
+    fn from(t: Text) -> String {
+        match t {
+            Text::String(s) => s,
+            Text::Bytes(b) => String::from_utf8_lossy(&b).into_owned(),
+        }
+    }
+}
+
+// 字节数组或 hex 字符串，ip 地址也可以是 `10.0.1.3`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Binary {
+    Bytes(Vec<u8>),
+    String(String),
+}
+
+impl Binary {
+    fn into_bytes(self, field: &str) -> RQResult<Vec<u8>> {
+        let s = match self {
+            Binary::Bytes(b) => return Ok(b),
+            Binary::String(s) => s,
+        };
+        if let Ok(ip) = s.parse::<std::net::Ipv4Addr>() {
+            return Ok(ip.octets().to_vec());
+        }
+        if !s.is_ascii() || !s.len().is_multiple_of(2) {
+            return Err(RQError::Decode(format!("invalid {field}: {s}")));
+        }
+        decode_hex(&s).map_err(|e| RQError::Decode(format!("invalid {field}: {e}")))
+    }
+}
+
+impl TryFrom<DeviceFile> for Device {
+    type Error = RQError;
+
+    fn try_from(f: DeviceFile) -> RQResult<Self> {
+        let text = |t: Option<Text>, default: &str| t.map(String::from).unwrap_or(default.into());
+        let device = Device {
+            display: f.display.into(),
+            product: f.product.into(),
+            device: f.device.into(),
+            board: f.board.into(),
+            model: f.model.into(),
+            finger_print: f.finger_print.into(),
+            boot_id: f.boot_id.into(),
+            proc_version: f.proc_version.into(),
+            imei: f.imei.into(),
+            brand: f.brand.into(),
+            bootloader: f.bootloader.into(),
+            base_band: f.base_band.into(),
+            version: OSVersion {
+                incremental: f.version.incremental.into(),
+                release: f.version.release.into(),
+                codename: f.version.codename.into(),
+                sdk: f.version.sdk,
+            },
+            sim_info: text(f.sim_info, "T-Mobile"),
+            os_type: text(f.os_type, "android"),
+            mac_address: text(f.mac_address, "00:50:56:C0:00:08"),
+            ip_address: match f.ip_address {
+                Some(ip) => ip.into_bytes("ip_address")?,
+                None => vec![10, 0, 1, 3],
+            },
+            wifi_bssid: text(f.wifi_bssid, "00:50:56:C0:00:08"),
+            wifi_ssid: text(f.wifi_ssid, "<unknown ssid>"),
+            imsi_md5: f.imsi_md5.into_bytes("imsi_md5")?,
+            android_id: text(f.android_id, ""),
+            apn: text(f.apn, "wifi"),
+            vendor_name: text(f.vendor_name, "MIUI"),
+            vendor_os_name: text(f.vendor_os_name, "ricq"),
+        };
+        if device.imei.is_empty() {
+            return Err(RQError::EmptyField("imei"));
+        }
+        Ok(device)
+    }
+}
+
 pub fn random_string(len: usize) -> String {
     Alphanumeric.sample_string(&mut rand::thread_rng(), len)
 }
@@ -137,3 +379,87 @@ pub fn random_imei<RNG: RngCore>(rng: &mut RNG) -> String {
     str.push_str(&ctrl_digit.to_string());
     str
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_seed() {
+        let a = Device::from_seed(10000);
+        assert_eq!(a.imei, Device::from_seed(10000).imei);
+        assert_eq!(a.android_id, Device::from_seed(10000).android_id);
+        assert_ne!(a.imei, Device::from_seed(10001).imei);
+
+        // 固定值，防止 rng 实现变化导致同一账号的设备改变
+        assert_eq!(a.display, "RICQ.579397.001");
+        assert_eq!(
+            a.finger_print,
+            "xiaomi/iarim/sagit:10/eomam.200122.001/4282837:user/release-keys"
+        );
+        assert_eq!(a.boot_id, "4d914b89-e1b4-dca3-d481-7b5cab7c9df9");
+        assert_eq!(a.imei, "929430129443424");
+        assert_eq!(
+            a.proc_version,
+            "Linux 5.4.0-54-generic-Rcxk7HMG (android-build@google.com)"
+        );
+        assert_eq!(encode_hex(&a.imsi_md5), "8a5ece1267f0f67f323d200c081d81f4");
+        assert_eq!(a.android_id, "183ee19c5f3dffb2");
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let device = Device::from_seed(10000);
+        for format in [DeviceFormat::GoCqHttp, DeviceFormat::Mirai] {
+            let d = Device::from_json(&device.to_json(format).unwrap()).unwrap();
+            assert_eq!(d.imei, device.imei);
+            assert_eq!(d.finger_print, device.finger_print);
+            assert_eq!(d.imsi_md5, device.imsi_md5);
+            assert_eq!(d.android_id, device.android_id);
+            assert_eq!(d.mac_address, device.mac_address);
+            assert_eq!(d.version.sdk, device.version.sdk);
+        }
+        let own = serde_json::to_string(&device).unwrap();
+        assert_eq!(Device::from_json(&own).unwrap().imsi_md5, device.imsi_md5);
+    }
+
+    #[test]
+    fn test_import_mirai_v1() {
+        let d = Device::from_json(
+            r#"{
+                "display": [77, 73, 82, 65, 73],
+                "product": [109, 105, 114, 97, 105],
+                "device": [109, 105, 114, 97, 105],
+                "board": [109, 105, 114, 97, 105],
+                "brand": [109, 97, 109, 111, 101],
+                "model": [109, 105, 114, 97, 105],
+                "bootloader": [117, 110, 107, 110, 111, 119, 110],
+                "fingerprint": [102, 112],
+                "bootId": [98, 105, 100],
+                "procVersion": [112, 118],
+                "baseBand": [],
+                "version": {
+                    "incremental": [53, 56, 57, 49, 57, 51, 56],
+                    "release": [49, 48],
+                    "codename": [82, 69, 76],
+                    "sdk": 29
+                },
+                "simInfo": [84, 45, 77, 111, 98, 105, 108, 101],
+                "osType": [97, 110, 100, 114, 111, 105, 100],
+                "macAddress": [48, 50],
+                "wifiBSSID": [48, 50],
+                "wifiSSID": [60, 62],
+                "imsiMd5": [1, 2, 3, 4],
+                "imei": "468356291846738",
+                "apn": [119, 105, 102, 105],
+                "ipAddress": [192, 168, 1, 2]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(d.display, "MIRAI");
+        assert_eq!(d.finger_print, "fp");
+        assert_eq!(d.version.incremental, "5891938");
+        assert_eq!(d.imsi_md5, vec![1, 2, 3, 4]);
+        assert_eq!(d.ip_address, vec![192, 168, 1, 2]);
+    }
+}
@@ -1,7 +1,7 @@
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use rand::Rng;
 
-use crate::binary::BinaryWriter;
+use crate::binary::{BinaryReader, BinaryWriter};
 use crate::crypto::{qqtea_decrypt, EncryptECDH};
 use crate::{RQError, RQResult};
 
@@ -86,32 +86,30 @@ impl Codec {
     where
         B: Buf,
     {
-        let flag = reader.get_u8();
+        let flag = reader.read_u8()?;
         if flag != 2 {
             return Err(RQError::UnknownFlag(flag));
         }
         let mut m = Message::default();
-        reader.get_u16(); // len
-        reader.get_u16(); // version
-        m.command = reader.get_u16();
-        reader.get_u16(); // 1
-        m.uin = reader.get_i32() as u32;
-        reader.get_u8();
-        let encrypt_type = reader.get_u8();
-        reader.get_u8();
-        match encrypt_type {
-            0 => {
-                let len = reader.remaining() - 1;
-                let d = reader.copy_to_bytes(len);
-                m.body = Bytes::from(qqtea_decrypt(&d, &self.ecdh.initial_share_key));
-            }
-            3 => {
-                let len = reader.remaining() - 1;
-                let d = reader.copy_to_bytes(len);
-                m.body = Bytes::from(qqtea_decrypt(&d, &self.wt_session_ticket_key));
-            }
+        reader.read_u16()?; // len
+        reader.read_u16()?; // version
+        m.command = reader.read_u16()?;
+        reader.read_u16()?; // 1
+        m.uin = reader.read_u32()?;
+        reader.read_u8()?;
+        let encrypt_type = reader.read_u8()?;
+        reader.read_u8()?;
+        let key = match encrypt_type {
+            0 => &self.ecdh.initial_share_key,
+            3 => &self.wt_session_ticket_key,
             _ => return Err(RQError::UnknownEncryptType),
-        }
+        };
+        let len = reader
+            .remaining()
+            .checked_sub(1)
+            .ok_or_else(|| RQError::Decode("oicq: empty body".into()))?;
+        let d = reader.copy_to_bytes(len);
+        m.body = Bytes::from(qqtea_decrypt(&d, key)?);
         Ok(m)
     }
 }
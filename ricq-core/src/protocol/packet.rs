@@ -54,6 +54,14 @@ impl EncryptType {
     }
 }
 
+/// SSO 包签名（sec_sig），写入 SSO head 的 reserve field
+#[derive(Default, Debug, Clone)]
+pub struct SsoSign {
+    pub sign: Bytes,
+    pub token: Bytes,
+    pub extra: Bytes,
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct Packet {
     pub packet_type: PacketType,
@@ -20,6 +20,8 @@ pub struct Sig {
     pub s_key_expired_time: i64,
     pub d2: Bytes,
     pub d2key: Bytes,
+    /// 最近一次登录/换 sig 成功的时间，用于计算 D2 的使用时长
+    pub login_time: i64,
     // TODO 是不是可能None？
     pub device_token: Bytes,
     pub ps_key_map: HashMap<String, Bytes>,
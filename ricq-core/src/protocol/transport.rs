@@ -8,7 +8,7 @@ use crate::command::common::PbToBytes;
 use crate::crypto::{qqtea_decrypt, qqtea_encrypt};
 use crate::protocol::{
     device::Device,
-    packet::{EncryptType, Packet, PacketType},
+    packet::{EncryptType, Packet, PacketType, SsoSign},
     sig::Sig,
     version::Version,
 };
@@ -33,7 +33,12 @@ impl Transport {
 }
 
 impl Transport {
-    pub fn encode_packet(&self, mut pkt: Packet) -> Bytes {
+    pub fn encode_packet(&self, pkt: Packet) -> Bytes {
+        self.encode_packet_with_sign(pkt, None)
+    }
+
+    /// sign 不为 None 时写入 SSO head 的 reserve field
+    pub fn encode_packet_with_sign(&self, mut pkt: Packet, sign: Option<&SsoSign>) -> Bytes {
         if self.sig.d2.is_empty() {
             pkt.encrypt_type = EncryptType::EmptyKey
         }
@@ -60,7 +65,7 @@ impl Transport {
         // ^^^ w.Write(head) ^^^
 
         let mut w2 = BytesMut::new();
-        self.encode_body(&pkt, &mut w2);
+        self.encode_body(&pkt, sign, &mut w2);
         let mut body = w2.freeze();
         match pkt.encrypt_type {
             EncryptType::D2Key => {
@@ -83,19 +88,19 @@ impl Transport {
         B: Buf,
     {
         let mut pkt = Packet {
-            packet_type: PacketType::from_i32(r.get_i32())?,
-            encrypt_type: EncryptType::from_u8(r.get_u8())?,
+            packet_type: PacketType::from_i32(r.read_i32()?)?,
+            encrypt_type: EncryptType::from_u8(r.read_u8()?)?,
             ..Default::default()
         };
-        r.get_u8(); // 0x00
+        r.read_u8()?; // 0x00
 
-        pkt.uin = r.read_string().parse().unwrap_or_default();
+        pkt.uin = r.read_string()?.parse().unwrap_or_default();
 
         let mut body = Bytes::from(r.chunk().to_owned());
         match pkt.encrypt_type {
             EncryptType::NoEncrypt => {}
-            EncryptType::D2Key => body = Bytes::from(qqtea_decrypt(&body, &self.sig.d2key)),
-            EncryptType::EmptyKey => body = Bytes::from(qqtea_decrypt(&body, &[0; 16])),
+            EncryptType::D2Key => body = Bytes::from(qqtea_decrypt(&body, &self.sig.d2key)?),
+            EncryptType::EmptyKey => body = Bytes::from(qqtea_decrypt(&body, &[0; 16])?),
         }
 
         self.decode_sso_frame(&mut pkt, body)?;
@@ -106,7 +111,7 @@ impl Transport {
         Ok(pkt)
     }
 
-    fn encode_body(&self, pkt: &Packet, w: &mut BytesMut) {
+    fn encode_body(&self, pkt: &Packet, sign: Option<&SsoSign>, w: &mut BytesMut) {
         let pos = w.len();
         w.put_u32(0); // len
 
@@ -135,7 +140,30 @@ impl Transport {
             w.put_u16(self.device.ksid().len() as u16 + 2);
             w.put_slice(&self.device.ksid());
         }
-        w.put_u32(0x04);
+        match sign {
+            Some(sign) => {
+                let reserve = pb::msf::SsoReserveField {
+                    flag: Some(0),
+                    qimei: Some(String::new()),
+                    newconn_flag: Some(0),
+                    uid: Some(pkt.uin.to_string()),
+                    imsi: Some(0),
+                    network_type: Some(1),
+                    ip_stack_type: Some(1),
+                    message_type: Some(0),
+                    sec_info: Some(pb::msf::SsoSecureInfo {
+                        sec_sig: Some(sign.sign.to_vec()),
+                        sec_device_token: Some(sign.token.to_vec()),
+                        sec_extra: Some(sign.extra.to_vec()),
+                    }),
+                    sso_ip_origin: Some(0),
+                }
+                .to_bytes();
+                w.put_u32(reserve.len() as u32 + 4);
+                w.put_slice(&reserve);
+            }
+            None => w.put_u32(0x04),
+        }
 
         // write len
         let len = w.len() - pos;
@@ -149,36 +177,33 @@ impl Transport {
     where
         B: Buf,
     {
-        let head_len = r.get_i32() as usize;
-        if head_len - 4 > r.remaining() {
-            return Err(RQError::PacketDropped);
-        }
+        let head_len = match usize::try_from(r.read_i32()?) {
+            Ok(len) if len >= 4 && len - 4 <= r.remaining() => len - 4,
+            _ => return Err(RQError::PacketDropped),
+        };
 
-        let mut head = r.copy_to_bytes(head_len - 4);
-        pkt.seq_id = head.get_i32();
+        let mut head = r.copy_to_bytes(head_len);
+        pkt.seq_id = head.read_i32()?;
 
-        let ret_code = head.get_i32();
+        let ret_code = head.read_i32()?;
         match ret_code {
             0 => {}
             -10008 => return Err(RQError::SessionExpired),
             other => return Err(RQError::UnsuccessfulRetCode(other)),
         }
-        pkt.message = head.read_string();
-        pkt.command_name = head.read_string();
+        pkt.message = head.read_string()?;
+        pkt.command_name = head.read_string()?;
         if &pkt.command_name == "Heartbeat.Alive" {
             return Ok(());
         }
 
-        let session_id_len = head.get_i32() as usize - 4;
-        let _ = head.copy_to_bytes(session_id_len);
+        let _session_id = head.read_string()?;
 
-        let compress_flag = head.get_i32();
+        let compress_flag = head.read_i32()?;
 
-        let mut body_len = r.get_i32() as usize - 4;
-        body_len = if body_len > 0 && body_len <= r.remaining() {
-            body_len
-        } else {
-            r.remaining()
+        let body_len = match usize::try_from(r.read_i32()?) {
+            Ok(len) if len > 4 && len - 4 <= r.remaining() => len - 4,
+            _ => r.remaining(),
         };
         let mut body = r.copy_to_bytes(body_len);
 
@@ -203,3 +228,157 @@ impl Transport {
         .to_bytes()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::protocol::version::{get_version, Protocol};
+
+    fn transport() -> Transport {
+        let mut transport = Transport::new(Device::random(), get_version(Protocol::IPad));
+        transport.sig.d2 = Bytes::from_static(&[1; 16]);
+        transport.sig.d2key = Bytes::from_static(&[2; 16]);
+        transport
+    }
+
+    // 服务器回包，decode_packet 的逆过程
+    fn encode_response(transport: &Transport, pkt: &Packet) -> Bytes {
+        let mut head = BytesMut::new();
+        head.put_i32(pkt.seq_id);
+        head.put_i32(0); // ret_code
+        head.write_string(&pkt.message);
+        head.write_string(&pkt.command_name);
+        head.put_i32(4); // session_id
+        head.put_i32(0); // compress_flag
+
+        let mut frame = BytesMut::new();
+        frame.put_i32(head.len() as i32 + 4);
+        frame.put_slice(&head);
+        frame.put_i32(pkt.body.len() as i32 + 4);
+        frame.put_slice(&pkt.body);
+        let frame = match pkt.encrypt_type {
+            EncryptType::D2Key => Bytes::from(qqtea_encrypt(&frame, &transport.sig.d2key)),
+            _ => frame.freeze(),
+        };
+
+        let mut w = BytesMut::new();
+        w.put_u32(pkt.packet_type.value());
+        w.put_u8(pkt.encrypt_type.value() as u8);
+        w.put_u8(0x00);
+        w.write_string(&pkt.uin.to_string());
+        w.put_slice(&frame);
+        w.freeze()
+    }
+
+    // encode_packet 的逆过程，仅支持 Simple + D2Key
+    fn decode_request(transport: &Transport, mut r: Bytes) -> RQResult<Packet> {
+        let packet_type = PacketType::from_i32(r.read_i32()?)?;
+        let encrypt_type = EncryptType::from_u8(r.read_u8()?)?;
+        let seq_id = r.read_i32()?;
+        r.read_u8()?;
+        let uin = r.read_string()?.parse().unwrap_or_default();
+        let mut frame = Bytes::from(qqtea_decrypt(&r, &transport.sig.d2key)?);
+        let head_len = frame.read_i32()? as usize - 4;
+        let command_name = frame.read_bytes(head_len)?.read_string()?;
+        let body_len = frame.read_i32()? as usize - 4;
+        Ok(Packet {
+            packet_type,
+            encrypt_type,
+            seq_id,
+            body: frame.read_bytes(body_len)?,
+            command_name,
+            uin,
+            ..Default::default()
+        })
+    }
+
+    fn packet() -> impl Strategy<Value = Packet> {
+        (
+            prop_oneof![Just(EncryptType::NoEncrypt), Just(EncryptType::D2Key)],
+            any::<i32>(),
+            vec(any::<u8>(), 0..256),
+            "[A-Za-z0-9_.]{1,32}",
+            any::<i64>(),
+            ".{0,16}",
+        )
+            .prop_map(
+                |(encrypt_type, seq_id, body, command_name, uin, message)| Packet {
+                    packet_type: PacketType::Simple,
+                    encrypt_type,
+                    seq_id,
+                    body: Bytes::from(body),
+                    command_name,
+                    uin,
+                    message,
+                },
+            )
+    }
+
+    #[test]
+    fn test_encode_packet_with_sign() {
+        use prost::Message;
+
+        let transport = transport();
+        let sign = SsoSign {
+            sign: Bytes::from_static(&[1, 2]),
+            token: Bytes::from_static(&[3]),
+            extra: Bytes::new(),
+        };
+        let pkt = Packet {
+            packet_type: PacketType::Simple,
+            encrypt_type: EncryptType::D2Key,
+            command_name: "MessageSvc.PbSendMsg".into(),
+            uin: 10000,
+            ..Default::default()
+        };
+        let mut r = transport.encode_packet_with_sign(pkt, Some(&sign));
+        r.skip(4 + 1 + 4 + 1).unwrap();
+        r.read_string().unwrap();
+        let mut frame = Bytes::from(qqtea_decrypt(&r, &transport.sig.d2key).unwrap());
+        let head_len = frame.read_i32().unwrap() as usize - 4;
+        let mut head = frame.read_bytes(head_len).unwrap();
+        assert_eq!(head.read_string().unwrap(), "MessageSvc.PbSendMsg");
+        head.read_string().unwrap(); // session_id
+        let reserve_len = head.read_i32().unwrap() as usize - 4;
+        let reserve =
+            pb::msf::SsoReserveField::decode(&*head.read_bytes(reserve_len).unwrap()).unwrap();
+        assert_eq!(reserve.uid(), "10000");
+        let sec_info = reserve.sec_info.unwrap();
+        assert_eq!(sec_info.sec_sig(), &[1, 2]);
+        assert_eq!(sec_info.sec_device_token(), &[3]);
+    }
+
+    proptest! {
+        #[test]
+        fn test_decode_packet_round_trip(pkt in packet()) {
+            let transport = transport();
+            let decoded = transport.decode_packet(encode_response(&transport, &pkt))?;
+            prop_assert_eq!(decoded.seq_id, pkt.seq_id);
+            prop_assert_eq!(decoded.uin, pkt.uin);
+            prop_assert_eq!(decoded.message, pkt.message);
+            prop_assert_eq!(decoded.command_name, pkt.command_name);
+            prop_assert_eq!(decoded.body, pkt.body);
+        }
+
+        #[test]
+        fn test_encode_packet_round_trip(mut pkt in packet()) {
+            let transport = transport();
+            pkt.encrypt_type = EncryptType::D2Key;
+            let decoded = decode_request(&transport, transport.encode_packet(pkt.clone()))?;
+            prop_assert_eq!(decoded.packet_type, pkt.packet_type);
+            prop_assert_eq!(decoded.encrypt_type, pkt.encrypt_type);
+            prop_assert_eq!(decoded.seq_id, pkt.seq_id);
+            prop_assert_eq!(decoded.uin, pkt.uin);
+            prop_assert_eq!(decoded.command_name, pkt.command_name);
+            prop_assert_eq!(decoded.body, pkt.body);
+        }
+
+        #[test]
+        fn test_decode_packet_arbitrary(data in vec(any::<u8>(), 0..256)) {
+            let _ = transport().decode_packet(Bytes::from(data));
+        }
+    }
+}
@@ -1,6 +1,13 @@
+use std::borrow::Cow;
 use std::convert::TryFrom;
+use std::path::Path;
 
-#[derive(Debug, Clone, derivative::Derivative, serde::Deserialize)]
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::hex::{decode_hex, encode_hex};
+use crate::{RQError, RQResult};
+
+#[derive(Debug, Clone, derivative::Derivative, Serialize, Deserialize)]
 #[derivative(Default)]
 pub enum Protocol {
     #[derivative(Default)]
@@ -12,13 +19,19 @@ pub enum Protocol {
     QiDian,
 }
 
-#[derive(Debug, Clone, serde::Deserialize)]
+/// 协议版本信息
+///
+/// 可以从 go-cqhttp (`data/versions/*.json`) 或 mirai (`fix-protocol-version`) 导出的协议文件加载，
+/// 反序列化时会校验 apk_sign 和 sig map。内置版本的字段是借用的，从文件加载的版本是拥有的
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "VersionDump")]
 pub struct Version {
-    pub apk_sign: &'static [u8],
-    pub apk_id: &'static str,
-    pub sort_version_name: &'static str,
-    pub build_ver: &'static str,
-    pub sdk_version: &'static str,
+    #[serde(serialize_with = "serialize_hex")]
+    pub apk_sign: Cow<'static, [u8]>,
+    pub apk_id: Cow<'static, str>,
+    pub sort_version_name: Cow<'static, str>,
+    pub build_ver: Cow<'static, str>,
+    pub sdk_version: Cow<'static, str>,
     pub app_id: u32,
     pub sub_app_id: u32,
     pub build_time: u32,
@@ -26,6 +39,7 @@ pub struct Version {
     pub misc_bitmap: u32,
     pub sub_sig_map: u32,
     pub main_sig_map: u32,
+    #[serde(rename = "protocol_type", serialize_with = "serialize_protocol")]
     pub protocol: Protocol,
 }
 
@@ -46,18 +60,222 @@ impl From<Protocol> for Version {
     }
 }
 
+impl Version {
+    /// 从 json 加载，文件中需要有协议字段（go-cqhttp 的 `protocol_type`）
+    pub fn from_json(s: &str) -> RQResult<Self> {
+        serde_json::from_str(s).map_err(|e| RQError::Decode(format!("version json: {e}")))
+    }
+
+    /// 从 json 加载，文件中没有协议字段时（例如 mirai 导出的文件）使用 protocol
+    pub fn from_json_as(s: &str, protocol: Protocol) -> RQResult<Self> {
+        serde_json::from_str::<VersionDump>(s)
+            .map_err(|e| RQError::Decode(format!("version json: {e}")))?
+            .into_version(Some(protocol))
+    }
+
+    /// 从 toml 加载，需要开启 `toml` feature
+    #[cfg(feature = "toml")]
+    pub fn from_toml(s: &str) -> RQResult<Self> {
+        toml::from_str(s).map_err(|e| RQError::Decode(format!("version toml: {e}")))
+    }
+
+    /// 从文件加载，`.toml` 后缀按 toml 解析，其他按 json 解析
+    ///
+    /// json 中没有协议字段时按文件名推断，例如 mirai 的 `android_phone.json`
+    pub fn load(path: impl AsRef<Path>) -> RQResult<Self> {
+        let path = path.as_ref();
+        let s = std::fs::read_to_string(path)?;
+        let protocol = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .and_then(|s| Protocol::try_from(s.to_ascii_uppercase().as_str()).ok());
+        match path.extension().and_then(|e| e.to_str()) {
+            #[cfg(feature = "toml")]
+            Some("toml") => Self::from_toml(&s),
+            #[cfg(not(feature = "toml"))]
+            Some("toml") => Err(RQError::Other(
+                "version toml: `toml` feature is not enabled".into(),
+            )),
+            _ => match protocol {
+                Some(protocol) => Self::from_json_as(&s, protocol),
+                None => Self::from_json(&s),
+            },
+        }
+    }
+
+    pub fn to_json(&self) -> RQResult<String> {
+        serde_json::to_string_pretty(self).map_err(|e| RQError::Other(format!("version json: {e}")))
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn to_toml(&self) -> RQResult<String> {
+        toml::to_string(self).map_err(|e| RQError::Other(format!("version toml: {e}")))
+    }
+
+    /// 校验 apk_sign 长度和 sig map
+    pub fn validate(&self) -> RQResult<()> {
+        let invalid = |msg: String| Err(RQError::Other(format!("invalid version: {msg}")));
+        if self.apk_id.is_empty()
+            || self.sort_version_name.is_empty()
+            || self.sdk_version.is_empty()
+        {
+            return invalid("apk_id, sort_version_name and sdk_version must not be empty".into());
+        }
+        if self.app_id == 0 || self.sub_app_id == 0 {
+            return invalid("app_id must not be 0".into());
+        }
+        // MacOS 协议的 apk_sign 是包名，其他协议是签名的 md5
+        match self.protocol {
+            Protocol::MacOS if self.apk_sign.is_empty() => {
+                return invalid("apk_sign must not be empty".into())
+            }
+            Protocol::MacOS => {}
+            _ if self.apk_sign.len() != 16 => {
+                return invalid(format!(
+                    "apk_sign must be 16 bytes, got {}",
+                    self.apk_sign.len()
+                ))
+            }
+            _ => {}
+        }
+        // 登录后需要 A2 和 D2
+        if self.main_sig_map & MAIN_SIG_MAP_REQUIRED != MAIN_SIG_MAP_REQUIRED {
+            return invalid(format!(
+                "main_sig_map {:#x} must contain {MAIN_SIG_MAP_REQUIRED:#x}",
+                self.main_sig_map
+            ));
+        }
+        if self.sub_sig_map == 0 {
+            return invalid("sub_sig_map must not be 0".into());
+        }
+        Ok(())
+    }
+}
+
+/// WLOGIN_A2 | WLOGIN_D2
+const MAIN_SIG_MAP_REQUIRED: u32 = 0x40 | 0x40000;
+
+// 兼容 go-cqhttp (snake_case) 和 mirai (camelCase) 的字段名
+#[derive(Deserialize)]
+struct VersionDump {
+    #[serde(alias = "apkId")]
+    apk_id: String,
+    #[serde(alias = "id")]
+    app_id: u32,
+    #[serde(default, alias = "subAppId")]
+    sub_app_id: Option<u32>,
+    #[serde(alias = "ver")]
+    sort_version_name: String,
+    #[serde(default, alias = "buildVer")]
+    build_ver: Option<String>,
+    #[serde(default, alias = "buildTime")]
+    build_time: u32,
+    #[serde(alias = "sign")]
+    apk_sign: ApkSign,
+    #[serde(alias = "sdkVer")]
+    sdk_version: String,
+    #[serde(alias = "ssoVersion")]
+    sso_version: u32,
+    #[serde(alias = "miscBitMap")]
+    misc_bitmap: u32,
+    #[serde(alias = "subSigMap")]
+    sub_sig_map: u32,
+    #[serde(alias = "mainSigMap")]
+    main_sig_map: u32,
+    #[serde(default, alias = "protocol")]
+    protocol_type: Option<ProtocolId>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ApkSign {
+    Hex(String),
+    Bytes(Vec<u8>),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ProtocolId {
+    Id(u8),
+    Name(String),
+}
+
+impl TryFrom<VersionDump> for Version {
+    type Error = RQError;
+
+    fn try_from(d: VersionDump) -> RQResult<Self> {
+        d.into_version(None)
+    }
+}
+
+impl VersionDump {
+    // 文件中的协议字段优先，没有时使用 fallback
+    fn into_version(self, fallback: Option<Protocol>) -> RQResult<Version> {
+        let d = self;
+        let apk_sign = match d.apk_sign {
+            ApkSign::Bytes(b) => b,
+            ApkSign::Hex(s) => {
+                // mirai 格式为 `A6 B7 45 ...`
+                let s: String = s
+                    .chars()
+                    .filter(|c| !c.is_whitespace() && *c != ':')
+                    .collect();
+                if !s.is_ascii() || !s.len().is_multiple_of(2) {
+                    return Err(RQError::Decode(format!("invalid apk_sign {s}")));
+                }
+                decode_hex(&s).map_err(|e| RQError::Decode(format!("invalid apk_sign: {e}")))?
+            }
+        };
+        let protocol = match d.protocol_type {
+            None => fallback.ok_or_else(|| RQError::Decode("missing protocol_type".into()))?,
+            Some(ProtocolId::Id(id)) => Protocol::try_from(id)
+                .map_err(|_| RQError::Decode(format!("unknown protocol_type {id}")))?,
+            Some(ProtocolId::Name(name)) => Protocol::try_from(name.as_str())
+                .map_err(|_| RQError::Decode(format!("unknown protocol {name}")))?,
+        };
+        let version = Version {
+            apk_sign: apk_sign.into(),
+            sub_app_id: d.sub_app_id.unwrap_or(d.app_id),
+            build_ver: d
+                .build_ver
+                .unwrap_or_else(|| d.sort_version_name.clone())
+                .into(),
+            apk_id: d.apk_id.into(),
+            sort_version_name: d.sort_version_name.into(),
+            sdk_version: d.sdk_version.into(),
+            app_id: d.app_id,
+            build_time: d.build_time,
+            sso_version: d.sso_version,
+            misc_bitmap: d.misc_bitmap,
+            sub_sig_map: d.sub_sig_map,
+            main_sig_map: d.main_sig_map,
+            protocol,
+        };
+        version.validate()?;
+        Ok(version)
+    }
+}
+
+fn serialize_hex<S: Serializer>(b: &impl AsRef<[u8]>, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&encode_hex(b.as_ref()))
+}
+
+fn serialize_protocol<S: Serializer>(p: &Protocol, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_u8(u8::from(p))
+}
+
 pub const ANDROID_PHONE: Version = Version {
-    apk_id: "com.tencent.mobileqq",
+    apk_id: Cow::Borrowed("com.tencent.mobileqq"),
     app_id: 537154734,
     sub_app_id: 537154734,
-    sort_version_name: "8.9.38.10545",
-    build_ver: "8.9.15.9425",
+    sort_version_name: Cow::Borrowed("8.9.38.10545"),
+    build_ver: Cow::Borrowed("8.9.15.9425"),
     build_time: 1676531414,
-    apk_sign: &[
+    apk_sign: Cow::Borrowed(&[
         0xA6, 0xB7, 0x45, 0xBF, 0x24, 0xA2, 0xC2, 0x77, 0x52, 0x77, 0x16, 0xF6, 0xF3, 0x6E, 0xB6,
         0x8D,
-    ],
-    sdk_version: "6.0.0.2535",
+    ]),
+    sdk_version: Cow::Borrowed("6.0.0.2535"),
     sso_version: 19,
     misc_bitmap: 150470524,
     sub_sig_map: 66560,
@@ -66,17 +284,17 @@ pub const ANDROID_PHONE: Version = Version {
 };
 
 pub const IPAD: Version = Version {
-    apk_id: "com.tencent.minihd.qq",
+    apk_id: Cow::Borrowed("com.tencent.minihd.qq"),
     app_id: 537065739,
     sub_app_id: 537065739,
-    sort_version_name: "5.8.9",
-    build_ver: "8.8.38.2266",
+    sort_version_name: Cow::Borrowed("5.8.9"),
+    build_ver: Cow::Borrowed("8.8.38.2266"),
     build_time: 1595836208,
-    apk_sign: &[
+    apk_sign: Cow::Borrowed(&[
         0xAA, 0x39, 0x78, 0xF4, 0x1F, 0xD9, 0x6F, 0xF9, 0x91, 0x4A, 0x66, 0x9E, 0x18, 0x64, 0x74,
         0xC7,
-    ],
-    sdk_version: "6.0.0.2433",
+    ]),
+    sdk_version: Cow::Borrowed("6.0.0.2433"),
     sso_version: 12,
     misc_bitmap: 150470524,
     sub_sig_map: 66560,
@@ -85,17 +303,17 @@ pub const IPAD: Version = Version {
 };
 
 pub const ANDROID_WATCH: Version = Version {
-    apk_id: "com.tencent.qqlite",
+    apk_id: Cow::Borrowed("com.tencent.qqlite"),
     app_id: 537064446,
     sub_app_id: 537064446,
-    sort_version_name: "2.0.5",
-    build_ver: "2.0.5",
+    sort_version_name: Cow::Borrowed("2.0.5"),
+    build_ver: Cow::Borrowed("2.0.5"),
     build_time: 1559564731,
-    apk_sign: &[
+    apk_sign: Cow::Borrowed(&[
         0xA6, 0xB7, 0x45, 0xBF, 0x24, 0xA2, 0xC2, 0x77, 0x52, 0x77, 0x16, 0xF6, 0xF3, 0x6E, 0xB6,
         0x8D,
-    ],
-    sdk_version: "6.0.0.236",
+    ]),
+    sdk_version: Cow::Borrowed("6.0.0.236"),
     sso_version: 5,
     misc_bitmap: 16252796,
     sub_sig_map: 0x10400,
@@ -104,32 +322,32 @@ pub const ANDROID_WATCH: Version = Version {
 };
 
 pub const MACOS: Version = Version {
-    apk_id: "com.tencent.qq",              // ok
-    app_id: 0x2003ca32,                    // ok
-    sub_app_id: 0x2003ca32,                // ok
-    sort_version_name: "6.7.9",            // ok
-    build_ver: "5.8.9.3460",               // 6.7.9.xxx?
-    build_time: 0,                         // ok
-    apk_sign: "com.tencent.qq".as_bytes(), // ok
-    sdk_version: "6.2.0.1023",             // ok
-    sso_version: 7,                        // ok
-    misc_bitmap: 0x7ffc,                   // ok
-    sub_sig_map: 66560,                    // ?
-    main_sig_map: 1970400,                 // ?
+    apk_id: Cow::Borrowed("com.tencent.qq"),    // ok
+    app_id: 0x2003ca32,                         // ok
+    sub_app_id: 0x2003ca32,                     // ok
+    sort_version_name: Cow::Borrowed("6.7.9"),  // ok
+    build_ver: Cow::Borrowed("5.8.9.3460"),     // 6.7.9.xxx?
+    build_time: 0,                              // ok
+    apk_sign: Cow::Borrowed(b"com.tencent.qq"), // ok
+    sdk_version: Cow::Borrowed("6.2.0.1023"),   // ok
+    sso_version: 7,                             // ok
+    misc_bitmap: 0x7ffc,                        // ok
+    sub_sig_map: 66560,                         // ?
+    main_sig_map: 1970400,                      // ?
     protocol: Protocol::MacOS,
 };
 
 pub const QIDIAN: Version = Version {
-    apk_id: "com.tencent.qidian",
+    apk_id: Cow::Borrowed("com.tencent.qidian"),
     app_id: 537061386,
     sub_app_id: 537036590,
-    sort_version_name: "3.8.6",
-    build_ver: "8.8.38.2266",
+    sort_version_name: Cow::Borrowed("3.8.6"),
+    build_ver: Cow::Borrowed("8.8.38.2266"),
     build_time: 1556628836,
-    apk_sign: &[
+    apk_sign: Cow::Borrowed(&[
         160, 30, 236, 171, 133, 233, 227, 186, 43, 15, 106, 21, 140, 133, 92, 41,
-    ],
-    sdk_version: "6.0.0.2365",
+    ]),
+    sdk_version: Cow::Borrowed("6.0.0.2365"),
     sso_version: 5,
     misc_bitmap: 49807228,
     sub_sig_map: 66560,
@@ -138,17 +356,17 @@ pub const QIDIAN: Version = Version {
 };
 
 pub const ANDROID_PAD: Version = Version {
-    apk_id: "com.tencent.mobileqq",
+    apk_id: Cow::Borrowed("com.tencent.mobileqq"),
     app_id: 537154261,
     sub_app_id: 537154261,
-    sort_version_name: "8.9.38.10545",
-    build_ver: "8.8.38.2266",
+    sort_version_name: Cow::Borrowed("8.9.38.10545"),
+    build_ver: Cow::Borrowed("8.8.38.2266"),
     build_time: 1556628836,
-    apk_sign: &[
+    apk_sign: Cow::Borrowed(&[
         0xa6, 0xb7, 0x45, 0xbf, 0x24, 0xa2, 0xc2, 0x77, 0x52, 0x77, 0x16, 0xf6, 0xf3, 0x6e, 0xb6,
         0x8d,
-    ],
-    sdk_version: "6.0.0.2535",
+    ]),
+    sdk_version: Cow::Borrowed("6.0.0.2535"),
     sso_version: 19,
     misc_bitmap: 150470524,
     sub_sig_map: 66560,
@@ -161,11 +379,12 @@ impl TryFrom<&str> for Protocol {
 
     fn try_from(s: &str) -> Result<Self, Self::Error> {
         match s {
-            "IPad" => Ok(Protocol::IPad),
-            "AndroidPhone" | "APhone" => Ok(Protocol::AndroidPhone),
-            "AndroidWatch" | "AWatch" => Ok(Protocol::AndroidWatch),
-            "AndroidPad" | "APad" => Ok(Protocol::AndroidPad),
-            "MacOS" => Ok(Protocol::MacOS),
+            // mirai 使用 ANDROID_PHONE 等名称
+            "IPad" | "IPAD" => Ok(Protocol::IPad),
+            "AndroidPhone" | "APhone" | "ANDROID_PHONE" => Ok(Protocol::AndroidPhone),
+            "AndroidWatch" | "AWatch" | "ANDROID_WATCH" => Ok(Protocol::AndroidWatch),
+            "AndroidPad" | "APad" | "ANDROID_PAD" => Ok(Protocol::AndroidPad),
+            "MacOS" | "MACOS" => Ok(Protocol::MacOS),
             "QiDian" => Ok(Protocol::QiDian),
             _ => Err(()),
         }
@@ -188,3 +407,136 @@ impl TryFrom<u8> for Protocol {
         }
     }
 }
+
+impl From<&Protocol> for u8 {
+    /// 与 go-cqhttp 的 protocol_type 一致
+    fn from(p: &Protocol) -> u8 {
+        match p {
+            Protocol::AndroidPhone => 1,
+            Protocol::AndroidWatch => 2,
+            Protocol::MacOS => 3,
+            Protocol::QiDian => 4,
+            Protocol::IPad => 5,
+            Protocol::AndroidPad => 6,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_versions_valid() {
+        for p in [
+            Protocol::IPad,
+            Protocol::AndroidPhone,
+            Protocol::AndroidWatch,
+            Protocol::AndroidPad,
+            Protocol::MacOS,
+            Protocol::QiDian,
+        ] {
+            let v = get_version(p.clone());
+            v.validate().unwrap();
+            let json = Version::from_json(&v.to_json().unwrap()).unwrap();
+            assert_eq!(json.apk_sign, v.apk_sign);
+            assert_eq!(u8::from(&json.protocol), u8::from(&p));
+            #[cfg(feature = "toml")]
+            {
+                let toml = Version::from_toml(&v.to_toml().unwrap()).unwrap();
+                assert_eq!(toml.main_sig_map, v.main_sig_map);
+            }
+        }
+    }
+
+    #[test]
+    fn test_load_gocq_dump() {
+        let v = Version::from_json(
+            r#"{
+                "apk_id": "com.tencent.mobileqq",
+                "app_id": 537164840,
+                "sub_app_id": 537164840,
+                "app_key": "0S200MNJT807V3GE",
+                "sort_version_name": "8.9.63.11390",
+                "build_time": 1685069178,
+                "apk_sign": "a6b745bf24a2c277527716f6f36eb68d",
+                "sdk_version": "6.0.0.2546",
+                "sso_version": 20,
+                "misc_bitmap": 150470524,
+                "main_sig_map": 34869472,
+                "sub_sig_map": 66560,
+                "dump_time": 1689780543,
+                "qua": "V1_AND_SQ_8.9.63_4194_YYB_D",
+                "protocol_type": 1
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(v.app_id, 537164840);
+        assert_eq!(v.build_ver, "8.9.63.11390");
+        assert_eq!(v.apk_sign, ANDROID_PHONE.apk_sign);
+        assert!(matches!(v.protocol, Protocol::AndroidPhone));
+    }
+
+    // mirai fix-protocol-version 导出的文件，没有协议字段
+    const MIRAI_DUMP: &str = r#"{
+        "apkId": "com.tencent.mobileqq",
+        "id": 537163098,
+        "ver": "8.9.58.11170",
+        "sdkVer": "6.0.0.2545",
+        "miscBitMap": 150470524,
+        "subSigMap": 66560,
+        "mainSigMap": 34869472,
+        "sign": "A6 B7 45 BF 24 A2 C2 77 52 77 16 F6 F3 6E B6 8D",
+        "buildTime": 1684467300,
+        "ssoVersion": 20,
+        "appKey": "0S200MNJT807V3GE"
+    }"#;
+
+    #[test]
+    fn test_load_mirai_dump() {
+        assert!(Version::from_json(MIRAI_DUMP).is_err());
+        let v = Version::from_json_as(MIRAI_DUMP, Protocol::AndroidPhone).unwrap();
+        assert_eq!(v.sub_app_id, 537163098);
+        assert_eq!(v.sdk_version, "6.0.0.2545");
+        assert_eq!(v.apk_sign, ANDROID_PHONE.apk_sign);
+        assert!(matches!(v.protocol, Protocol::AndroidPhone));
+
+        for (name, protocol) in [
+            ("ANDROID_PAD", Protocol::AndroidPad),
+            ("ANDROID_WATCH", Protocol::AndroidWatch),
+            ("IPAD", Protocol::IPad),
+            ("MACOS", Protocol::MacOS),
+        ] {
+            assert_eq!(
+                u8::from(&Protocol::try_from(name).unwrap()),
+                u8::from(&protocol)
+            );
+        }
+
+        // 按文件名推断协议
+        let dir = std::env::temp_dir().join(format!("ricq-version-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("android_pad.json");
+        std::fs::write(&path, MIRAI_DUMP).unwrap();
+        let v = Version::load(&path);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(v.unwrap().protocol, Protocol::AndroidPad));
+    }
+
+    #[test]
+    fn test_invalid_version() {
+        let mut v = get_version(Protocol::AndroidPhone);
+        v.apk_sign.to_mut().pop();
+        assert!(v.validate().is_err());
+        assert!(Version::from_json(&v.to_json().unwrap()).is_err());
+
+        let mut v = get_version(Protocol::AndroidPhone);
+        v.main_sig_map = 0x10400;
+        assert!(v.validate().is_err());
+
+        let mut json: serde_json::Value =
+            serde_json::from_str(&get_version(Protocol::AndroidPhone).to_json().unwrap()).unwrap();
+        json.as_object_mut().unwrap().remove("protocol_type");
+        assert!(Version::from_json(&json.to_string()).is_err());
+    }
+}
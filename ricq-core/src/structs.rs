@@ -1,4 +1,6 @@
 use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
 use std::time::Duration;
 
 pub use crate::command::multi_msg::{ForwardMessage, ForwardNode, MessageNode};
@@ -18,11 +20,18 @@ pub struct AccountInfo {
 
 #[derive(Default, Debug)]
 pub struct AddressInfo {
+    /// ConfigPushSvc 下发的 SSO 服务器 (`ip:port`)，后台测速完成后按延迟排序
     pub srv_sso_addrs: Vec<String>,
     pub other_srv_addrs: Vec<String>,
     pub file_storage_info: jce::FileStoragePushFSSvcList,
 }
 
+/// 服务器地址快照，可以序列化保存，重启后通过 load_address_snapshot 恢复
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddressSnapshot {
+    pub srv_sso_addrs: Vec<SocketAddr>,
+}
+
 #[derive(Debug, Default)]
 pub struct OtherClientInfo {
     pub app_id: i64,
@@ -221,6 +230,96 @@ pub struct DeleteFriend {
     pub uin: i64,
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct MemberCardUpdate {
+    pub group_code: i64,
+    pub member_uin: i64,
+    /// 从群消息中发现时才有旧名片
+    pub old_card: Option<String>,
+    pub new_card: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MemberSpecialTitleUpdate {
+    pub group_code: i64,
+    pub member_uin: i64,
+    pub new_title: String,
+}
+
+/// 群荣誉
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupHonor {
+    /// 龙王
+    Talkative,
+    /// 群聊之火
+    Performer,
+    /// 快乐源泉
+    Emotion,
+}
+
+#[derive(Debug, Clone)]
+pub struct MemberHonorChange {
+    pub group_code: i64,
+    pub member_uin: i64,
+    pub nick: String,
+    pub honor: GroupHonor,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GroupEssenceChange {
+    pub group_code: i64,
+    pub msg_seq: i32,
+    pub msg_rand: i32,
+    pub sender_uin: i64,
+    pub operator_uin: i64,
+    pub time: i32,
+    /// true 设为精华，false 移出精华
+    pub is_set: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FriendNicknameUpdate {
+    pub uin: i64,
+    pub nickname: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FriendRemarkUpdate {
+    pub uin: i64,
+    pub remark: String,
+}
+
+/// 本账号的其他客户端上线/下线
+#[derive(Debug, Clone, Default)]
+pub struct OtherClientStatusChange {
+    pub app_id: i64,
+    pub platform: i64,
+    /// 设备名，例如“iPhone”
+    pub title: String,
+    /// 设备描述，例如“手机QQ”
+    pub info: String,
+    pub product_type: i64,
+    pub client_type: i64,
+    pub online: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FriendInputStatus {
+    pub uin: i64,
+    /// true 正在输入，false 停止输入
+    pub typing: bool,
+}
+
+/// 服务端推送解析失败
+#[derive(Debug, Clone, Default)]
+pub struct DecodeError {
+    /// 包的 command 名称
+    pub command: String,
+    pub error: String,
+    /// 解析失败的原始数据
+    pub raw: Bytes,
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct MemberPermissionChange {
     pub group_code: i64,
@@ -270,6 +369,34 @@ pub struct FriendAudioMessage {
     pub from_nick: String,
     pub audio: FriendAudio,
 }
+
+/// 好友离线文件，下载链接用 `Client::get_offline_file_download` 获取
+#[derive(Debug, Clone, Default)]
+pub struct OfflineFile {
+    pub name: String,
+    pub size: i64,
+    pub uuid: Vec<u8>,
+    pub md5: Vec<u8>,
+}
+
+/// 好友离线文件消息，通过 `QEvent::FriendFileMessage` 推送，不在消息链中；群文件是消息链中的 `RQElem::GroupFile`
+#[derive(Debug, Clone, Default)]
+pub struct FriendFileMessage {
+    pub seqs: Vec<i32>,
+    pub rands: Vec<i32>,
+    pub target: i64,
+    pub time: i32,
+    pub from_uin: i64,
+    pub from_nick: String,
+    pub file: OfflineFile,
+}
+
+/// 上传群文件的结果
+#[derive(Debug, Clone, Default)]
+pub struct GroupFileUpload {
+    pub file_id: String,
+    pub bus_id: i32,
+}
 // 群文件总数
 #[derive(Debug, Clone, Default)]
 pub struct GroupFileCount {
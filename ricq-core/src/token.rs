@@ -13,3 +13,46 @@ pub struct Token {
     pub tgtgt_key: Vec<u8>,
     pub wt_session_ticket_key: Vec<u8>, // oicq
 }
+
+#[cfg(test)]
+mod tests {
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn token() -> impl Strategy<Value = Token> {
+        let bytes = || vec(any::<u8>(), 0..64);
+        (
+            any::<i64>(),
+            [bytes(), bytes(), bytes(), bytes(), bytes()],
+            [bytes(), bytes(), bytes(), bytes()],
+        )
+            .prop_map(|(uin, [d2, d2key, tgt, srm_token, t133], rest)| {
+                let [encrypted_a1, out_packet_session_id, tgtgt_key, wt_session_ticket_key] = rest;
+                Token {
+                    uin,
+                    d2,
+                    d2key,
+                    tgt,
+                    srm_token,
+                    t133,
+                    encrypted_a1,
+                    out_packet_session_id,
+                    tgtgt_key,
+                    wt_session_ticket_key,
+                }
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn test_token_serde_round_trip(token in token()) {
+            let json = serde_json::to_string(&token).unwrap();
+            let decoded: Token = serde_json::from_str(&json).unwrap();
+            prop_assert_eq!(serde_json::to_string(&decoded).unwrap(), json);
+            prop_assert_eq!(decoded.uin, token.uin);
+            prop_assert_eq!(decoded.wt_session_ticket_key, token.wt_session_ticket_key);
+        }
+    }
+}
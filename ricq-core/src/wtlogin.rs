@@ -1,4 +1,5 @@
 use std::sync::atomic::Ordering;
+use std::time::UNIX_EPOCH;
 
 use bytes::{BufMut, Bytes, BytesMut};
 
@@ -54,6 +55,7 @@ impl super::Engine {
         sig.s_key_expired_time = resp.s_key_expired_time;
         sig.d2.option_set(resp.d2);
         sig.d2key.option_set(resp.d2key);
+        sig.login_time = UNIX_EPOCH.elapsed().unwrap().as_secs() as i64;
         sig.device_token.option_set(resp.device_token);
 
         if let Some(v) = resp.t402 {
@@ -1,19 +1,28 @@
-use std::time::Duration;
+use std::net::SocketAddr;
+use std::time::{Duration, UNIX_EPOCH};
 
 use bytes::BufMut;
+use tokio::io::{AsyncRead, AsyncSeek};
 
 use ricq_core::command::long_conn::OffPicUpResp;
+use ricq_core::command::multi_msg::{
+    forward_message_chain, gen_forward_preview, gen_forward_template, gen_long_message_template,
+    long_message_chain, MultiMsgTarget,
+};
 use ricq_core::command::oidb_svc::{LinkShare, MusicShare, MusicVersion, ShareTarget};
 use ricq_core::command::{friendlist::*, profile_service::*};
 use ricq_core::hex::encode_hex;
-use ricq_core::highway::BdhInput;
+use ricq_core::highway::{BdhInput, FileUploadExtInput};
 use ricq_core::msg::elem::FriendImage;
-use ricq_core::msg::MessageChain;
+use ricq_core::msg::{MessageChain, SendMode, SendPlan};
 use ricq_core::pb;
 use ricq_core::pb::msg::routing_head::RoutingHead;
 use ricq_core::structs::FriendAudio;
 use ricq_core::structs::MessageReceipt;
+use ricq_core::structs::{ForwardMessage, MessageNode};
 
+use crate::client::highway::hash_reader;
+use crate::client::SendTarget;
 use crate::structs::ImageInfo;
 use crate::{RQError, RQResult};
 
@@ -164,6 +173,28 @@ impl super::super::Client {
         self._send_friend_message(target, message_chain, None).await
     }
 
+    /// 按 mode 发送好友消息，消息过大时拆分或作为长消息发送，返回每条消息的回执
+    pub async fn send_friend_message_with_mode(
+        &self,
+        target: i64,
+        message_chain: MessageChain,
+        mode: SendMode,
+    ) -> RQResult<Vec<MessageReceipt>> {
+        match message_chain.plan_send(mode) {
+            SendPlan::Direct(chain) => Ok(vec![self.send_friend_message(target, chain).await?]),
+            SendPlan::Split(parts) => {
+                let mut receipts = Vec::with_capacity(parts.len());
+                for chain in parts {
+                    receipts.push(self.send_friend_message(target, chain).await?);
+                }
+                Ok(receipts)
+            }
+            SendPlan::LongMessage(chain) => {
+                Ok(vec![self.send_friend_long_message(target, chain).await?])
+            }
+        }
+    }
+
     /// 发送好友语音
     pub async fn send_friend_audio(
         &self,
@@ -174,6 +205,55 @@ impl super::super::Client {
             .await
     }
 
+    /// 发送好友长消息
+    pub async fn send_friend_long_message(
+        &self,
+        target: i64,
+        message_chain: MessageChain,
+    ) -> RQResult<MessageReceipt> {
+        let brief = message_chain.brief();
+        let res_id = self
+            .upload_friend_msgs(
+                target,
+                vec![MessageNode {
+                    sender_id: self.uin().await,
+                    time: UNIX_EPOCH.elapsed().unwrap().as_secs() as i32,
+                    sender_name: self.account_info.read().await.nickname.clone(),
+                    elements: message_chain,
+                }
+                .into()],
+                true,
+            )
+            .await?;
+        let template = gen_long_message_template(
+            &res_id,
+            &UNIX_EPOCH.elapsed().unwrap().as_millis().to_string(),
+            &brief,
+        );
+        self._send_friend_message(target, long_message_chain(template, res_id), None)
+            .await
+    }
+
+    /// 发送好友转发消息
+    pub async fn send_friend_forward_message(
+        &self,
+        target: i64,
+        msgs: Vec<ForwardMessage>,
+    ) -> RQResult<MessageReceipt> {
+        let t_sum = msgs.len();
+        let preview = gen_forward_preview(&msgs);
+        let res_id = self.upload_friend_msgs(target, msgs, false).await?;
+        let template = gen_forward_template(
+            &res_id,
+            &UNIX_EPOCH.elapsed().unwrap().as_millis().to_string(),
+            MultiMsgTarget::Friend(target).forward_title(),
+            t_sum,
+            &preview,
+        );
+        self._send_friend_message(target, forward_message_chain(template), None)
+            .await
+    }
+
     async fn _send_friend_message(
         &self,
         target: i64,
@@ -190,6 +270,128 @@ impl super::super::Client {
         .await
     }
 
+    /// 上传并发送离线文件，从开头分块读取 reader，不会一次读入内存
+    ///
+    /// 内存中的数据可以用 `std::io::Cursor` 包装
+    pub async fn send_friend_file<R: AsyncRead + AsyncSeek + Unpin>(
+        &self,
+        target: i64,
+        file_name: &str,
+        mut reader: R,
+    ) -> RQResult<MessageReceipt> {
+        let hash = hash_reader(&mut reader).await?;
+        let req = self.engine.read().await.build_offline_file_upload_packet(
+            target,
+            file_name.into(),
+            &hash,
+        );
+        let resp = self.send_and_wait(req).await?;
+        let rsp = self
+            .engine
+            .read()
+            .await
+            .decode_offline_file_upload_response(resp.body)?;
+        if !rsp.bool_file_exist {
+            let addr = match self.highway_addrs.read().await.first() {
+                Some(addr) => (*addr).into(),
+                None => SocketAddr::new(
+                    rsp.upload_ip_list
+                        .first()
+                        .unwrap_or(&rsp.upload_ip)
+                        .parse()
+                        .map_err(|_| RQError::EmptyField("upload_ip"))?,
+                    rsp.upload_port as u16,
+                ),
+            };
+            let session = self.highway_session.read().await;
+            let ext = session.build_file_upload_ext(FileUploadExtInput {
+                receiver: target,
+                bus_id: 3,
+                name: file_name.into(),
+                size: hash.size,
+                md5: hash.md5.clone(),
+                sha1: hash.sha1.clone(),
+                file_id: rsp.uuid.clone(),
+                upload_key: rsp.upload_key.clone(),
+                host: rsp.upload_ip_list.first().cloned().unwrap_or_default(),
+                port: rsp.upload_port,
+                ..Default::default()
+            });
+            let ticket = session.sig_session.to_vec();
+            drop(session);
+            self.highway_upload_bdh_reader(
+                addr,
+                BdhInput {
+                    command_id: 69,
+                    ticket,
+                    ext,
+                    encrypt: false,
+                    chunk_size: 256 * 1024,
+                    send_echo: true,
+                },
+                &mut reader,
+                hash.size as u64,
+                hash.md5.clone(),
+            )
+            .await?;
+        }
+        let req = self
+            .engine
+            .read()
+            .await
+            .build_offline_file_upload_succ_packet(target, rsp.uuid.clone());
+        let resp = self.send_and_wait(req).await?;
+        self.engine
+            .read()
+            .await
+            .decode_offline_file_upload_succ_response(resp.body)?;
+
+        self.acquire_send_permit(Some(SendTarget::Friend(target)))
+            .await?;
+        let time = UNIX_EPOCH.elapsed().unwrap().as_secs() as i64;
+        let seq = self.engine.read().await.next_friend_seq();
+        let ran = (rand::random::<u32>() >> 1) as i32;
+        let req = self.engine.read().await.build_offline_file_sending_packet(
+            target,
+            pb::msg::NotOnlineFile {
+                file_type: Some(0),
+                file_uuid: Some(rsp.uuid),
+                file_md5: Some(hash.md5),
+                file_name: Some(file_name.as_bytes().to_vec()),
+                file_size: Some(hash.size),
+                subcmd: Some(1),
+                ..Default::default()
+            },
+            seq,
+            ran,
+            time,
+        );
+        let resp = self.send_and_wait(req).await?;
+        self.engine
+            .read()
+            .await
+            .decode_send_message_response(resp.body)?;
+        Ok(MessageReceipt {
+            seqs: vec![seq],
+            rands: vec![ran],
+            time,
+        })
+    }
+
+    /// 获取离线文件下载链接，sender 为发送者 QQ 号
+    pub async fn get_offline_file_download(&self, sender: i64, uuid: Vec<u8>) -> RQResult<String> {
+        let req = self
+            .engine
+            .read()
+            .await
+            .build_offline_file_download_packet(sender, uuid);
+        let resp = self.send_and_wait(req).await?;
+        self.engine
+            .read()
+            .await
+            .decode_offline_file_download_response(resp.body)
+    }
+
     pub async fn upload_friend_image(&self, target: i64, data: &[u8]) -> RQResult<FriendImage> {
         let image_info = ImageInfo::try_new(&data)?;
         let image_store = self.get_off_pic_store(target, &image_info).await?;
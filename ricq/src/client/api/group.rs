@@ -1,25 +1,35 @@
 use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::time::{Duration, UNIX_EPOCH};
 
 use bytes::Bytes;
 use cached::Cached;
 use prost::Message;
+use tokio::io::{AsyncRead, AsyncSeek};
 
 use ricq_core::command::common::PbToBytes;
 use ricq_core::command::img_store::GroupImageStoreResp;
-use ricq_core::command::multi_msg::gen_forward_preview;
+use ricq_core::command::multi_msg::{
+    forward_message_chain, gen_forward_preview, gen_forward_template, gen_long_message_template,
+    long_message_chain, MultiMsgTarget,
+};
+use ricq_core::command::online_push::GroupMessagePart;
 use ricq_core::command::{friendlist::*, oidb_svc::*, profile_service::*};
 use ricq_core::common::group_code2uin;
 use ricq_core::hex::encode_hex;
-use ricq_core::highway::BdhInput;
-use ricq_core::msg::elem::{Anonymous, GroupImage, RichMsg, VideoFile};
-use ricq_core::msg::MessageChain;
+use ricq_core::highway::{BdhInput, FileUploadExtInput};
+use ricq_core::msg::elem::{Anonymous, GroupImage, VideoFile};
+use ricq_core::msg::{MessageChain, SendMode, SendPlan};
 use ricq_core::pb;
 use ricq_core::pb::short_video::ShortVideoUploadRsp;
-use ricq_core::structs::{ForwardMessage, GroupFileCount, GroupFileList, MessageNode};
+use ricq_core::structs::{
+    ForwardMessage, GroupFileCount, GroupFileList, GroupFileUpload, MessageNode,
+};
 use ricq_core::structs::{GroupAudio, GroupMemberPermission};
-use ricq_core::structs::{GroupInfo, GroupMemberInfo, MessageReceipt};
+use ricq_core::structs::{GroupInfo, GroupMemberInfo, GroupMessage, MessageReceipt};
 
+use crate::client::highway::hash_reader;
+use crate::client::SendTarget;
 use crate::structs::ImageInfo;
 use crate::{RQError, RQResult};
 
@@ -105,6 +115,28 @@ impl super::super::Client {
             .await
     }
 
+    /// 按 mode 发送群消息，消息过大时拆分或作为长消息发送，返回每条消息的回执
+    pub async fn send_group_message_with_mode(
+        &self,
+        group_code: i64,
+        message_chain: MessageChain,
+        mode: SendMode,
+    ) -> RQResult<Vec<MessageReceipt>> {
+        match message_chain.plan_send(mode) {
+            SendPlan::Direct(chain) => Ok(vec![self.send_group_message(group_code, chain).await?]),
+            SendPlan::Split(parts) => {
+                let mut receipts = Vec::with_capacity(parts.len());
+                for chain in parts {
+                    receipts.push(self.send_group_message(group_code, chain).await?);
+                }
+                Ok(receipts)
+            }
+            SendPlan::LongMessage(chain) => {
+                Ok(vec![self.send_group_long_message(group_code, chain).await?])
+            }
+        }
+    }
+
     /// 发送群语音
     pub async fn send_group_audio(
         &self,
@@ -121,6 +153,8 @@ impl super::super::Client {
         elems: Vec<pb::msg::Elem>,
         ptt: Option<pb::msg::Ptt>,
     ) -> RQResult<MessageReceipt> {
+        self.acquire_send_permit(Some(SendTarget::Group(group_code)))
+            .await?;
         let ran = (rand::random::<u32>() >> 1) as i32;
         let (tx, rx) = tokio::sync::oneshot::channel();
         {
@@ -747,14 +781,13 @@ impl super::super::Client {
         Ok(decode)
     }
 
-    /// 发送群消息
-    /// 仅在多张图片时需要，发送文字不需要
+    /// 发送群长消息，消息上传后以卡片形式发送
     pub async fn send_group_long_message(
         &self,
         group_code: i64,
         message_chain: MessageChain,
     ) -> RQResult<MessageReceipt> {
-        let brief = "[图片][图片][图片]"; // TODO brief
+        let brief = message_chain.brief();
         let res_id = self
             .upload_msgs(
                 group_code,
@@ -768,30 +801,12 @@ impl super::super::Client {
                 true,
             )
             .await?;
-        let template=format!(
-            "<?xml version='1.0' encoding='UTF-8' standalone='yes' ?><msg serviceID=\"35\" templateID=\"1\" action=\"viewMultiMsg\" brief=\"{}\" m_resid=\"{}\" m_fileName=\"{}\" sourceMsgId=\"0\" url=\"\" flag=\"3\" adverSign=\"0\" multiMsgFlag=\"1\"><item layout=\"1\"><title>{}</title><hr hidden=\"false\" style=\"0\" /><summary>点击查看完整消息</summary></item><source name=\"聊天记录\" icon=\"\" action=\"\" appid=\"-1\" /></msg>",
-            brief,
-            res_id,
-            UNIX_EPOCH.elapsed().unwrap().as_millis(),
-            brief);
-        let mut chain = MessageChain::default();
-        chain.push(RichMsg {
-            service_id: 35,
-            template1: template,
-        });
-        chain.0.extend(vec![
-            pb::msg::elem::Elem::Text(pb::msg::Text {
-                str: Some("你的QQ暂不支持查看[转发多条消息]，请期待后续版本。".into()),
-                ..Default::default()
-            }),
-            pb::msg::elem::Elem::GeneralFlags(pb::msg::GeneralFlags {
-                long_text_flag: Some(1),
-                long_text_resid: Some(res_id),
-                pendant_id: Some(0),
-                pb_reserve: Some(vec![0x78, 0x00, 0xF8, 0x01, 0x00, 0xC8, 0x02, 0x00]), // TODO 15=73255?
-                ..Default::default()
-            }),
-        ]);
+        let template = gen_long_message_template(
+            &res_id,
+            &UNIX_EPOCH.elapsed().unwrap().as_millis().to_string(),
+            &brief,
+        );
+        let chain = long_message_chain(template, res_id);
         self._send_group_message(group_code, chain.into(), None)
             .await
     }
@@ -805,27 +820,14 @@ impl super::super::Client {
         let t_sum = msgs.len();
         let preview = gen_forward_preview(&msgs);
         let res_id = self.upload_msgs(group_code, msgs, false).await?;
-        // TODO friend template?
-        let template = format!(
-            r##"<?xml version='1.0' encoding='UTF-8' standalone='yes' ?><msg serviceID="35" templateID="1" action="viewMultiMsg" brief="[聊天记录]" m_resid="{}" m_fileName="{}" tSum="{}" sourceMsgId="0" url="" flag="3" adverSign="0" multiMsgFlag="0"><item layout="1" advertiser_id="0" aid="0"><title size="34" maxLines="2" lineSpace="12">群聊的聊天记录</title>{}<hr hidden="false" style="0" /><summary size="26" color="#777777">查看{}条转发消息</summary></item><source name="聊天记录" icon="" action="" appid="-1" /></msg>"##,
-            res_id,
-            UNIX_EPOCH.elapsed().unwrap().as_millis(), // TODO m_filename?
+        let template = gen_forward_template(
+            &res_id,
+            &UNIX_EPOCH.elapsed().unwrap().as_millis().to_string(), // TODO m_filename?
+            MultiMsgTarget::Group(group_code).forward_title(),
             t_sum,
-            preview,
-            t_sum
+            &preview,
         );
-        let mut chain = MessageChain::default();
-        chain.push(RichMsg {
-            service_id: 35,
-            template1: template,
-        });
-        chain
-            .0
-            .push(pb::msg::elem::Elem::GeneralFlags(pb::msg::GeneralFlags {
-                pendant_id: Some(0),
-                pb_reserve: Some(vec![0x78, 0x00, 0xF8, 0x01, 0x00, 0xC8, 0x02, 0x00]),
-                ..Default::default()
-            }));
+        let chain = forward_message_chain(template);
         self._send_group_message(group_code, chain.into(), None)
             .await
     }
@@ -926,4 +928,284 @@ impl super::super::Client {
             .await
             .decode_group_file_download_response(resp.body, file_name)
     }
+
+    /// 上传群文件并发送到群，parent_folder_id 为 `/` 时上传到根目录
+    ///
+    /// 从开头分块读取 reader，不会一次读入内存，内存中的数据可以用 `std::io::Cursor` 包装
+    pub async fn upload_group_file<R: AsyncRead + AsyncSeek + Unpin>(
+        &self,
+        group_code: i64,
+        parent_folder_id: &str,
+        file_name: &str,
+        mut reader: R,
+    ) -> RQResult<GroupFileUpload> {
+        let hash = hash_reader(&mut reader).await?;
+        let req = self
+            .engine
+            .read()
+            .await
+            .build_group_file_upload_request_packet(
+                group_code,
+                parent_folder_id.into(),
+                file_name.into(),
+                &hash,
+            );
+        let resp = self.send_and_wait(req).await?;
+        let rsp = self
+            .engine
+            .read()
+            .await
+            .decode_group_file_upload_response(resp.body)?;
+        let upload = GroupFileUpload {
+            file_id: rsp.file_id().into(),
+            bus_id: rsp.bus_id(),
+        };
+        if !rsp.bool_file_exist() {
+            let addr = match self.highway_addrs.read().await.first() {
+                Some(addr) => (*addr).into(),
+                None => SocketAddr::new(
+                    rsp.upload_ip()
+                        .parse()
+                        .map_err(|_| RQError::EmptyField("upload_ip"))?,
+                    rsp.upload_port() as u16,
+                ),
+            };
+            let session = self.highway_session.read().await;
+            let ext = session.build_file_upload_ext(FileUploadExtInput {
+                group_code,
+                receiver: group_code,
+                bus_id: upload.bus_id,
+                name: file_name.into(),
+                size: hash.size,
+                md5: hash.md5.clone(),
+                sha1: hash.sha1.clone(),
+                file_id: upload.file_id.clone().into_bytes(),
+                upload_key: rsp.check_key().to_vec(),
+                host: rsp.upload_ip_lan_v4.first().cloned().unwrap_or_default(),
+                port: rsp.upload_port(),
+            });
+            let ticket = session.sig_session.to_vec();
+            drop(session);
+            self.highway_upload_bdh_reader(
+                addr,
+                BdhInput {
+                    command_id: 71,
+                    ticket,
+                    ext,
+                    encrypt: false,
+                    chunk_size: 256 * 1024,
+                    send_echo: true,
+                },
+                &mut reader,
+                hash.size as u64,
+                hash.md5,
+            )
+            .await?;
+        }
+        let req = self
+            .engine
+            .read()
+            .await
+            .build_group_file_feed_request_packet(
+                group_code as u64,
+                upload.file_id.clone(),
+                upload.bus_id as u32,
+                rand::random(),
+            );
+        let resp = self.send_and_wait(req).await?;
+        self.engine
+            .read()
+            .await
+            .decode_group_file_feed_response(resp.body)?;
+        Ok(upload)
+    }
+
+    /// 获取群历史消息，包含 begin_seq 和 end_seq，分片消息会被合并
+    ///
+    /// 被范围截断的分片消息会额外获取其他分片，无法获取全部分片时返回错误
+    ///
+    /// 一次获取的消息数量有限，较大范围请用 [`group_message_pager`](Self::group_message_pager)
+    pub async fn get_group_messages(
+        &self,
+        group_code: i64,
+        begin_seq: i64,
+        end_seq: i64,
+    ) -> RQResult<Vec<GroupMessage>> {
+        let (_, groups) = self
+            .fetch_group_message_parts(group_code, begin_seq, end_seq)
+            .await?;
+        let mut messages = Vec::with_capacity(groups.len());
+        for parts in groups {
+            if is_complete(&parts) {
+                messages.push(self.parse_group_message(parts).await?);
+            } else if let Some(message) = self.get_group_message(group_code, parts[0].seq).await? {
+                messages.push(message);
+            }
+        }
+        Ok(messages)
+    }
+
+    /// 获取单条群消息，可以用于获取 `Reply.reply_seq` 对应的原消息
+    ///
+    /// seq 为分片消息的任意一个分片时，会获取其他分片并合并，无法获取全部分片时返回错误
+    pub async fn get_group_message(
+        &self,
+        group_code: i64,
+        seq: i32,
+    ) -> RQResult<Option<GroupMessage>> {
+        let (mut begin, mut end) = (seq as i64, seq as i64);
+        for _ in 0..MAX_PART_FETCHES {
+            let (_, groups) = self
+                .fetch_group_message_parts(group_code, begin, end)
+                .await?;
+            let Some(parts) = groups
+                .into_iter()
+                .find(|parts| parts.iter().any(|p| p.seq == seq))
+            else {
+                return Ok(None);
+            };
+            if is_complete(&parts) {
+                return self.parse_group_message(parts).await.map(Some);
+            }
+            // 分片的 seq 通常连续，每次按 pkg_num 向前后扩大范围
+            let pkg_num = parts[0].pkg_num as i64;
+            begin = (begin - pkg_num).max(1);
+            end += pkg_num;
+        }
+        Err(RQError::Other(format!(
+            "incomplete multi-part group message at seq {seq}"
+        )))
+    }
+
+    /// 分页获取群历史消息，按 seq 从小到大
+    pub fn group_message_pager(
+        &self,
+        group_code: i64,
+        begin_seq: i64,
+        end_seq: i64,
+    ) -> GroupMessagePager<'_> {
+        GroupMessagePager {
+            client: self,
+            group_code,
+            next_seq: begin_seq,
+            end_seq,
+            page_size: GroupMessagePager::DEFAULT_PAGE_SIZE,
+        }
+    }
+
+    // 返回 (return_end_seq, 按分片合并后的消息)
+    async fn fetch_group_message_parts(
+        &self,
+        group_code: i64,
+        begin_seq: i64,
+        end_seq: i64,
+    ) -> RQResult<(i64, Vec<Vec<GroupMessagePart>>)> {
+        let req = self
+            .engine
+            .read()
+            .await
+            .build_get_group_msg_request(group_code, begin_seq, end_seq);
+        let resp = self.send_and_wait(req).await?;
+        let history = self
+            .engine
+            .read()
+            .await
+            .decode_get_group_msg_response(resp.body)?;
+        let mut groups: Vec<Vec<GroupMessagePart>> = Vec::new();
+        let mut div_index: HashMap<i32, usize> = HashMap::new();
+        for mut part in history.parts {
+            if part.group_code == 0 {
+                part.group_code = group_code;
+            }
+            if part.pkg_num > 1 {
+                if let Some(&i) = div_index.get(&part.div_seq) {
+                    groups[i].push(part);
+                    continue;
+                }
+                div_index.insert(part.div_seq, groups.len());
+            }
+            groups.push(vec![part]);
+        }
+        groups.sort_by_key(|parts| parts.iter().map(|p| p.seq).min());
+        Ok((history.return_end_seq, groups))
+    }
+}
+
+/// 获取分片消息时最多请求的次数
+const MAX_PART_FETCHES: usize = 4;
+
+fn is_complete(parts: &[GroupMessagePart]) -> bool {
+    parts.len() >= parts[0].pkg_num.max(1) as usize
+}
+
+/// 群历史消息分页，由 [`Client::group_message_pager`](super::super::Client::group_message_pager) 创建
+pub struct GroupMessagePager<'a> {
+    client: &'a super::super::Client,
+    group_code: i64,
+    next_seq: i64,
+    end_seq: i64,
+    page_size: i64,
+}
+
+impl GroupMessagePager<'_> {
+    pub const DEFAULT_PAGE_SIZE: i64 = 20;
+
+    /// 每页请求的 seq 数量
+    pub fn page_size(mut self, page_size: i64) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// 下一页的起始 seq
+    pub fn next_seq(&self) -> i64 {
+        self.next_seq
+    }
+
+    /// 获取下一页，没有更多消息时返回 `Ok(None)`，页中可能没有消息（例如已撤回）
+    ///
+    /// 页末不完整的分片消息留到下一页；一页中只有这条消息时，扩大范围直到获取全部分片。
+    /// 仍无法获取全部分片时返回错误，并跳过这条消息，下次调用从它之后继续
+    pub async fn next_page(&mut self) -> RQResult<Option<Vec<GroupMessage>>> {
+        if self.next_seq > self.end_seq {
+            return Ok(None);
+        }
+        let mut end = (self.next_seq + self.page_size - 1).min(self.end_seq);
+        let mut fetches = 0;
+        let (next_seq, groups) = loop {
+            let (return_end, mut groups) = self
+                .client
+                .fetch_group_message_parts(self.group_code, self.next_seq, end)
+                .await?;
+            fetches += 1;
+            let next_seq = return_end.max(end) + 1;
+            let Some(last) = groups.last().filter(|last| !is_complete(last)) else {
+                break (next_seq, groups);
+            };
+            let first_seq = last.iter().map(|p| p.seq).min().unwrap_or_default() as i64;
+            if first_seq > self.next_seq {
+                groups.pop();
+                break (first_seq, groups);
+            }
+            if fetches >= MAX_PART_FETCHES {
+                let last_seq = last.iter().map(|p| p.seq).max().unwrap_or_default() as i64;
+                self.next_seq = last_seq + 1;
+                return Err(RQError::Other(format!(
+                    "incomplete multi-part group message at seq {first_seq}"
+                )));
+            }
+            // 先按 pkg_num 获取到最后一个分片，仍不完整时（中间插入了其他消息）继续扩大
+            let pkg_num = last[0].pkg_num as i64;
+            end = if end < first_seq + pkg_num - 1 {
+                first_seq + pkg_num - 1
+            } else {
+                end + pkg_num
+            };
+        };
+        self.next_seq = next_seq;
+        let mut messages = Vec::with_capacity(groups.len());
+        for parts in groups {
+            messages.push(self.client.parse_group_message(parts).await?);
+        }
+        Ok(Some(messages))
+    }
 }
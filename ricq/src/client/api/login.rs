@@ -1,5 +1,7 @@
 use std::sync::atomic::Ordering;
 
+use bytes::Bytes;
+
 use crate::jce::SvcRespRegister;
 use crate::{RQError, RQResult};
 use ricq_core::command::wtlogin::*;
@@ -7,6 +9,18 @@ use ricq_core::token::Token;
 
 /// 登录相关
 impl super::super::Client {
+    /// 通过 SignProvider 计算 wtlogin.login 子命令的 tlv 0x544
+    async fn t544_energy(&self, sub_command: u32) -> RQResult<Option<Bytes>> {
+        let (uin, salt) = {
+            let engine = self.engine.read().await;
+            (engine.uin(), engine.t544_salt(sub_command))
+        };
+        let provider = self.sign_provider.read().await.clone();
+        provider
+            .energy(uin, &format!("810_{sub_command:x}"), salt)
+            .await
+    }
+
     /// 二维码登录 - 获取二维码
     pub async fn fetch_qrcode(&self) -> RQResult<QRCodeState> {
         let req = self.engine.read().await.build_qrcode_fetch_request_packet();
@@ -44,11 +58,13 @@ impl super::super::Client {
         tmp_no_pic_sig: &[u8],
         tgt_qr: &[u8],
     ) -> RQResult<LoginResponse> {
-        let req =
-            self.engine
-                .read()
-                .await
-                .build_qrcode_login_packet(tmp_pwd, tmp_no_pic_sig, tgt_qr);
+        let t544 = self.t544_energy(9).await?;
+        let req = self.engine.read().await.build_qrcode_login_packet(
+            tmp_pwd,
+            tmp_no_pic_sig,
+            tgt_qr,
+            t544.as_deref(),
+        );
         let resp = self.send_and_wait(req).await?;
         let resp = self.engine.read().await.decode_login_response(resp.body)?;
         self.process_login_response(&resp).await;
@@ -62,11 +78,12 @@ impl super::super::Client {
         password_md5: &[u8],
     ) -> RQResult<LoginResponse> {
         self.engine.read().await.uin.store(uin, Ordering::Relaxed);
+        let t544 = self.t544_energy(9).await?;
         let req = self
             .engine
             .read()
             .await
-            .build_login_packet(password_md5, true);
+            .build_login_packet(password_md5, true, t544.as_deref());
         let resp = self.send_and_wait(req).await?;
         let resp = self.engine.read().await.decode_login_response(resp.body)?;
         self.process_login_response(&resp).await;
@@ -89,11 +106,12 @@ impl super::super::Client {
 
     /// 密码登录 - 提交短信验证码
     pub async fn submit_sms_code(&self, code: &str) -> RQResult<LoginResponse> {
+        let t544 = self.t544_energy(7).await?;
         let req = self
             .engine
             .read()
             .await
-            .build_sms_code_submit_packet(code.trim());
+            .build_sms_code_submit_packet(code.trim(), t544.as_deref());
         let resp = self.send_and_wait(req).await?;
         let resp = self.engine.read().await.decode_login_response(resp.body)?;
         self.process_login_response(&resp).await;
@@ -102,7 +120,12 @@ impl super::super::Client {
 
     /// 密码登录 - 提交滑块ticket
     pub async fn submit_ticket(&self, ticket: &str) -> RQResult<LoginResponse> {
-        let req = self.engine.read().await.build_ticket_submit_packet(ticket);
+        let t544 = self.t544_energy(2).await?;
+        let req = self
+            .engine
+            .read()
+            .await
+            .build_ticket_submit_packet(ticket, t544.as_deref());
         let resp = self.send_and_wait(req).await?;
         let resp = self.engine.read().await.decode_login_response(resp.body)?;
         self.process_login_response(&resp).await;
@@ -1,4 +1,6 @@
+use std::future::Future;
 use std::net::SocketAddr;
+use std::pin::Pin;
 use std::sync::atomic::Ordering;
 use std::time::UNIX_EPOCH;
 
@@ -6,15 +8,19 @@ use bytes::Bytes;
 use cached::Cached;
 
 use ricq_core::command::message_svc::MessageSyncResponse;
+use ricq_core::command::multi_msg::{parse_multi_msg_transmit, MultiMsgTarget};
 use ricq_core::command::oidb_svc::*;
-use ricq_core::common::{group_code2uin, RQAddr};
+use ricq_core::common::RQAddr;
 use ricq_core::highway::BdhInput;
+use ricq_core::msg::elem::{Forward, RQElem};
 use ricq_core::msg::MessageChain;
 use ricq_core::pb;
 use ricq_core::structs::Status;
 use ricq_core::structs::SummaryCardInfo;
-use ricq_core::structs::{ForwardMessage, MessageReceipt};
+use ricq_core::structs::{ForwardMessage, ForwardNode, MessageReceipt};
 
+use crate::client::http::http_get;
+use crate::client::SendTarget;
 use crate::jce::SvcDevLoginInfo;
 use crate::{RQError, RQResult};
 
@@ -22,6 +28,30 @@ mod friend;
 mod group;
 mod login;
 
+pub use group::GroupMessagePager;
+
+/// 下载嵌套转发的最大层数
+const MAX_RESOLVE_DEPTH: usize = 8;
+
+// 消息中的转发消息或长消息（`GeneralFlags.long_text_resid`）
+fn find_forward(chain: &MessageChain) -> Option<Forward> {
+    chain.clone().into_iter().find_map(|elem| match elem {
+        RQElem::Forward(forward) if !forward.res_id.is_empty() => Some(forward),
+        RQElem::Other(elem) => match *elem {
+            pb::msg::elem::Elem::GeneralFlags(flags) => flags
+                .long_text_resid
+                .filter(|res_id| !res_id.is_empty())
+                .map(|res_id| Forward {
+                    res_id,
+                    is_long: true,
+                    ..Default::default()
+                }),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
 /// API
 impl super::Client {
     /// 设置在线状态 TODO net_type
@@ -284,16 +314,20 @@ impl super::Client {
     // 准备上传消息，获取 ukey, resid, ip, port
     async fn multi_msg_apply_up(
         &self,
-        dst_uin: i64,
+        target: MultiMsgTarget,
         data: &[u8],
         is_long: bool,
     ) -> RQResult<pb::multimsg::MultiMsgApplyUpRsp> {
-        let req = self.engine.read().await.build_multi_msg_apply_up_req(
-            data.len() as i64,
-            md5::compute(data).to_vec(),
-            if is_long { 1 } else { 2 },
-            dst_uin,
-        );
+        let req = self
+            .engine
+            .read()
+            .await
+            .build_multi_msg_apply_up_req_with_target(
+                data.len() as i64,
+                md5::compute(data).to_vec(),
+                if is_long { 1 } else { 2 },
+                target,
+            );
         let resp = self.send_and_wait(req).await?;
         self.engine
             .read()
@@ -301,21 +335,40 @@ impl super::Client {
             .decode_multi_msg_apply_up_resp(resp.body)
     }
 
-    // 上传长消息、转发消息 私聊未测试
+    // 上传长消息、转发消息
     pub async fn upload_msgs(
         &self,
         group_code: i64,
         msgs: Vec<ForwardMessage>,
         is_long: bool,
+    ) -> RQResult<String> {
+        self.upload_multi_msg(MultiMsgTarget::Group(group_code), msgs, is_long)
+            .await
+    }
+
+    /// 上传好友的长消息、转发消息，返回 res_id
+    pub async fn upload_friend_msgs(
+        &self,
+        target: i64,
+        msgs: Vec<ForwardMessage>,
+        is_long: bool,
+    ) -> RQResult<String> {
+        self.upload_multi_msg(MultiMsgTarget::Friend(target), msgs, is_long)
+            .await
+    }
+
+    async fn upload_multi_msg(
+        &self,
+        target: MultiMsgTarget,
+        msgs: Vec<ForwardMessage>,
+        is_long: bool,
     ) -> RQResult<String> {
         let data = self
             .engine
             .read()
             .await
-            .calculate_validation_data(msgs, group_code);
-        let rsp = self
-            .multi_msg_apply_up(group_code2uin(group_code), &data, is_long)
-            .await?;
+            .calculate_multi_msg_data(msgs, target);
+        let rsp = self.multi_msg_apply_up(target, &data, is_long).await?;
         let resid = rsp.msg_resid;
         if self.highway_session.read().await.session_key.is_empty() {
             return Err(RQError::EmptyField("highway_session_key is empty"));
@@ -326,11 +379,12 @@ impl super::Client {
             .zip(rsp.uint32_up_port.into_iter())
             .map(|(ip, port)| RQAddr(ip as u32, port as u16))
             .collect();
-        let body =
-            self.engine
-                .read()
-                .await
-                .build_long_req(group_code2uin(group_code), data, rsp.msg_ukey);
+        let body = self.engine.read().await.build_long_req_with_msg_type(
+            target.dst_uin(),
+            target.msg_type(),
+            data,
+            rsp.msg_ukey,
+        );
         for addr in addrs {
             match self
                 .highway_upload_bdh(
@@ -356,12 +410,13 @@ impl super::Client {
     async fn multi_msg_apply_down(
         &self,
         res_id: String,
+        bu_type: i32,
     ) -> RQResult<pb::multimsg::MultiMsgApplyDownRsp> {
         let req = self
             .engine
             .read()
             .await
-            .build_multi_msg_apply_down_req(res_id);
+            .build_multi_msg_apply_down_req_with_bu_type(res_id, bu_type);
         let resp = self.send_and_wait(req).await?;
         self.engine
             .read()
@@ -369,31 +424,150 @@ impl super::Client {
             .decode_multi_msg_apply_down_resp(resp.body)
     }
 
-    pub async fn download_msgs(&self, res_id: String) -> RQResult<Vec<ForwardMessage>> {
-        let mut resp = self.multi_msg_apply_down(res_id).await?;
+    // 下载并解密转发消息、长消息
+    async fn download_multi_msg(
+        &self,
+        res_id: String,
+        is_long: bool,
+    ) -> RQResult<pb::msg::PbMultiMsgTransmit> {
+        let resp = self
+            .multi_msg_apply_down(res_id, if is_long { 1 } else { 2 })
+            .await?;
         if resp.result != 0 {
             return Err(RQError::Other(format!(
                 "multi_msg_apply_down result {}",
                 resp.result
             )));
         }
-        let prefix=if let Some(pb::multimsg::ExternMsg { channel_type }) = resp.msg_extern_info && channel_type == 2 {
-            "https://ssl.htdata.qq.com".into()
-        } else {
-            let addr = SocketAddr::from(RQAddr(resp.down_ip.pop().ok_or(RQError::EmptyField("down_ip"))?,resp.down_port.pop().ok_or(RQError::EmptyField("down_port"))? as u16));
-            format!("http://{addr}")
+        // channel_type 为 2 时也可以直接用 down_ip 通过 http 下载
+        let addrs: Vec<SocketAddr> = resp
+            .down_ip
+            .iter()
+            .zip(resp.down_port.iter())
+            .map(|(ip, port)| RQAddr(*ip, *port as u16).into())
+            .collect();
+        if addrs.is_empty() {
+            return Err(RQError::EmptyField("down_ip"));
+        }
+        let path = String::from_utf8_lossy(&resp.thumb_down_para).into_owned();
+        let proxy = self.proxy.read().await.clone();
+        let mut last_err = RQError::Other("failed to download multi message".into());
+        for addr in addrs {
+            match http_get(proxy.as_ref(), addr, &path, self.request_timeout()).await {
+                Ok(data) => {
+                    return self
+                        .engine
+                        .read()
+                        .await
+                        .decode_multi_msg_down_data(Bytes::from(data), &resp.msg_key)
+                }
+                Err(err) => last_err = err,
+            }
+        }
+        Err(last_err)
+    }
+
+    /// 下载转发消息
+    pub async fn download_msgs(&self, res_id: String) -> RQResult<Vec<ForwardMessage>> {
+        let transmit = self.download_multi_msg(res_id, false).await?;
+        Ok(parse_multi_msg_transmit(transmit))
+    }
+
+    /// 下载转发消息或长消息的内容，嵌套的转发也会一并下载
+    pub async fn resolve_forward(&self, forward: &Forward) -> RQResult<Vec<ForwardMessage>> {
+        if forward.res_id.is_empty() {
+            return Err(RQError::EmptyField("res_id"));
+        }
+        let transmit = self
+            .download_multi_msg(forward.res_id.clone(), forward.is_long)
+            .await?;
+        self.resolve_nested_forward(parse_multi_msg_transmit(transmit), 0)
+            .await
+    }
+
+    /// 识别消息中的转发消息或长消息（`GeneralFlags.long_text_resid`），并下载内容
+    ///
+    /// 不是转发消息或长消息时返回 `Ok(None)`
+    pub async fn resolve_forward_in(
+        &self,
+        chain: &MessageChain,
+    ) -> RQResult<Option<Vec<ForwardMessage>>> {
+        match find_forward(chain) {
+            Some(forward) => self.resolve_forward(&forward).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    // 收到长消息时下载完整内容替换消息链，下载失败时保留原消息
+    pub(crate) async fn resolve_long_message(&self, chain: &mut MessageChain) {
+        let Some(forward) = find_forward(chain).filter(|f| f.is_long) else {
+            return;
         };
-        let _url = format!(
-            "{}{}",
-            prefix,
-            String::from_utf8_lossy(&resp.thumb_down_para)
-        );
-        let _encrypt_key = resp.msg_key;
-        // TODO get data and decrypt
-        // TODO decoder -> LongRspBody
-        // TODO uncompress
-        // TODO link message, convert to Vec<ForwardMessage>
-        todo!()
+        let transmit = match self.download_multi_msg(forward.res_id, true).await {
+            Ok(transmit) => transmit,
+            Err(err) => {
+                tracing::warn!("failed to download long message {}", err);
+                return;
+            }
+        };
+        let elems: Vec<_> = parse_multi_msg_transmit(transmit)
+            .into_iter()
+            .flat_map(|m| match m {
+                ForwardMessage::Message(m) => m.elements.0,
+                ForwardMessage::Forward(_) => Vec::new(),
+            })
+            .collect();
+        if !elems.is_empty() {
+            *chain = MessageChain(elems);
+        }
+    }
+
+    // 下载仍未展开的嵌套转发（只有 res_id 没有对应 pb_item_list 的情况）
+    fn resolve_nested_forward(
+        &self,
+        nodes: Vec<ForwardMessage>,
+        depth: usize,
+    ) -> Pin<Box<dyn Future<Output = RQResult<Vec<ForwardMessage>>> + Send + '_>> {
+        Box::pin(async move {
+            let mut result = Vec::with_capacity(nodes.len());
+            for node in nodes {
+                let node = match node {
+                    ForwardMessage::Forward(mut f) => {
+                        f.nodes = self.resolve_nested_forward(f.nodes, depth).await?;
+                        ForwardMessage::Forward(f)
+                    }
+                    ForwardMessage::Message(m) => {
+                        let forward = m.elements.clone().into_iter().find_map(|e| match e {
+                            RQElem::Forward(f) if !f.res_id.is_empty() => Some(f),
+                            _ => None,
+                        });
+                        match forward {
+                            Some(forward) if depth < MAX_RESOLVE_DEPTH => {
+                                let transmit = self
+                                    .download_multi_msg(forward.res_id, forward.is_long)
+                                    .await?;
+                                let nodes = self
+                                    .resolve_nested_forward(
+                                        parse_multi_msg_transmit(transmit),
+                                        depth + 1,
+                                    )
+                                    .await?;
+                                ForwardNode {
+                                    sender_id: m.sender_id,
+                                    time: m.time,
+                                    sender_name: m.sender_name,
+                                    nodes,
+                                }
+                                .into()
+                            }
+                            _ => ForwardMessage::Message(m),
+                        }
+                    }
+                };
+                result.push(node);
+            }
+            Ok(result)
+        })
     }
 
     /// 发送消息
@@ -403,6 +577,13 @@ impl super::Client {
         message_chain: MessageChain,
         ptt: Option<pb::msg::Ptt>,
     ) -> RQResult<MessageReceipt> {
+        let target = match &routing_head {
+            pb::msg::routing_head::RoutingHead::C2c(c2c) => c2c.to_uin.map(SendTarget::Friend),
+            pb::msg::routing_head::RoutingHead::Grp(grp) => grp.group_code.map(SendTarget::Group),
+            pb::msg::routing_head::RoutingHead::GrpTmp(tmp) => tmp.to_uin.map(SendTarget::Friend),
+            _ => None,
+        };
+        self.acquire_send_permit(target).await?;
         let time = UNIX_EPOCH.elapsed().unwrap().as_secs() as i64;
         let seq = self.engine.read().await.next_friend_seq();
         let ran = (rand::random::<u32>() >> 1) as i32;
@@ -418,7 +599,11 @@ impl super::Client {
             ran,
             time,
         );
-        self.send_and_wait(req).await?;
+        let resp = self.send_and_wait(req).await?;
+        self.engine
+            .read()
+            .await
+            .decode_send_message_response(resp.body)?;
         let receipt = MessageReceipt {
             seqs: vec![seq],
             rands: vec![ran],
@@ -1,12 +1,18 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use ricq_core::command::profile_service::{JoinGroupRequest, NewFriendRequest, SelfInvited};
+use ricq_core::msg::elem::{At, Reply, Text};
+use ricq_core::msg::{MessageChain, MessageElem};
+use ricq_core::structs::MessageReceipt;
 use ricq_core::structs::{
-    DeleteFriend, FriendAudioMessage, FriendInfo, FriendMessageRecall, FriendPoke,
-    GroupAudioMessage, GroupDisband, GroupLeave, GroupMessageRecall, GroupMute, GroupNameUpdate,
-    GroupPoke, GroupTempMessage, MemberPermissionChange, NewMember,
+    DecodeError, DeleteFriend, FriendAudioMessage, FriendFileMessage, FriendInfo,
+    FriendInputStatus, FriendMessageRecall, FriendNicknameUpdate, FriendPoke, FriendRemarkUpdate,
+    GroupAudioMessage, GroupDisband, GroupEssenceChange, GroupLeave, GroupMessageRecall, GroupMute,
+    GroupNameUpdate, GroupPoke, GroupTempMessage, MemberCardUpdate, MemberHonorChange,
+    MemberPermissionChange, MemberSpecialTitleUpdate, NewMember, OtherClientStatusChange,
 };
-use ricq_core::{jce, RQResult};
+use ricq_core::{jce, RQResult, Token};
 
 use crate::client::NetworkStatus;
 use crate::structs::{FriendMessage, GroupMessage};
@@ -33,10 +39,68 @@ impl GroupMessageEvent {
             )
             .await
     }
+
+    /// 引用该消息回复
+    pub async fn reply(&self, mut chain: MessageChain) -> RQResult<MessageReceipt> {
+        chain.with_reply(Reply::from(&self.inner));
+        self.client
+            .send_group_message(self.inner.group_code, chain)
+            .await
+    }
+
+    /// 引用该消息回复，并在开头 At 发送者
+    pub async fn reply_with_at(&self, mut chain: MessageChain) -> RQResult<MessageReceipt> {
+        let display = if self.inner.group_card.is_empty() {
+            format!("@{}", self.inner.from_uin)
+        } else {
+            format!("@{}", self.inner.group_card)
+        };
+        let mut at = MessageChain::new(At {
+            target: self.inner.from_uin,
+            display,
+        });
+        at.push(Text::new(" ".into()));
+        let index = chain
+            .0
+            .iter()
+            .take_while(|e| matches!(e, MessageElem::SrcMsg(_) | MessageElem::AnonGroupMsg(_)))
+            .count();
+        chain.0.splice(index..index, at.0);
+        self.reply(chain).await
+    }
 }
 
 pub type FriendMessageEvent = EventWithClient<FriendMessage>;
+
+impl FriendMessageEvent {
+    /// 引用该消息回复，本账号在其他设备发送的消息会发给 `inner.target`
+    pub async fn reply(&self, mut chain: MessageChain) -> RQResult<MessageReceipt> {
+        chain.with_reply(Reply::from(&self.inner));
+        let target = if self.inner.from_uin == self.client.uin().await {
+            self.inner.target
+        } else {
+            self.inner.from_uin
+        };
+        self.client.send_friend_message(target, chain).await
+    }
+}
+
+/// 本账号在其他设备发送的群消息，本客户端发送的消息不会触发
+pub type SelfGroupMessageEvent = EventWithClient<GroupMessage>;
+/// 本账号在其他设备发送的好友消息，`inner.target` 为好友
+pub type SelfFriendMessageEvent = EventWithClient<FriendMessage>;
 pub type GroupTempMessageEvent = EventWithClient<GroupTempMessage>;
+
+impl GroupTempMessageEvent {
+    /// 引用该消息回复
+    pub async fn reply(&self, mut chain: MessageChain) -> RQResult<MessageReceipt> {
+        chain.with_reply(Reply::from(&self.inner));
+        self.client
+            .send_group_temp_message(self.inner.group_code, self.inner.from_uin, chain)
+            .await
+    }
+}
+
 pub type JoinGroupRequestEvent = EventWithClient<JoinGroupRequest>;
 
 impl JoinGroupRequestEvent {
@@ -99,6 +163,15 @@ pub type GroupPokeEvent = EventWithClient<GroupPoke>;
 pub type GroupNameUpdateEvent = EventWithClient<GroupNameUpdate>;
 pub type DeleteFriendEvent = EventWithClient<DeleteFriend>;
 pub type MemberPermissionChangeEvent = EventWithClient<MemberPermissionChange>;
+pub type MemberCardUpdateEvent = EventWithClient<MemberCardUpdate>;
+pub type MemberSpecialTitleUpdateEvent = EventWithClient<MemberSpecialTitleUpdate>;
+pub type MemberHonorChangeEvent = EventWithClient<MemberHonorChange>;
+pub type GroupEssenceChangeEvent = EventWithClient<GroupEssenceChange>;
+pub type FriendNicknameUpdateEvent = EventWithClient<FriendNicknameUpdate>;
+pub type FriendRemarkUpdateEvent = EventWithClient<FriendRemarkUpdate>;
+pub type OtherClientStatusChangeEvent = EventWithClient<OtherClientStatusChange>;
+pub type FriendInputStatusEvent = EventWithClient<FriendInputStatus>;
+pub type DecodeErrorEvent = EventWithClient<DecodeError>;
 pub type SelfInvitedEvent = EventWithClient<SelfInvited>;
 pub type GroupAudioMessageEvent = EventWithClient<GroupAudioMessage>;
 
@@ -120,6 +193,16 @@ impl FriendAudioMessageEvent {
     }
 }
 
+pub type FriendFileMessageEvent = EventWithClient<FriendFileMessage>;
+
+impl FriendFileMessageEvent {
+    pub async fn url(&self) -> RQResult<String> {
+        self.client
+            .get_offline_file_download(self.inner.from_uin, self.inner.file.uuid.clone())
+            .await
+    }
+}
+
 pub type KickedOfflineEvent = EventWithClient<jce::RequestPushForceOffline>;
 pub type MSFOfflineEvent = EventWithClient<jce::RequestMSFForceOffline>;
 
@@ -149,3 +232,42 @@ impl ClientDisconnect {
         self.inner
     }
 }
+
+/// 正在重连
+#[derive(Copy, Clone, Debug)]
+pub struct Reconnecting {
+    /// 第几次尝试，从 1 开始
+    pub attempt: usize,
+    /// 本次尝试前等待的时间
+    pub delay: Duration,
+}
+
+pub type ReconnectingEvent = EventWithClient<Reconnecting>;
+
+/// 重连成功
+#[derive(Copy, Clone, Debug)]
+pub struct Reconnected {
+    /// 成功时的尝试次数
+    pub attempt: usize,
+}
+
+pub type ReconnectedEvent = EventWithClient<Reconnected>;
+
+/// 放弃重连
+#[derive(Copy, Clone, Debug)]
+pub struct ReconnectGaveUp {
+    /// 已尝试的次数
+    pub attempts: usize,
+    /// 从掉线开始经过的时间
+    pub elapsed: Duration,
+}
+
+pub type ReconnectGaveUpEvent = EventWithClient<ReconnectGaveUp>;
+
+/// sig 刷新成功，可以保存新的 token
+#[derive(Clone, Debug)]
+pub struct SigRefreshed {
+    pub token: Token,
+}
+
+pub type SigRefreshedEvent = EventWithClient<SigRefreshed>;
@@ -24,8 +24,14 @@ pub enum QEvent {
     FriendMessage(FriendMessageEvent),
     /// 群语音
     FriendAudioMessage(FriendAudioMessageEvent),
+    /// 好友离线文件
+    FriendFileMessage(FriendFileMessageEvent),
     /// 群临时消息
     GroupTempMessage(GroupTempMessageEvent),
+    /// 本账号在其他设备（手机、电脑）发送的群消息，需要 `Config::self_message_events` 开启
+    SelfGroupMessage(SelfGroupMessageEvent),
+    /// 本账号在其他设备（手机、电脑）发送的好友消息，需要 `Config::self_message_events` 开启
+    SelfFriendMessage(SelfFriendMessageEvent),
     /// 加群申请
     GroupRequest(JoinGroupRequestEvent),
     /// 加群申请
@@ -56,6 +62,24 @@ pub enum QEvent {
     DeleteFriend(DeleteFriendEvent),
     /// 群成员权限变更
     MemberPermissionChange(MemberPermissionChangeEvent),
+    /// 群名片修改
+    MemberCardUpdate(MemberCardUpdateEvent),
+    /// 群头衔修改
+    MemberSpecialTitleUpdate(MemberSpecialTitleUpdateEvent),
+    /// 群荣誉变更（龙王、群聊之火等）
+    MemberHonorChange(MemberHonorChangeEvent),
+    /// 群精华消息设置/移除
+    GroupEssenceChange(GroupEssenceChangeEvent),
+    /// 好友昵称修改
+    FriendNicknameUpdate(FriendNicknameUpdateEvent),
+    /// 好友备注修改
+    FriendRemarkUpdate(FriendRemarkUpdateEvent),
+    /// 本账号的其他客户端上线/下线
+    OtherClientStatusChange(OtherClientStatusChangeEvent),
+    /// 好友正在输入/停止输入
+    FriendInputStatus(FriendInputStatusEvent),
+    /// 服务端推送解析失败，需要 `Config::report_decode_error` 开启
+    DecodeError(DecodeErrorEvent),
     /// 被其他客户端踢下线
     /// 不能用于掉线重连，掉线重连以 start 返回为准
     KickedOffline(KickedOfflineEvent),
@@ -65,6 +89,14 @@ pub enum QEvent {
     /// 网络原因/客户端主动掉线
     /// 可用于掉线重连
     ClientDisconnect(ClientDisconnect),
+    /// 自动重连中，每次尝试前触发
+    Reconnecting(ReconnectingEvent),
+    /// 自动重连成功
+    Reconnected(ReconnectedEvent),
+    /// 自动重连达到上限，不再重连
+    ReconnectGaveUp(ReconnectGaveUpEvent),
+    /// sig 刷新成功，携带新的 token
+    SigRefreshed(SigRefreshedEvent),
 }
 
 /// 处理外发数据的接口
@@ -134,6 +166,20 @@ impl Handler for DefaultHandler {
             QEvent::GroupTempMessage(m) => {
                 tracing::info!("MESSAGE (TEMP={}): {}", m.inner.from_uin, m.inner.elements)
             }
+            QEvent::SelfGroupMessage(m) => {
+                tracing::info!(
+                    "SELF MESSAGE (GROUP={}): {}",
+                    m.inner.group_code,
+                    m.inner.elements
+                )
+            }
+            QEvent::SelfFriendMessage(m) => {
+                tracing::info!(
+                    "SELF MESSAGE (FRIEND={}): {}",
+                    m.inner.target,
+                    m.inner.elements
+                )
+            }
             QEvent::GroupRequest(m) => {
                 tracing::info!(
                     "REQUEST (GROUP={}, UIN={}): {}",
@@ -185,7 +231,10 @@ pub trait PartlyHandler: Sync {
     async fn handle_group_audio(&self, _event: GroupAudioMessageEvent) {}
     async fn handle_friend_message(&self, _event: FriendMessageEvent) {}
     async fn handle_friend_audio(&self, _event: FriendAudioMessageEvent) {}
+    async fn handle_friend_file(&self, _event: FriendFileMessageEvent) {}
     async fn handle_group_temp_message(&self, _event: GroupTempMessageEvent) {}
+    async fn handle_self_group_message(&self, _event: SelfGroupMessageEvent) {}
+    async fn handle_self_friend_message(&self, _event: SelfFriendMessageEvent) {}
     async fn handle_group_request(&self, _event: JoinGroupRequestEvent) {}
     async fn handle_self_invited(&self, _event: SelfInvitedEvent) {}
     async fn handle_friend_request(&self, _event: NewFriendRequestEvent) {}
@@ -201,9 +250,22 @@ pub trait PartlyHandler: Sync {
     async fn handle_group_name_update(&self, _event: GroupNameUpdateEvent) {}
     async fn handle_delete_friend(&self, _event: DeleteFriendEvent) {}
     async fn handle_member_permission_change(&self, _event: MemberPermissionChangeEvent) {}
+    async fn handle_member_card_update(&self, _event: MemberCardUpdateEvent) {}
+    async fn handle_member_special_title_update(&self, _event: MemberSpecialTitleUpdateEvent) {}
+    async fn handle_member_honor_change(&self, _event: MemberHonorChangeEvent) {}
+    async fn handle_group_essence_change(&self, _event: GroupEssenceChangeEvent) {}
+    async fn handle_friend_nickname_update(&self, _event: FriendNicknameUpdateEvent) {}
+    async fn handle_friend_remark_update(&self, _event: FriendRemarkUpdateEvent) {}
+    async fn handle_other_client_status_change(&self, _event: OtherClientStatusChangeEvent) {}
+    async fn handle_friend_input_status(&self, _event: FriendInputStatusEvent) {}
+    async fn handle_decode_error(&self, _event: DecodeErrorEvent) {}
     async fn handle_kicked_offline(&self, _event: KickedOfflineEvent) {}
     async fn handle_msf_offline(&self, _event: MSFOfflineEvent) {}
     async fn handle_client_disconnect(&self, _event: ClientDisconnect) {}
+    async fn handle_reconnecting(&self, _event: ReconnectingEvent) {}
+    async fn handle_reconnected(&self, _event: ReconnectedEvent) {}
+    async fn handle_reconnect_gave_up(&self, _event: ReconnectGaveUpEvent) {}
+    async fn handle_sig_refreshed(&self, _event: SigRefreshedEvent) {}
 }
 
 #[async_trait]
@@ -218,7 +280,10 @@ where
             QEvent::GroupAudioMessage(m) => self.handle_group_audio(m).await,
             QEvent::FriendMessage(m) => self.handle_friend_message(m).await,
             QEvent::FriendAudioMessage(m) => self.handle_friend_audio(m).await,
+            QEvent::FriendFileMessage(m) => self.handle_friend_file(m).await,
             QEvent::GroupTempMessage(m) => self.handle_group_temp_message(m).await,
+            QEvent::SelfGroupMessage(m) => self.handle_self_group_message(m).await,
+            QEvent::SelfFriendMessage(m) => self.handle_self_friend_message(m).await,
             QEvent::GroupRequest(m) => self.handle_group_request(m).await,
             QEvent::SelfInvited(m) => self.handle_self_invited(m).await,
             QEvent::NewFriendRequest(m) => self.handle_friend_request(m).await,
@@ -234,9 +299,22 @@ where
             QEvent::GroupNameUpdate(m) => self.handle_group_name_update(m).await,
             QEvent::DeleteFriend(m) => self.handle_delete_friend(m).await,
             QEvent::MemberPermissionChange(m) => self.handle_member_permission_change(m).await,
+            QEvent::MemberCardUpdate(m) => self.handle_member_card_update(m).await,
+            QEvent::MemberSpecialTitleUpdate(m) => self.handle_member_special_title_update(m).await,
+            QEvent::MemberHonorChange(m) => self.handle_member_honor_change(m).await,
+            QEvent::GroupEssenceChange(m) => self.handle_group_essence_change(m).await,
+            QEvent::FriendNicknameUpdate(m) => self.handle_friend_nickname_update(m).await,
+            QEvent::FriendRemarkUpdate(m) => self.handle_friend_remark_update(m).await,
+            QEvent::OtherClientStatusChange(m) => self.handle_other_client_status_change(m).await,
+            QEvent::FriendInputStatus(m) => self.handle_friend_input_status(m).await,
+            QEvent::DecodeError(m) => self.handle_decode_error(m).await,
             QEvent::KickedOffline(m) => self.handle_kicked_offline(m).await,
             QEvent::MSFOffline(m) => self.handle_msf_offline(m).await,
             QEvent::ClientDisconnect(m) => self.handle_client_disconnect(m).await,
+            QEvent::Reconnecting(m) => self.handle_reconnecting(m).await,
+            QEvent::Reconnected(m) => self.handle_reconnected(m).await,
+            QEvent::ReconnectGaveUp(m) => self.handle_reconnect_gave_up(m).await,
+            QEvent::SigRefreshed(m) => self.handle_sig_refreshed(m).await,
         }
     }
 }
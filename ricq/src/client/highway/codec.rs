@@ -29,15 +29,48 @@ impl Decoder for HighwayCodec {
         if src.len() < 10 {
             return Ok(None);
         }
-        src.get_u8();
-        let head_length = src.get_u32() as usize;
-        let body_length = src.get_u32() as usize;
-        if head_length + body_length + 1 > src.remaining() {
+        // 帧不完整时不能消费缓冲区
+        let head_length = (&src[1..5]).get_u32() as usize;
+        let body_length = (&src[5..9]).get_u32() as usize;
+        if 10 + head_length + body_length > src.len() {
+            src.reserve(10 + head_length + body_length - src.len());
             return Ok(None);
         }
+        src.advance(9);
         let head = src.copy_to_bytes(head_length);
         let body = src.copy_to_bytes(body_length);
         src.get_u8();
         Ok(Some(Self::Item { head, body }))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+
+    #[test]
+    fn test_decode_partial_frame() {
+        let mut buf = BytesMut::new();
+        HighwayCodec
+            .encode(
+                HighwayFrame {
+                    head: Bytes::from_static(b"head"),
+                    body: Bytes::from_static(b"body"),
+                },
+                &mut buf,
+            )
+            .unwrap();
+        let mut src = BytesMut::new();
+        for b in &buf[..buf.len() - 1] {
+            src.put_u8(*b);
+            assert!(HighwayCodec.decode(&mut src).unwrap().is_none());
+        }
+        src.put_u8(buf[buf.len() - 1]);
+        let frame = HighwayCodec.decode(&mut src).unwrap().unwrap();
+        assert_eq!(frame.head, "head");
+        assert_eq!(frame.body, "body");
+        assert!(src.is_empty());
+    }
+}
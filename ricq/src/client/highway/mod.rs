@@ -1,9 +1,78 @@
 use bytes::Bytes;
+use sha1::{Digest, Sha1};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
 
-mod codec;
+use ricq_core::highway::FileHash;
+use ricq_core::RQResult;
+
+pub(crate) mod codec;
 mod net;
 
 pub struct HighwayFrame {
     pub head: Bytes,
     pub body: Bytes,
 }
+
+const HASH_BUF_SIZE: usize = 256 * 1024;
+const MD5_10M_SIZE: u64 = 10 * 1024 * 1024;
+
+/// 从开头分块读取 reader 计算大小和摘要，完成后回到开头
+pub(crate) async fn hash_reader<R: AsyncRead + AsyncSeek + Unpin>(
+    reader: &mut R,
+) -> RQResult<FileHash> {
+    reader.seek(std::io::SeekFrom::Start(0)).await?;
+    let mut md5 = md5::Context::new();
+    let mut md5_10m = md5::Context::new();
+    let mut sha1 = Sha1::new();
+    let mut size = 0u64;
+    let mut buf = vec![0; HASH_BUF_SIZE];
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        let chunk = &buf[..n];
+        md5.consume(chunk);
+        sha1.update(chunk);
+        if size < MD5_10M_SIZE {
+            let take = (MD5_10M_SIZE - size).min(n as u64) as usize;
+            md5_10m.consume(&chunk[..take]);
+        }
+        size += n as u64;
+    }
+    reader.seek(std::io::SeekFrom::Start(0)).await?;
+    Ok(FileHash {
+        size: size as i64,
+        md5: md5.compute().to_vec(),
+        sha1: sha1.finalize().to_vec(),
+        md5_10m: md5_10m.compute().to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_hash_reader() {
+        let data: Vec<u8> = (0..MD5_10M_SIZE as usize + 1000)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let mut reader = Cursor::new(&data);
+        reader.set_position(5);
+        let hash = hash_reader(&mut reader).await.unwrap();
+        assert_eq!(reader.position(), 0);
+        assert_eq!(hash.size, data.len() as i64);
+        assert_eq!(hash.md5, md5::compute(&data).to_vec());
+        assert_eq!(hash.sha1, Sha1::digest(&data).to_vec());
+        assert_eq!(
+            hash.md5_10m,
+            md5::compute(&data[..MD5_10M_SIZE as usize]).to_vec()
+        );
+
+        let hash = hash_reader(&mut Cursor::new(b"abc")).await.unwrap();
+        assert_eq!(hash.md5_10m, hash.md5);
+    }
+}
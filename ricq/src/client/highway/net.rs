@@ -3,6 +3,7 @@ use std::time::Duration;
 
 use bytes::Bytes;
 use futures_util::{SinkExt, StreamExt};
+use tokio::io::{AsyncRead, AsyncReadExt};
 use tokio::net::TcpStream;
 use tokio_util::codec::Framed;
 
@@ -13,28 +14,46 @@ use ricq_core::{pb, RQError, RQResult};
 
 use crate::client::highway::codec::HighwayCodec;
 use crate::client::highway::HighwayFrame;
-use crate::client::tcp::tcp_connect_timeout;
+use crate::client::proxy::connect_with_proxy;
 use crate::Client;
 
 impl Client {
     pub async fn highway_upload_bdh(
         &self,
         addr: SocketAddr,
-        mut input: BdhInput,
+        input: BdhInput,
         data: &[u8],
+    ) -> RQResult<Bytes> {
+        let sum = md5::compute(data).to_vec();
+        self.highway_upload_bdh_reader(
+            addr,
+            input,
+            &mut std::io::Cursor::new(data),
+            data.len() as u64,
+            sum,
+        )
+        .await
+    }
+
+    /// 从 reader 分块读取并上传 size 字节，sum 为整个文件的 md5
+    pub async fn highway_upload_bdh_reader<R: AsyncRead + Unpin>(
+        &self,
+        addr: SocketAddr,
+        mut input: BdhInput,
+        reader: &mut R,
+        size: u64,
+        sum: Vec<u8>,
     ) -> RQResult<Bytes> {
         if input.encrypt {
             let session_key = self.highway_session.read().await.session_key.clone();
             input.ext = qqtea_encrypt(&input.ext, &session_key)
         }
-        let stream = tcp_connect_timeout(addr, Duration::from_secs(5))
+        let proxy = self.proxy.read().await.clone();
+        let stream = connect_with_proxy(proxy.as_ref(), addr, Duration::from_secs(5))
             .await
             .map_err(RQError::IO)?;
         let mut stream = Framed::new(stream, HighwayCodec);
         // send heartbeat
-        let sum = md5::compute(data).to_vec();
-        let length = data.len();
-
         if input.send_echo {
             stream
                 .send(HighwayFrame {
@@ -46,13 +65,14 @@ impl Client {
         }
         let mut ticket = input.ticket;
         let mut rsp_ext = Bytes::new();
-        let data = Bytes::copy_from_slice(data);
-        let len = data.len();
-        let chunk_size = input.chunk_size;
+        let chunk_size = input.chunk_size.max(1);
+        let mut offset = 0u64;
 
-        for i in (0..len).step_by(chunk_size) {
-            let min = std::cmp::min(i + chunk_size, len);
-            let chunk = data.slice(i..min);
+        while offset < size {
+            let len = (size - offset).min(chunk_size as u64) as usize;
+            let mut chunk = vec![0; len];
+            reader.read_exact(&mut chunk).await?;
+            let chunk = Bytes::from(chunk);
             let head = pb::ReqDataHighwayHead {
                 msg_basehead: Some(self.highway_session.read().await.build_basehead(
                     "PicUp.DataUp".into(),
@@ -61,8 +81,8 @@ impl Client {
                     2052,
                 )),
                 msg_seghead: Some(self.highway_session.read().await.build_seghead(
-                    length as i64,
-                    i as i64,
+                    size as i64,
+                    offset as i64,
                     &chunk,
                     ticket.clone(),
                     sum.clone(),
@@ -76,6 +96,7 @@ impl Client {
                     body: chunk,
                 })
                 .await?;
+            offset += len as u64;
             let resp = read_response(&mut stream).await?;
             let rsp_head = self
                 .highway_session
@@ -103,9 +124,6 @@ impl Client {
 }
 
 async fn read_response(stream: &mut Framed<TcpStream, HighwayCodec>) -> RQResult<HighwayFrame> {
-    loop {
-        if let Some(resp) = stream.next().await {
-            return resp;
-        }
-    }
+    // 连接关闭时返回错误，不要空转
+    stream.next().await.ok_or(RQError::Network)?
 }
@@ -0,0 +1,167 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+
+use crate::client::proxy::{connect_with_proxy, Proxy};
+use crate::{RQError, RQResult};
+
+/// 响应（含 header）的大小上限
+const MAX_RESPONSE_SIZE: usize = 64 * 1024 * 1024;
+
+/// 简单的 HTTP/1.1 GET，用于下载转发消息等不需要 TLS 的资源
+pub(crate) async fn http_get(
+    proxy: Option<&Proxy>,
+    addr: SocketAddr,
+    path: &str,
+    timeout: Duration,
+) -> RQResult<Vec<u8>> {
+    http_request(proxy, addr, "GET", path, None, timeout).await
+}
+
+/// 简单的 HTTP/1.1 POST，body 为 (content_type, data)
+pub(crate) async fn http_post(
+    proxy: Option<&Proxy>,
+    addr: SocketAddr,
+    path: &str,
+    content_type: &str,
+    body: &[u8],
+    timeout: Duration,
+) -> RQResult<Vec<u8>> {
+    http_request(
+        proxy,
+        addr,
+        "POST",
+        path,
+        Some((content_type, body)),
+        timeout,
+    )
+    .await
+}
+
+async fn http_request(
+    proxy: Option<&Proxy>,
+    addr: SocketAddr,
+    method: &str,
+    path: &str,
+    body: Option<(&str, &[u8])>,
+    timeout: Duration,
+) -> RQResult<Vec<u8>> {
+    tokio::time::timeout(timeout, async {
+        let mut stream = connect_with_proxy(proxy, addr, timeout).await?;
+        let mut req = format!(
+            "{method} {path} HTTP/1.1\r\nHost: {addr}\r\nUser-Agent: QQ/8.2.0.1296 CFNetwork/1126\r\nNet-Type: Wifi\r\nConnection: close\r\n"
+        )
+        .into_bytes();
+        if let Some((content_type, data)) = body {
+            req.extend_from_slice(
+                format!(
+                    "Content-Type: {content_type}\r\nContent-Length: {}\r\n\r\n",
+                    data.len()
+                )
+                .as_bytes(),
+            );
+            req.extend_from_slice(data);
+        } else {
+            req.extend_from_slice(b"\r\n");
+        }
+        stream.write_all(&req).await?;
+        let resp = read_limited(&mut stream, MAX_RESPONSE_SIZE).await?;
+        parse_response(&resp)
+    })
+    .await
+    .map_err(|_| RQError::Timeout)?
+}
+
+/// 读取到 EOF，超过 limit 时返回错误
+async fn read_limited<R: AsyncRead + Unpin>(reader: R, limit: usize) -> RQResult<Vec<u8>> {
+    let mut buf = Vec::new();
+    reader.take(limit as u64 + 1).read_to_end(&mut buf).await?;
+    if buf.len() > limit {
+        return Err(RQError::Decode(format!(
+            "http: response larger than {limit} bytes"
+        )));
+    }
+    Ok(buf)
+}
+
+fn parse_response(resp: &[u8]) -> RQResult<Vec<u8>> {
+    let header_end = resp
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(|| RQError::Decode("http: incomplete response header".into()))?;
+    let header = String::from_utf8_lossy(&resp[..header_end]);
+    let body = &resp[header_end + 4..];
+    let mut lines = header.lines();
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .unwrap_or_default();
+    if status != "200" {
+        return Err(RQError::Other(format!("http: unexpected status {status}")));
+    }
+    let mut chunked = false;
+    let mut content_length = None;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("transfer-encoding") {
+            chunked = value.eq_ignore_ascii_case("chunked");
+        } else if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.parse::<usize>().ok();
+        }
+    }
+    if chunked {
+        return decode_chunked(body);
+    }
+    match content_length {
+        Some(len) if len <= body.len() => Ok(body[..len].to_vec()),
+        Some(_) => Err(RQError::Decode("http: body too short".into())),
+        None => Ok(body.to_vec()),
+    }
+}
+
+fn decode_chunked(mut body: &[u8]) -> RQResult<Vec<u8>> {
+    let mut out = Vec::new();
+    loop {
+        let line_end = body
+            .windows(2)
+            .position(|w| w == b"\r\n")
+            .ok_or_else(|| RQError::Decode("http: invalid chunk".into()))?;
+        let size = String::from_utf8_lossy(&body[..line_end]);
+        let size = size.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16)
+            .map_err(|_| RQError::Decode("http: invalid chunk size".into()))?;
+        body = &body[line_end + 2..];
+        if size == 0 {
+            return Ok(out);
+        }
+        if body.len() < size + 2 {
+            return Err(RQError::Decode("http: chunk too short".into()));
+        }
+        out.extend_from_slice(&body[..size]);
+        body = &body[size + 2..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response() {
+        let resp = b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nabcdef";
+        assert_eq!(parse_response(resp).unwrap(), b"abc");
+        let resp = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n2;x=y\r\nde\r\n0\r\n\r\n";
+        assert_eq!(parse_response(resp).unwrap(), b"abcde");
+        assert!(parse_response(b"HTTP/1.1 404 Not Found\r\n\r\n").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_limited() {
+        assert_eq!(read_limited(&b"abcd"[..], 4).await.unwrap(), b"abcd");
+        assert!(read_limited(&b"abcde"[..], 4).await.is_err());
+    }
+}
@@ -1,13 +1,18 @@
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU8, Ordering};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, AtomicU8, Ordering};
+use std::sync::Arc;
 use std::time::UNIX_EPOCH;
 
+use bytes::Bytes;
 use cached::Cached;
 use tokio::sync::{broadcast, RwLock};
 use tokio::sync::{oneshot, Mutex};
 use tokio::time::{sleep, Duration};
 
-pub use net::{Connector, DefaultConnector};
+pub use api::GroupMessagePager;
+pub use net::{Connector, DefaultConnector, HttpConnectConnector, Socks5Connector};
+pub use proxy::{Proxy, ProxyAuth};
 use ricq_core::command::online_push::GroupMessagePart;
 use ricq_core::command::profile_service::GroupSystemMessages;
 use ricq_core::common::RQAddr;
@@ -16,20 +21,31 @@ use ricq_core::protocol::{device::Device, packet::Packet};
 use ricq_core::structs::{AccountInfo, AddressInfo, OtherClientInfo};
 use ricq_core::Engine;
 pub use ricq_core::Token;
+pub use scheduler::{
+    RateLimit, SendPriority, SendQueueMetrics, SendScheduler, SendSchedulerConfig, SendTarget,
+};
+pub use sig_refresh::SigRefreshConfig;
+pub use sign::{HttpSignProvider, NoopSignProvider, SignProvider, DEFAULT_SIGN_COMMANDS};
 
+use crate::ext::session::{Session, SessionStore};
 use crate::{RQError, RQResult};
 
 mod api;
 pub mod event;
 pub mod handler;
-mod highway;
+pub(crate) mod highway;
+mod http;
 pub(crate) mod net;
 mod processor;
+mod proxy;
+mod scheduler;
+mod sig_refresh;
+mod sign;
 mod tcp;
 
 pub struct Client {
     /// QEvent Handler 调用 handle 方法外发 QEvent
-    handler: Box<dyn handler::Handler + Sync + Send + 'static>,
+    pub(crate) handler: Box<dyn handler::Handler + Sync + Send + 'static>,
     pub engine: RwLock<Engine>,
 
     // 状态相关
@@ -41,14 +57,30 @@ pub struct Client {
     pub online: AtomicBool,
     /// 心跳包是否已启用
     pub heartbeat_enabled: AtomicBool,
+    /// 自动刷新 sig 是否已启用
+    pub sig_refresh_enabled: AtomicBool,
 
     // 包相关
     /// 外发包 Sender
     out_pkt_sender: net::OutPktSender,
     /// send_and_wait WaitMap
-    packet_promises: RwLock<HashMap<i32, oneshot::Sender<Packet>>>,
+    packet_promises: std::sync::Mutex<HashMap<i32, oneshot::Sender<Packet>>>,
+    /// send_and_wait 默认超时时间（毫秒）
+    request_timeout: AtomicU64,
+    /// 消息发送队列，为 None 时不限速
+    send_scheduler: RwLock<Option<Arc<SendScheduler>>>,
     /// 当前客户端发送消息后使用 cache 避免上报自身消息事件
     receipt_waiters: Mutex<cached::TimedCache<i32, oneshot::Sender<i32>>>,
+    /// 数据包签名
+    sign_provider: RwLock<Arc<dyn SignProvider>>,
+    /// 推送解析失败时是否外发 QEvent::DecodeError
+    report_decode_error: AtomicBool,
+    /// 其他设备发送的消息是否外发为 SelfGroupMessage/SelfFriendMessage
+    self_message_events: AtomicBool,
+    /// 自动刷新 sig 配置，为 None 时不自动刷新
+    sig_refresh: RwLock<Option<SigRefreshConfig>>,
+    /// 登录成功后自动保存会话
+    session_store: RwLock<Option<Arc<dyn SessionStore>>>,
 
     // account info
     pub account_info: RwLock<AccountInfo>,
@@ -70,9 +102,13 @@ pub struct Client {
     push_req_cache: RwLock<cached::TimedCache<(i16, i64), ()>>,
     push_trans_cache: RwLock<cached::TimedCache<(i32, i64), ()>>,
     group_sys_message_cache: RwLock<GroupSystemMessages>,
+    /// 群消息中见过的群名片 <(group_code, uin), card>，用于发现名片修改，满 4096 条时清空
+    member_card_cache: RwLock<cached::UnboundCache<(i64, i64), String>>,
 
     pub highway_session: RwLock<ricq_core::highway::Session>,
     pub highway_addrs: RwLock<Vec<RQAddr>>,
+    /// 代理，使用代理 Connector 连接时设置，highway 上传也会使用
+    pub proxy: RwLock<Option<Proxy>>,
 
     packet_handler: RwLock<HashMap<String, broadcast::Sender<Packet>>>,
 }
@@ -93,12 +129,20 @@ impl super::Client {
             engine: RwLock::new(Engine::new(device, version)),
             status: AtomicU8::new(NetworkStatus::Unknown as u8),
             heartbeat_enabled: AtomicBool::new(false),
+            sig_refresh_enabled: AtomicBool::new(false),
             online: AtomicBool::new(false),
             out_pkt_sender,
             disconnect_signal,
             // out_going_packet_session_id: RwLock::new(Bytes::from_static(&[0x02, 0xb0, 0x5b, 0x8b])),
             packet_promises: Default::default(),
+            request_timeout: AtomicU64::new(DEFAULT_REQUEST_TIMEOUT.as_millis() as u64),
+            send_scheduler: RwLock::new(None),
             receipt_waiters: Mutex::new(cached::TimedCache::with_lifespan(60)),
+            sign_provider: RwLock::new(Arc::new(NoopSignProvider)),
+            report_decode_error: AtomicBool::new(false),
+            self_message_events: AtomicBool::new(false),
+            sig_refresh: RwLock::new(Some(SigRefreshConfig::default())),
+            session_store: RwLock::new(None),
             account_info: Default::default(),
             address: Default::default(),
             online_clients: Default::default(),
@@ -109,8 +153,10 @@ impl super::Client {
             push_req_cache: RwLock::new(cached::TimedCache::with_lifespan(30)),
             push_trans_cache: RwLock::new(cached::TimedCache::with_lifespan(15)),
             group_sys_message_cache: RwLock::new(Default::default()),
+            member_card_cache: RwLock::new(cached::UnboundCache::with_capacity(4096)),
             highway_session: RwLock::new(Default::default()),
             highway_addrs: RwLock::new(Default::default()),
+            proxy: RwLock::new(None),
             packet_handler: Default::default(),
         }
     }
@@ -122,7 +168,21 @@ impl super::Client {
     where
         H: crate::client::handler::Handler + 'static + Sync + Send,
     {
-        Self::new(config.device, config.version, handler)
+        let mut client = Self::new(config.device, config.version, handler);
+        client.set_request_timeout(config.request_timeout);
+        client.set_report_decode_error(config.report_decode_error);
+        client.set_self_message_events(config.self_message_events);
+        if let Some(provider) = config.sign_provider {
+            client.sign_provider = RwLock::new(provider);
+        }
+        client.session_store = RwLock::new(config.session_store);
+        client.sig_refresh = RwLock::new(config.sig_refresh);
+        client.send_scheduler = RwLock::new(
+            config
+                .send_scheduler
+                .map(|c| Arc::new(SendScheduler::new(c))),
+        );
+        client
     }
 
     /// 获取当前 Client uin
@@ -130,41 +190,166 @@ impl super::Client {
         self.engine.read().await.uin.load(Ordering::Relaxed)
     }
 
+    /// 编码数据包，需要签名时先调用 SignProvider
+    async fn encode_packet(&self, pkt: Packet) -> RQResult<Bytes> {
+        let provider = self.sign_provider.read().await.clone();
+        let sign = if provider.should_sign(&pkt.command_name) {
+            provider
+                .sso_sign(
+                    self.uin().await,
+                    pkt.seq_id,
+                    &pkt.command_name,
+                    pkt.body.clone(),
+                )
+                .await?
+        } else {
+            None
+        };
+        Ok(self
+            .engine
+            .read()
+            .await
+            .transport
+            .encode_packet_with_sign(pkt, sign.as_ref()))
+    }
+
     /// 向服务器发包
     pub async fn send(&self, pkt: Packet) -> RQResult<usize> {
         tracing::trace!("sending pkt {}-{},", pkt.command_name, pkt.seq_id);
-        let data = self.engine.read().await.transport.encode_packet(pkt);
+        let data = self.encode_packet(pkt).await?;
         self.out_pkt_sender
             .send(data)
             .map_err(|_| RQError::Other("failed to send out_pkt".into()))
     }
 
-    /// 向服务器发包并等待接收返回的包，15 秒后超时返回 `Err(RQError::Timeout)`
+    /// 向服务器发包并等待接收返回的包，超时返回 `Err(RQError::Timeout)`
+    ///
+    /// 超时时间优先使用 `with_request_timeout` 设置的值，其次是 `request_timeout`
     pub async fn send_and_wait(&self, pkt: Packet) -> RQResult<Packet> {
+        let timeout = REQUEST_TIMEOUT
+            .try_with(|t| *t)
+            .unwrap_or_else(|_| self.request_timeout());
+        self.send_and_wait_timeout(pkt, timeout).await
+    }
+
+    /// 向服务器发包并等待接收返回的包，timeout 后返回 `Err(RQError::Timeout)`
+    ///
+    /// 连接断开时立即返回 `Err(RQError::Network)`，取消（drop）该 Future 会移除等待中的请求
+    pub async fn send_and_wait_timeout(&self, pkt: Packet, timeout: Duration) -> RQResult<Packet> {
         tracing::trace!("send_and_waitting pkt {}-{},", pkt.command_name, pkt.seq_id);
         let seq = pkt.seq_id;
         let expect = pkt.command_name.clone();
-        let data = self.engine.read().await.transport.encode_packet(pkt);
+        let data = self.encode_packet(pkt).await?;
         let (sender, receiver) = oneshot::channel();
-        {
-            let mut packet_promises = self.packet_promises.write().await;
-            packet_promises.insert(seq, sender);
-        }
+        self.packet_promises.lock().unwrap().insert(seq, sender);
+        let _guard = PromiseGuard { client: self, seq };
         if self.out_pkt_sender.send(data).is_err() {
-            let mut packet_promises = self.packet_promises.write().await;
-            packet_promises.remove(&seq);
             return Err(RQError::Network);
         }
-        match tokio::time::timeout(std::time::Duration::from_secs(15), receiver).await {
-            Ok(p) => p.unwrap().check_command_name(&expect),
+        match tokio::time::timeout(timeout, receiver).await {
+            Ok(Ok(p)) => p.check_command_name(&expect),
+            // net_loop 退出时会清空 packet_promises
+            Ok(Err(_)) => Err(RQError::Network),
             Err(_) => {
                 tracing::trace!("waiting pkt {}-{} timeout", expect, seq);
-                self.packet_promises.write().await.remove(&seq);
                 Err(RQError::Timeout)
             }
         }
     }
 
+    /// 在 fut 内调用的 send_and_wait 使用指定的超时时间，可用于单次调用的 api
+    ///
+    /// ```ignore
+    /// client.with_request_timeout(Duration::from_secs(60), client.get_group_member_list(code, owner)).await
+    /// ```
+    pub async fn with_request_timeout<F: Future>(&self, timeout: Duration, fut: F) -> F::Output {
+        REQUEST_TIMEOUT.scope(timeout, fut).await
+    }
+
+    /// send_and_wait 默认超时时间
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_millis(self.request_timeout.load(Ordering::Relaxed))
+    }
+
+    /// 设置 send_and_wait 默认超时时间
+    pub fn set_request_timeout(&self, timeout: Duration) {
+        self.request_timeout
+            .store(timeout.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// 设置推送解析失败时是否外发 `QEvent::DecodeError`
+    pub fn set_report_decode_error(&self, report: bool) {
+        self.report_decode_error.store(report, Ordering::Relaxed);
+    }
+
+    /// 设置本账号在其他设备发送的消息是否外发为 `QEvent::SelfGroupMessage`/`QEvent::SelfFriendMessage`
+    ///
+    /// 默认关闭，这些消息和其他人的消息一样外发为 `QEvent::GroupMessage`/`QEvent::FriendMessage`。
+    /// 语音不受影响，始终外发为 `QEvent::FriendAudioMessage`/`QEvent::GroupAudioMessage`
+    pub fn set_self_message_events(&self, enabled: bool) {
+        self.self_message_events.store(enabled, Ordering::Relaxed);
+    }
+
+    /// 设置数据包签名
+    pub async fn set_sign_provider(&self, provider: Arc<dyn SignProvider>) {
+        *self.sign_provider.write().await = provider;
+    }
+
+    /// 设置自动刷新 sig，None 表示不自动刷新
+    pub async fn set_sig_refresh(&self, config: Option<SigRefreshConfig>) {
+        *self.sig_refresh.write().await = config;
+    }
+
+    /// 设置会话存储，登录成功和刷新 token 后自动保存
+    pub async fn set_session_store(&self, store: Option<Arc<dyn SessionStore>>) {
+        *self.session_store.write().await = store;
+    }
+
+    /// 保存当前会话，未设置会话存储时不做任何事
+    pub async fn save_session(&self) -> RQResult<()> {
+        let store = self.session_store.read().await.clone();
+        match store {
+            Some(store) => store.save(&Session::from_client(self).await).await,
+            None => Ok(()),
+        }
+    }
+
+    /// 设置消息发送队列，None 表示不限速
+    pub async fn set_send_scheduler(&self, config: Option<SendSchedulerConfig>) {
+        *self.send_scheduler.write().await = config.map(|c| Arc::new(SendScheduler::new(c)));
+    }
+
+    /// 消息发送队列状态，未启用时返回 None
+    pub async fn send_queue_metrics(&self) -> Option<SendQueueMetrics> {
+        self.send_scheduler
+            .read()
+            .await
+            .as_ref()
+            .map(|s| s.metrics())
+    }
+
+    /// 在 fut 内发送的消息使用指定的优先级排队
+    pub async fn with_send_priority<F: Future>(&self, priority: SendPriority, fut: F) -> F::Output {
+        SEND_PRIORITY.scope(priority, fut).await
+    }
+
+    /// 发送消息前排队，未启用发送队列时直接返回
+    pub(crate) async fn acquire_send_permit(&self, target: Option<SendTarget>) -> RQResult<()> {
+        let scheduler = self.send_scheduler.read().await.clone();
+        match scheduler {
+            Some(scheduler) => {
+                let priority = SEND_PRIORITY.try_with(|p| *p).unwrap_or_default();
+                scheduler.acquire(target, priority).await
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// 连接断开时调用，等待中的 send_and_wait 会返回 `Err(RQError::Network)`
+    fn clear_packet_promises(&self) {
+        self.packet_promises.lock().unwrap().clear();
+    }
+
     /// 向服务器发送心跳包，并自动注册客户端
     ///
     /// 该方法会阻塞当前协程，通常 spawn 使用
@@ -218,6 +403,28 @@ impl super::Client {
     }
 }
 
+/// send_and_wait 默认超时时间
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+tokio::task_local! {
+    static REQUEST_TIMEOUT: Duration;
+    static SEND_PRIORITY: SendPriority;
+}
+
+/// 请求结束（完成、超时或被取消）时移除 packet_promises 中的记录
+struct PromiseGuard<'a> {
+    client: &'a Client,
+    seq: i32,
+}
+
+impl Drop for PromiseGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut promises) = self.client.packet_promises.lock() {
+            promises.remove(&self.seq);
+        }
+    }
+}
+
 impl Drop for Client {
     fn drop(&mut self) {
         self.stop(NetworkStatus::Drop);
@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::net::SocketAddr;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
@@ -7,12 +8,14 @@ use crate::client::event::{ClientDisconnect, DisconnectReason};
 use async_trait::async_trait;
 use bytes::Bytes;
 use futures_util::{SinkExt, StreamExt};
+use ricq_core::structs::AddressSnapshot;
 use tokio::io::{self, AsyncRead, AsyncWrite};
 use tokio::net::TcpStream;
 use tokio::sync::broadcast;
 use tokio_util::codec::LengthDelimitedCodec;
 
-use crate::client::tcp::tcp_connect_fastest;
+use crate::client::proxy::{Proxy, ProxyAuth};
+use crate::client::tcp::{sort_addrs, tcp_connect_fastest};
 use crate::client::NetworkStatus;
 use crate::handler::QEvent;
 
@@ -34,6 +37,94 @@ impl Connector<TcpStream> for DefaultConnector {
     }
 }
 
+/// 通过 SOCKS5 代理连接，highway 上传也会使用该代理
+pub struct Socks5Connector {
+    pub proxy: SocketAddr,
+    pub auth: Option<ProxyAuth>,
+}
+
+impl Socks5Connector {
+    pub fn new(proxy: SocketAddr) -> Self {
+        Self { proxy, auth: None }
+    }
+
+    pub fn with_auth(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.auth = Some(ProxyAuth::new(username, password));
+        self
+    }
+}
+
+#[async_trait]
+impl Connector<TcpStream> for Socks5Connector {
+    async fn connect(&self, client: &Client) -> io::Result<TcpStream> {
+        connect_via_proxy(
+            client,
+            Proxy::Socks5 {
+                addr: self.proxy,
+                auth: self.auth.clone(),
+            },
+        )
+        .await
+    }
+}
+
+/// 通过 HTTP CONNECT 代理连接，highway 上传也会使用该代理
+pub struct HttpConnectConnector {
+    pub proxy: SocketAddr,
+    pub auth: Option<ProxyAuth>,
+}
+
+impl HttpConnectConnector {
+    pub fn new(proxy: SocketAddr) -> Self {
+        Self { proxy, auth: None }
+    }
+
+    pub fn with_auth(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.auth = Some(ProxyAuth::new(username, password));
+        self
+    }
+}
+
+#[async_trait]
+impl Connector<TcpStream> for HttpConnectConnector {
+    async fn connect(&self, client: &Client) -> io::Result<TcpStream> {
+        connect_via_proxy(
+            client,
+            Proxy::HttpConnect {
+                addr: self.proxy,
+                auth: self.auth.clone(),
+            },
+        )
+        .await
+    }
+}
+
+// 代理无法测速，按顺序尝试服务器地址
+async fn connect_via_proxy(client: &Client, proxy: Proxy) -> io::Result<TcpStream> {
+    *client.proxy.write().await = Some(proxy.clone());
+    let mut last_err = io::Error::new(io::ErrorKind::NotConnected, "NotConnected");
+    for addr in client.get_address_list().await {
+        match proxy.connect(addr, Duration::from_secs(5)).await {
+            Ok(stream) => return Ok(stream),
+            Err(err) => {
+                tracing::warn!("failed to connect {} via proxy: {}", addr, err);
+                last_err = err;
+            }
+        }
+    }
+    Err(last_err)
+}
+
+// 去重并保持顺序
+fn dedup_addrs(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let mut seen = HashSet::new();
+    addrs.into_iter().filter(|a| seen.insert(*a)).collect()
+}
+
+fn format_addrs(addrs: &[SocketAddr]) -> Vec<String> {
+    addrs.iter().map(ToString::to_string).collect()
+}
+
 impl crate::Client {
     /// 获取服务器地址
     pub async fn get_address_list(&self) -> Vec<SocketAddr> {
@@ -45,12 +136,57 @@ impl crate::Client {
             ([114, 221, 144, 215], 80),
             ([42, 81, 172, 22], 80),
         ];
-        let mut addrs: Vec<_> = BUILD_IN.into_iter().map(SocketAddr::from).collect();
+        // 优先使用服务器下发的地址
+        let mut addrs = self.sso_addrs().await;
+        addrs.extend(BUILD_IN.into_iter().map(SocketAddr::from));
         if let Ok(res) = tokio::net::lookup_host(("msfwifi.3g.qq.com", 8080)).await {
             addrs.extend(res);
         }
-        // TODO: src/client/processor/config_push_svc.rs
-        addrs
+        dedup_addrs(addrs)
+    }
+
+    /// 更新 SSO 服务器地址，下次连接优先使用
+    ///
+    /// 测速在后台进行，不会阻塞收包，完成后按延迟重新排序
+    pub(crate) async fn update_sso_addrs(self: &Arc<Self>, addrs: Vec<SocketAddr>) {
+        let addrs = dedup_addrs(addrs);
+        let unsorted = format_addrs(&addrs);
+        self.address.write().await.srv_sso_addrs = unsorted.clone();
+        let cli = self.clone();
+        tokio::spawn(async move {
+            let sorted = sort_addrs(addrs, Duration::from_secs(5)).await;
+            // 全部测速失败（例如只能通过代理连接）时保留原顺序
+            if sorted.is_empty() {
+                return;
+            }
+            let mut address = cli.address.write().await;
+            // 测速期间地址已被更新时不覆盖
+            if address.srv_sso_addrs == unsorted {
+                address.srv_sso_addrs = format_addrs(&sorted);
+            }
+        });
+    }
+
+    async fn sso_addrs(&self) -> Vec<SocketAddr> {
+        let address = self.address.read().await;
+        address
+            .srv_sso_addrs
+            .iter()
+            .filter_map(|addr| addr.parse().ok())
+            .collect()
+    }
+
+    /// 导出服务器地址快照
+    pub async fn gen_address_snapshot(&self) -> AddressSnapshot {
+        AddressSnapshot {
+            srv_sso_addrs: self.sso_addrs().await,
+        }
+    }
+
+    /// 从快照恢复服务器地址，保持快照中的顺序
+    pub async fn load_address_snapshot(&self, snapshot: AddressSnapshot) {
+        self.address.write().await.srv_sso_addrs =
+            format_addrs(&dedup_addrs(snapshot.srv_sso_addrs));
     }
 
     /// 获取网络状态
@@ -66,6 +202,7 @@ impl crate::Client {
             .store(NetworkStatus::Running as u8, Ordering::Relaxed);
         self.net_loop(stream).await; // 阻塞到断开
         self.disconnect();
+        self.clear_packet_promises();
         self.online.store(false, Ordering::Relaxed);
 
         match self.status.compare_exchange(
@@ -141,8 +278,10 @@ impl crate::Client {
                     }
                 }
                 output = rx.recv() => {
-                    if let Ok(output) = output && write_half.send(output).await.is_err() {
-                        break;
+                    if let Ok(output) = output {
+                        if write_half.send(output).await.is_err() {
+                            break;
+                        }
                     }
                 }
                 _ = disconnect_signal.recv() => {
@@ -152,3 +291,45 @@ impl crate::Client {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::version::Protocol;
+    use crate::Device;
+
+    #[tokio::test]
+    async fn test_address_snapshot() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let client = Client::new(Device::random(), Protocol::IPad.into(), tx);
+        client
+            .load_address_snapshot(AddressSnapshot {
+                srv_sso_addrs: vec![addr, addr],
+            })
+            .await;
+        let snapshot = client.gen_address_snapshot().await;
+        assert_eq!(snapshot.srv_sso_addrs, vec![addr]);
+    }
+
+    #[tokio::test]
+    async fn test_update_sso_addrs() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        // 不可路由的地址，测速会等到超时
+        let unreachable = SocketAddr::from(([10, 255, 255, 1], 80));
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let client = Arc::new(Client::new(Device::random(), Protocol::IPad.into(), tx));
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            client.update_sso_addrs(vec![unreachable, addr, addr]),
+        )
+        .await
+        .unwrap();
+        let snapshot = client.gen_address_snapshot().await;
+        assert_eq!(snapshot.srv_sso_addrs, vec![unreachable, addr]);
+    }
+}
@@ -1,11 +1,17 @@
 use cached::Cached;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
+use prost::Message;
 use ricq_core::msg::MessageChain;
-use ricq_core::structs::{FriendAudio, FriendAudioMessage, FriendMessage};
-use ricq_core::{pb, RQResult};
+use ricq_core::structs::{
+    FriendAudio, FriendAudioMessage, FriendFileMessage, FriendMessage, OfflineFile,
+};
+use ricq_core::{pb, RQError, RQResult};
 
-use crate::client::event::{FriendAudioMessageEvent, FriendMessageEvent};
+use crate::client::event::{
+    FriendAudioMessageEvent, FriendFileMessageEvent, FriendMessageEvent, SelfFriendMessageEvent,
+};
 use crate::handler::QEvent;
 use crate::Client;
 
@@ -18,27 +24,37 @@ impl Client {
             msg.body.as_mut()?.rich_text.as_mut()?.ptt.take()
         }
         if let Some(ptt) = take_ptt(&mut msg) {
-            // TODO self friend audio
+            let message = parse_friend_audio_message(msg, ptt)?;
+            if message.from_uin == self.uin().await
+                && self.complete_receipt(&message.rands, &message.seqs).await
+            {
+                return Ok(());
+            }
+            // 其他设备发送的语音没有单独的事件，from_uin 为本账号
             self.handler
                 .handle(QEvent::FriendAudioMessage(FriendAudioMessageEvent {
                     client: self.clone(),
-                    inner: parse_friend_audio_message(msg, ptt)?,
+                    inner: message,
                 }))
                 .await;
             return Ok(());
         }
 
-        let message = parse_friend_message(msg)?;
-        if message.from_uin == self.uin().await {
-            if let Some(tx) = self
-                .receipt_waiters
-                .lock()
-                .await
-                .cache_remove(&message.rands.first().cloned().unwrap_or_default())
-            {
-                let _ = tx.send(message.seqs.first().cloned().unwrap_or_default());
-                return Ok(());
-            }
+        let mut message = parse_friend_message(msg)?;
+        let from_self = message.from_uin == self.uin().await;
+        if from_self && self.complete_receipt(&message.rands, &message.seqs).await {
+            return Ok(());
+        }
+        self.resolve_long_message(&mut message.elements).await;
+        // 其他设备发送的消息
+        if from_self && self.self_message_events.load(Ordering::Relaxed) {
+            self.handler
+                .handle(QEvent::SelfFriendMessage(SelfFriendMessageEvent {
+                    client: self.clone(),
+                    inner: message,
+                }))
+                .await;
+            return Ok(());
         }
         self.handler
             .handle(QEvent::FriendMessage(FriendMessageEvent {
@@ -48,24 +64,59 @@ impl Client {
             .await;
         Ok(())
     }
+
+    // 本客户端发送的消息，返回是否有等待回执的发送
+    async fn complete_receipt(&self, rands: &[i32], seqs: &[i32]) -> bool {
+        let tx = self
+            .receipt_waiters
+            .lock()
+            .await
+            .cache_remove(&rands.first().cloned().unwrap_or_default());
+        if let Some(tx) = tx {
+            let _ = tx.send(seqs.first().cloned().unwrap_or_default());
+            return true;
+        }
+        false
+    }
+
+    pub(crate) async fn process_friend_file_message(
+        self: &Arc<Self>,
+        msg: pb::msg::Message,
+    ) -> RQResult<()> {
+        let message = parse_friend_file_message(msg)?;
+        // 本账号发送的文件只在开启 self_message_events 时外发
+        if message.from_uin == self.uin().await && !self.self_message_events.load(Ordering::Relaxed)
+        {
+            return Ok(());
+        }
+        self.handler
+            .handle(QEvent::FriendFileMessage(FriendFileMessageEvent {
+                client: self.clone(),
+                inner: message,
+            }))
+            .await;
+        Ok(())
+    }
 }
 
 pub fn parse_friend_message(msg: pb::msg::Message) -> RQResult<FriendMessage> {
-    let head = msg.head.unwrap();
+    let head = msg.head.ok_or(RQError::EmptyField("head"))?;
+    let rich_text = msg
+        .body
+        .and_then(|body| body.rich_text)
+        .ok_or(RQError::EmptyField("rich_text"))?;
     Ok(FriendMessage {
         seqs: vec![head.msg_seq()],
-        target: head.to_uin.unwrap(),
-        time: head.msg_time.unwrap(),
+        target: head.to_uin.ok_or(RQError::EmptyField("to_uin"))?,
+        time: head.msg_time.unwrap_or_default(),
         from_uin: head.from_uin.unwrap_or_default(),
         from_nick: head.from_nick.unwrap_or_default(),
-        rands: vec![
-            if let Some(attr) = &msg.body.as_ref().unwrap().rich_text.as_ref().unwrap().attr {
-                attr.random()
-            } else {
-                0
-            },
-        ],
-        elements: MessageChain::from(msg.body.unwrap().rich_text.unwrap().elems), // todo ptt_store
+        rands: vec![rich_text
+            .attr
+            .as_ref()
+            .map(|attr| attr.random())
+            .unwrap_or_default()],
+        elements: MessageChain::from(rich_text.elems), // todo ptt_store
     })
 }
 
@@ -73,20 +124,45 @@ pub fn parse_friend_audio_message(
     msg: pb::msg::Message,
     ptt: pb::msg::Ptt,
 ) -> RQResult<FriendAudioMessage> {
-    let head = msg.head.unwrap();
+    let head = msg.head.ok_or(RQError::EmptyField("head"))?;
     Ok(FriendAudioMessage {
         seqs: vec![head.msg_seq()],
-        target: head.to_uin.unwrap(),
-        time: head.msg_time.unwrap(),
+        target: head.to_uin.ok_or(RQError::EmptyField("to_uin"))?,
+        time: head.msg_time.unwrap_or_default(),
         from_uin: head.from_uin.unwrap_or_default(),
         from_nick: head.from_nick.unwrap_or_default(),
-        rands: vec![
-            if let Some(attr) = &msg.body.as_ref().unwrap().rich_text.as_ref().unwrap().attr {
-                attr.random()
-            } else {
-                0
-            },
-        ],
+        rands: vec![msg
+            .body
+            .as_ref()
+            .and_then(|body| body.rich_text.as_ref())
+            .and_then(|rich_text| rich_text.attr.as_ref())
+            .map(|attr| attr.random())
+            .unwrap_or_default()],
         audio: FriendAudio(ptt),
     })
 }
+
+pub fn parse_friend_file_message(msg: pb::msg::Message) -> RQResult<FriendFileMessage> {
+    let head = msg.head.ok_or(RQError::EmptyField("head"))?;
+    let content = msg
+        .body
+        .and_then(|body| body.msg_content)
+        .ok_or(RQError::EmptyField("msg_content"))?;
+    let file = pb::msg::SubMsgType0x4Body::decode(&*content)?
+        .not_online_file
+        .ok_or(RQError::EmptyField("not_online_file"))?;
+    Ok(FriendFileMessage {
+        seqs: vec![head.msg_seq()],
+        rands: vec![head.msg_uid() as i32],
+        target: head.to_uin.ok_or(RQError::EmptyField("to_uin"))?,
+        time: head.msg_time.unwrap_or_default(),
+        from_uin: head.from_uin.unwrap_or_default(),
+        from_nick: head.from_nick.unwrap_or_default(),
+        file: OfflineFile {
+            name: String::from_utf8_lossy(file.file_name()).into_owned(),
+            size: file.file_size(),
+            uuid: file.file_uuid.unwrap_or_default(),
+            md5: file.file_md5.unwrap_or_default(),
+        },
+    })
+}
@@ -13,7 +13,8 @@ impl Client {
         self: &Arc<Self>,
         msg: pb::msg::Message,
     ) -> RQResult<()> {
-        let message = parse_temp_message(msg)?;
+        let mut message = parse_temp_message(msg)?;
+        self.resolve_long_message(&mut message.elements).await;
         self.handler
             .handle(QEvent::GroupTempMessage(GroupTempMessageEvent {
                 client: self.clone(),
@@ -25,24 +26,26 @@ impl Client {
 }
 
 pub fn parse_temp_message(msg: pb::msg::Message) -> RQResult<GroupTempMessage> {
-    let head = msg.head.unwrap();
+    let head = msg.head.ok_or(RQError::EmptyField("head"))?;
     let tmp_head = head
         .c2c_tmp_msg_head
         .ok_or(RQError::EmptyField("c2c_tmp_msg_head"))?;
+    let rich_text = msg
+        .body
+        .and_then(|body| body.rich_text)
+        .ok_or(RQError::EmptyField("rich_text"))?;
 
     Ok(GroupTempMessage {
         seqs: vec![head.msg_seq.unwrap_or_default()],
-        rands: vec![
-            if let Some(attr) = &msg.body.as_ref().unwrap().rich_text.as_ref().unwrap().attr {
-                attr.random()
-            } else {
-                0
-            },
-        ],
-        time: head.msg_time.unwrap(),
+        rands: vec![rich_text
+            .attr
+            .as_ref()
+            .map(|attr| attr.random())
+            .unwrap_or_default()],
+        time: head.msg_time.unwrap_or_default(),
         from_uin: head.from_uin.unwrap_or_default(),
         from_nick: head.from_nick.unwrap_or_default(),
-        elements: MessageChain::from(msg.body.unwrap().rich_text.unwrap().elems), // todo ptt_store
+        elements: MessageChain::from(rich_text.elems), // todo ptt_store
         group_code: tmp_head.group_code.unwrap_or_default(),
     })
 }
@@ -1,3 +1,4 @@
+use std::sync::Arc;
 use std::time::Duration;
 
 use bytes::Bytes;
@@ -12,7 +13,7 @@ use crate::RQError;
 
 impl Client {
     pub(crate) async fn process_config_push_req(
-        &self,
+        self: &Arc<Self>,
         config_push_req: ConfigPushReq,
     ) -> Result<(), RQError> {
         // send response to server
@@ -25,7 +26,19 @@ impl Client {
         self.send(response).await?;
         match config_push_req.body {
             ConfigPushBody::Unknown => {}
-            ConfigPushBody::SsoServers { .. } => {}
+            ConfigPushBody::SsoServers { servers } => {
+                let mut addrs = Vec::new();
+                for server in servers {
+                    if let Ok(res) =
+                        tokio::net::lookup_host((server.server.as_str(), server.port as u16)).await
+                    {
+                        addrs.extend(res);
+                    }
+                }
+                if !addrs.is_empty() {
+                    self.update_sso_addrs(addrs).await;
+                }
+            }
             ConfigPushBody::FileStorageInfo { info: _, rsp_body } => {
                 let mut session = self.highway_session.write().await;
                 if let Some(rsp_body) = rsp_body {
@@ -65,11 +65,14 @@ impl Client {
 
     pub(crate) async fn process_message_sync(self: &Arc<Self>, msgs: Vec<pb::msg::Message>) {
         for msg in msgs {
-            let head = msg.head.clone().unwrap();
-            if self.msg_exists(&head).await {
+            let Some(head) = msg.head.as_ref() else {
+                tracing::warn!("sync message without head");
+                continue;
+            };
+            if self.msg_exists(head).await {
                 continue;
             }
-            match msg.head.as_ref().unwrap().msg_type() {
+            match head.msg_type() {
                 9 | 10 | 31 | 79 | 97 | 120 | 132 | 133 | 166 | 167 => {
                     if let Err(err) = self.process_friend_message(msg).await {
                         tracing::error!("failed to process friend message {err}");
@@ -88,6 +91,11 @@ impl Client {
                 208 => {
                     // friend ptt_store
                 }
+                529 if head.c2c_cmd() == 4 => {
+                    if let Err(err) = self.process_friend_file_message(msg).await {
+                        tracing::error!("failed to process friend file message {err}");
+                    }
+                }
                 _ => tracing::warn!("unhandled sync message type"),
             }
         }
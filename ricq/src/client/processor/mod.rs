@@ -1,8 +1,14 @@
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use bytes::Bytes;
 
 use ricq_core::protocol::packet::Packet;
+use ricq_core::structs::DecodeError;
+use ricq_core::RQError;
+
+use crate::client::event::DecodeErrorEvent;
+use crate::handler::QEvent;
 
 pub mod c2c;
 pub mod config_push_svc;
@@ -21,15 +27,33 @@ macro_rules! log_error {
 }
 
 impl super::Client {
+    /// 记录推送解析失败，开启 `report_decode_error` 时外发 `QEvent::DecodeError`
+    pub(crate) async fn on_decode_error(self: &Arc<Self>, command: &str, raw: Bytes, err: RQError) {
+        tracing::warn!("failed to decode [{}]: {}", command, err);
+        if !self.report_decode_error.load(Ordering::Relaxed) {
+            return;
+        }
+        self.handler
+            .handle(QEvent::DecodeError(DecodeErrorEvent {
+                client: self.clone(),
+                inner: DecodeError {
+                    command: command.to_owned(),
+                    error: err.to_string(),
+                    raw,
+                },
+            }))
+            .await;
+    }
+
     /// 接收到的 Packet 统一分发
     pub async fn process_income_packet(self: &Arc<Self>, pkt: Packet) {
         tracing::trace!("received pkt: {}", &pkt.command_name);
         // response, send_and_wait 的包将会在此被截流
-        {
-            if let Some(sender) = self.packet_promises.write().await.remove(&pkt.seq_id) {
-                sender.send(pkt).unwrap();
-                return;
-            }
+        let sender = self.packet_promises.lock().unwrap().remove(&pkt.seq_id);
+        if let Some(sender) = sender {
+            // 等待方可能已经超时或取消
+            sender.send(pkt).ok();
+            return;
         }
 
         tracing::trace!("pkt: {} passed packet_promises", &pkt.command_name);
@@ -48,7 +72,7 @@ impl super::Client {
                         .engine
                         .read()
                         .await
-                        .decode_group_message_packet(pkt.body);
+                        .decode_group_message_packet(pkt.body.clone());
                     match p {
                         Ok(part) => {
                             log_error!(
@@ -57,12 +81,16 @@ impl super::Client {
                             )
                         }
                         Err(err) => {
-                            tracing::warn!("failed to decode [OnlinePush.PbPushGroupMsg]: {}", err);
+                            cli.on_decode_error(&pkt.command_name, pkt.body, err).await;
                         }
                     }
                 }
                 "ConfigPushSvc.PushReq" => {
-                    let req = cli.engine.read().await.decode_push_req_packet(pkt.body);
+                    let req = cli
+                        .engine
+                        .read()
+                        .await
+                        .decode_push_req_packet(pkt.body.clone());
                     match req {
                         Ok(req) => {
                             log_error!(
@@ -71,7 +99,7 @@ impl super::Client {
                             )
                         }
                         Err(err) => {
-                            tracing::warn!("failed to decode [ConfigPushSvc.PushReq]: {}", err);
+                            cli.on_decode_error(&pkt.command_name, pkt.body, err).await;
                         }
                     }
                 }
@@ -85,7 +113,7 @@ impl super::Client {
                             )
                         }
                         Err(err) => {
-                            tracing::warn!("failed to decode [RegPrxySvc.PushParam]: {}", err);
+                            cli.on_decode_error(&pkt.command_name, pkt.body, err).await;
                         }
                     }
                 }
@@ -94,13 +122,13 @@ impl super::Client {
                     // 1. Server 发送 PushNotify 到 Client, 表示有通知需要 Client 拉取 (不带具体内容)
                     // 2. Client 根据 msg_type 发送请求拉取具体通知内容
                     // 类型：好友申请、群申请、私聊消息、其他?
-                    let resp = cli.engine.read().await.decode_svc_notify(pkt.body);
+                    let resp = cli.engine.read().await.decode_svc_notify(pkt.body.clone());
                     match resp {
                         Ok(notify) => {
                             cli.process_push_notify(notify).await;
                         }
                         Err(err) => {
-                            tracing::warn!("failed to decode [MessageSvc.PushNotify]: {}", err);
+                            cli.on_decode_error(&pkt.command_name, pkt.body, err).await;
                         }
                     }
                 }
@@ -109,7 +137,7 @@ impl super::Client {
                         .engine
                         .read()
                         .await
-                        .decode_online_push_req_packet(pkt.body);
+                        .decode_online_push_req_packet(pkt.body.clone());
                     match resp {
                         Ok(resp) => {
                             log_error!(
@@ -126,7 +154,7 @@ impl super::Client {
                             cli.process_push_req(resp.msg_infos).await;
                         }
                         Err(err) => {
-                            tracing::warn!("failed to decode [OnlinePush.ReqPush]: {}", err);
+                            cli.on_decode_error(&pkt.command_name, pkt.body, err).await;
                         }
                     }
                 }
@@ -135,44 +163,68 @@ impl super::Client {
                         .engine
                         .read()
                         .await
-                        .decode_online_push_trans_packet(pkt.body);
+                        .decode_online_push_trans_packet(pkt.body.clone());
                     match online_push_trans {
                         Ok(online_push_trans) => {
                             cli.process_push_trans(online_push_trans).await;
                         }
                         Err(err) => {
-                            tracing::warn!("failed to decode [OnlinePush.PbPushTransMsg]: {}", err);
+                            cli.on_decode_error(&pkt.command_name, pkt.body, err).await;
                         }
                     }
                 }
                 "MessageSvc.PushForceOffline" => {
-                    let offline = cli.engine.read().await.decode_force_offline(pkt.body);
+                    let offline = cli
+                        .engine
+                        .read()
+                        .await
+                        .decode_force_offline(pkt.body.clone());
                     match offline {
                         Ok(offline) => {
                             cli.process_push_force_offline(offline).await;
                         }
                         Err(err) => {
-                            tracing::warn!(
-                                "failed to decode [MessageSvc.PushForceOffline]: {}",
-                                err
-                            );
+                            cli.on_decode_error(&pkt.command_name, pkt.body, err).await;
                         }
                     }
                 }
                 "StatSvc.ReqMSFOffline" => {
-                    let offline = cli.engine.read().await.decode_msf_force_offline(pkt.body);
+                    let offline = cli
+                        .engine
+                        .read()
+                        .await
+                        .decode_msf_force_offline(pkt.body.clone());
                     match offline {
                         Ok(offline) => {
                             cli.process_msf_force_offline(offline).await;
                         }
                         Err(err) => {
-                            tracing::warn!("failed to decode [StatSvc.ReqMSFOffline]: {}", err);
+                            cli.on_decode_error(&pkt.command_name, pkt.body, err).await;
+                        }
+                    }
+                }
+                "StatSvc.SvcReqMSFLoginNotify" => {
+                    let notify = cli
+                        .engine
+                        .read()
+                        .await
+                        .decode_msf_login_notify(pkt.body.clone());
+                    match notify {
+                        Ok(notify) => {
+                            cli.process_msf_login_notify(notify).await;
+                        }
+                        Err(err) => {
+                            cli.on_decode_error(&pkt.command_name, pkt.body, err).await;
                         }
                     }
                 }
                 "OnlinePush.PbC2CMsgSync" => {
                     // 其他设备发送消息，同步
-                    let push = cli.engine.read().await.decode_c2c_sync_packet(pkt.body);
+                    let push = cli
+                        .engine
+                        .read()
+                        .await
+                        .decode_c2c_sync_packet(pkt.body.clone());
                     match push {
                         Ok(push) => {
                             log_error!(
@@ -181,7 +233,7 @@ impl super::Client {
                             )
                         }
                         Err(err) => {
-                            tracing::warn!("failed to decode [OnlinePush.PbC2CMsgSync]: {}", err);
+                            cli.on_decode_error(&pkt.command_name, pkt.body, err).await;
                         }
                     }
                 }
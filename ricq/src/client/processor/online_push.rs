@@ -1,35 +1,46 @@
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::Duration;
 
-use bytes::{Buf, Bytes};
+use bytes::Bytes;
 use cached::Cached;
 
 use prost::Message;
+use ricq_core::binary::BinaryReader;
 use ricq_core::command::online_push::GroupMessagePart;
+use ricq_core::command::online_push::{parse_honor_tip, parse_special_title_tip};
 use ricq_core::command::online_push::{OnlinePushTrans, PushTransInfo};
 use ricq_core::msg::MessageChain;
 use ricq_core::structs::{
-    DeleteFriend, FriendInfo, FriendMessageRecall, FriendPoke, GroupAudio, GroupAudioMessage,
-    GroupLeave, GroupMessage, GroupMessageRecall, GroupMute, GroupNameUpdate, GroupPoke,
+    DeleteFriend, FriendInfo, FriendInputStatus, FriendMessageRecall, FriendNicknameUpdate,
+    FriendPoke, FriendRemarkUpdate, GroupAudio, GroupAudioMessage, GroupEssenceChange, GroupLeave,
+    GroupMessage, GroupMessageRecall, GroupMute, GroupNameUpdate, GroupPoke, MemberCardUpdate,
 };
 use ricq_core::{jce, pb};
 
 use crate::client::event::{
-    DeleteFriendEvent, FriendMessageRecallEvent, FriendPokeEvent, GroupAudioMessageEvent,
-    GroupDisbandEvent, GroupLeaveEvent, GroupMessageEvent, GroupMessageRecallEvent, GroupMuteEvent,
-    GroupNameUpdateEvent, GroupPokeEvent, MemberPermissionChangeEvent, NewFriendEvent,
+    DeleteFriendEvent, FriendInputStatusEvent, FriendMessageRecallEvent, FriendNicknameUpdateEvent,
+    FriendPokeEvent, FriendRemarkUpdateEvent, GroupAudioMessageEvent, GroupDisbandEvent,
+    GroupEssenceChangeEvent, GroupLeaveEvent, GroupMessageEvent, GroupMessageRecallEvent,
+    GroupMuteEvent, GroupNameUpdateEvent, GroupPokeEvent, MemberCardUpdateEvent,
+    MemberHonorChangeEvent, MemberPermissionChangeEvent, MemberSpecialTitleUpdateEvent,
+    NewFriendEvent,
 };
 use crate::client::handler::QEvent;
 use crate::client::Client;
 use crate::RQResult;
 
+/// 群名片缓存的最大条数，满了清空
+const MEMBER_CARD_CACHE_SIZE: usize = 4096;
+
 impl Client {
     pub(crate) async fn process_group_message_part(
         self: &Arc<Self>,
         group_message_part: GroupMessagePart,
     ) -> RQResult<()> {
         // receipt message
-        if group_message_part.from_uin == self.uin().await {
+        let from_self = group_message_part.from_uin == self.uin().await;
+        if from_self {
             if let Some(tx) = self
                 .receipt_waiters
                 .lock()
@@ -86,12 +97,23 @@ impl Client {
         // handle message
         if let Some(group_msg) = group_msg {
             // message is finish
-            self.handler
-                .handle(QEvent::GroupMessage(GroupMessageEvent {
-                    client: self.clone(),
-                    inner: self.parse_group_message(group_msg).await?,
-                }))
-                .await; //todo
+            let mut event = GroupMessageEvent {
+                client: self.clone(),
+                inner: self.parse_group_message(group_msg).await?,
+            };
+            self.resolve_long_message(&mut event.inner.elements).await;
+            self.check_member_card(
+                event.inner.group_code,
+                event.inner.from_uin,
+                &event.inner.group_card,
+            )
+            .await;
+            if from_self && self.self_message_events.load(Ordering::Relaxed) {
+                // 其他设备发送的消息
+                self.handler.handle(QEvent::SelfGroupMessage(event)).await;
+            } else {
+                self.handler.handle(QEvent::GroupMessage(event)).await;
+            }
         }
         Ok(())
     }
@@ -136,222 +158,396 @@ impl Client {
         })
 
         // TODO: extInfo
-        // TODO: group_card_update
         // TODO: ptt_store
     }
 
+    // 群消息中的名片与上次见到的不同时，视为名片修改
+    async fn check_member_card(self: &Arc<Self>, group_code: i64, uin: i64, card: &str) {
+        let key = (group_code, uin);
+        let unchanged = self
+            .member_card_cache
+            .read()
+            .await
+            .get_store()
+            .get(&key)
+            .map(|old| old == card)
+            .unwrap_or_default();
+        if unchanged {
+            return;
+        }
+        let old = self.set_member_card(key, card.to_owned()).await;
+        if let Some(old) = old {
+            if old != card {
+                self.handler
+                    .handle(QEvent::MemberCardUpdate(MemberCardUpdateEvent {
+                        client: self.clone(),
+                        inner: MemberCardUpdate {
+                            group_code,
+                            member_uin: uin,
+                            old_card: Some(old),
+                            new_card: card.to_owned(),
+                        },
+                    }))
+                    .await;
+            }
+        }
+    }
+
+    // 记录群名片，返回旧名片
+    async fn set_member_card(&self, key: (i64, i64), card: String) -> Option<String> {
+        let mut cache = self.member_card_cache.write().await;
+        if cache.cache_size() >= MEMBER_CARD_CACHE_SIZE {
+            cache.cache_clear();
+        }
+        cache.cache_set(key, card)
+    }
+
     pub(crate) async fn process_push_req(self: &Arc<Self>, msg_infos: Vec<jce::PushMessageInfo>) {
         for info in msg_infos {
             if self.push_req_exists(&info).await {
                 continue;
             }
-            match info.msg_type {
-                732 => {
-                    let mut r = info.v_msg;
-                    let group_code = r.get_u32() as i64;
-                    let i_type = r.get_u8();
-                    r.get_u8();
-                    match i_type {
-                        0x0c => {
-                            let operator = r.get_u32() as i64;
-                            if operator == self.uin().await {
-                                continue;
-                            }
-                            r.advance(6);
-                            let target = r.get_u32() as i64;
-                            let duration = Duration::from_secs(r.get_u32() as u64);
-                            self.handler
-                                .handle(QEvent::GroupMute(GroupMuteEvent {
-                                    client: self.clone(),
-                                    inner: GroupMute {
-                                        group_code,
-                                        operator_uin: operator,
-                                        target_uin: target,
-                                        duration,
-                                    },
-                                }))
-                                .await;
-                        }
-                        0x10 | 0x11 | 0x14 | 0x15 => {
-                            // group notify msg
-                            r.advance(1);
-                            let b = pb::notify::NotifyMsgBody::decode(&*r).unwrap();
-                            if let Some(opt_msg_recall) = b.opt_msg_recall {
-                                let operator_uin = opt_msg_recall.uin;
-                                // use map iterator here will produce massive asm code
-                                for rm in opt_msg_recall.recalled_msg_list {
-                                    if rm.msg_type == 2 {
-                                        continue;
-                                    }
-                                    self.handler
-                                        .handle(QEvent::GroupMessageRecall(
-                                            GroupMessageRecallEvent {
-                                                client: self.clone(),
-                                                inner: GroupMessageRecall {
-                                                    msg_seq: rm.seq,
-                                                    group_code,
-                                                    operator_uin,
-                                                    author_uin: rm.author_uin,
-                                                    time: rm.time,
-                                                },
-                                            },
-                                        ))
-                                        .await;
-                                }
-                            }
+            let raw = info.v_msg.clone();
+            if let Err(err) = self.process_push_message_info(info).await {
+                self.on_decode_error("OnlinePush.ReqPush", raw, err).await;
+            }
+        }
+    }
 
-                            if let Some(t) = b.opt_general_gray_tip {
-                                let mut sender: i64 = 0;
-                                let mut receiver: i64 = 0;
-                                for templ in t.msg_templ_param {
-                                    match &*templ.name {
-                                        "uin_str1" => {
-                                            sender = templ.value.parse().unwrap_or_default()
-                                        }
-                                        "uin_str2" => {
-                                            receiver = templ.value.parse().unwrap_or_default()
-                                        }
-                                        _ => {}
-                                    }
-                                }
-                                if sender != 0 {
-                                    self.handler
-                                        .handle(QEvent::GroupPoke(GroupPokeEvent {
-                                            client: self.clone(),
-                                            inner: GroupPoke {
-                                                group_code,
-                                                sender,
-                                                receiver,
-                                            },
-                                        }))
-                                        .await;
-                                }
-                            }
-                            // TODO 一些没什么用的 event 暂时没写
+    async fn process_push_message_info(
+        self: &Arc<Self>,
+        info: jce::PushMessageInfo,
+    ) -> RQResult<()> {
+        match info.msg_type {
+            732 => {
+                let mut r = info.v_msg;
+                let group_code = r.read_u32()? as i64;
+                let i_type = r.read_u8()?;
+                r.read_u8()?;
+                match i_type {
+                    0x0c => {
+                        let operator = r.read_u32()? as i64;
+                        if operator == self.uin().await {
+                            return Ok(());
                         }
-                        _ => {}
+                        r.skip(6)?;
+                        let target = r.read_u32()? as i64;
+                        let duration = Duration::from_secs(r.read_u32()? as u64);
+                        self.handler
+                            .handle(QEvent::GroupMute(GroupMuteEvent {
+                                client: self.clone(),
+                                inner: GroupMute {
+                                    group_code,
+                                    operator_uin: operator,
+                                    target_uin: target,
+                                    duration,
+                                },
+                            }))
+                            .await;
                     }
-                }
-                528 => {
-                    let mut v_msg = info.v_msg;
-                    let msg: jce::MsgType0x210 = jcers::from_buf(&mut v_msg).unwrap();
-                    match msg.sub_msg_type {
-                        0x8A | 0x8B => {
-                            let s8a = pb::Sub8A::decode(&*msg.v_protobuf).unwrap();
-                            for m in s8a.msg_info {
+                    0x10 | 0x11 | 0x14 | 0x15 => {
+                        // group notify msg
+                        r.skip(1)?;
+                        let b = pb::notify::NotifyMsgBody::decode(&*r)?;
+                        if let Some(opt_msg_recall) = b.opt_msg_recall {
+                            let operator_uin = opt_msg_recall.uin;
+                            // use map iterator here will produce massive asm code
+                            for rm in opt_msg_recall.recalled_msg_list {
+                                if rm.msg_type == 2 {
+                                    continue;
+                                }
                                 self.handler
-                                    .handle(QEvent::FriendMessageRecall(FriendMessageRecallEvent {
+                                    .handle(QEvent::GroupMessageRecall(GroupMessageRecallEvent {
                                         client: self.clone(),
-                                        inner: FriendMessageRecall {
-                                            msg_seq: m.msg_seq,
-                                            friend_uin: m.from_uin,
-                                            time: m.msg_time,
+                                        inner: GroupMessageRecall {
+                                            msg_seq: rm.seq,
+                                            group_code,
+                                            operator_uin,
+                                            author_uin: rm.author_uin,
+                                            time: rm.time,
                                         },
                                     }))
                                     .await;
                             }
                         }
-                        0xB3 => {
-                            let msg_add_frd_notify = pb::SubB3::decode(&*msg.v_protobuf).unwrap();
-                            if let Some(f) = msg_add_frd_notify.msg_add_frd_notify {
+
+                        if let Some(tips) = b.opt_msg_gray_tips {
+                            if let Some(update) = parse_special_title_tip(
+                                group_code,
+                                &String::from_utf8_lossy(&tips.content),
+                            ) {
                                 self.handler
-                                    .handle(QEvent::NewFriend(NewFriendEvent {
-                                        client: self.clone(),
-                                        inner: FriendInfo {
-                                            uin: f.uin,
-                                            nick: f.nick,
-                                            ..Default::default()
+                                    .handle(QEvent::MemberSpecialTitleUpdate(
+                                        MemberSpecialTitleUpdateEvent {
+                                            client: self.clone(),
+                                            inner: update,
                                         },
-                                    }))
+                                    ))
                                     .await;
                             }
                         }
-                        0xD4 => {
-                            let d4 = pb::SubD4::decode(&*msg.v_protobuf).unwrap();
+
+                        if let Some(digest) = b.qq_group_digest_msg {
                             self.handler
-                                .handle(QEvent::GroupLeave(GroupLeaveEvent {
+                                .handle(QEvent::GroupEssenceChange(GroupEssenceChangeEvent {
                                     client: self.clone(),
-                                    inner: GroupLeave {
-                                        group_code: d4.uin,
-                                        member_uin: self.uin().await,
-                                        operator_uin: None,
+                                    inner: GroupEssenceChange {
+                                        group_code,
+                                        msg_seq: digest.seq as i32,
+                                        msg_rand: digest.random as i32,
+                                        sender_uin: digest.sender as i64,
+                                        operator_uin: digest.digest_oper as i64,
+                                        time: digest.op_time as i32,
+                                        is_set: digest.op_type == 1,
                                     },
                                 }))
                                 .await;
                         }
-                        0x122 | 0x123 => {
-                            let t =
-                                pb::notify::GeneralGrayTipInfo::decode(&*msg.v_protobuf).unwrap();
+
+                        if let Some(change) = b
+                            .opt_general_gray_tip
+                            .as_ref()
+                            .and_then(|t| parse_honor_tip(group_code, t))
+                        {
+                            self.handler
+                                .handle(QEvent::MemberHonorChange(MemberHonorChangeEvent {
+                                    client: self.clone(),
+                                    inner: change,
+                                }))
+                                .await;
+                        } else if let Some(t) = b.opt_general_gray_tip {
                             let mut sender: i64 = 0;
                             let mut receiver: i64 = 0;
                             for templ in t.msg_templ_param {
-                                if templ.name == "uin_str1" {
-                                    sender = templ.value.parse().unwrap_or_default()
-                                } else if templ.name == "uin_str2" {
-                                    receiver = templ.value.parse().unwrap_or_default()
+                                match &*templ.name {
+                                    "uin_str1" => sender = templ.value.parse().unwrap_or_default(),
+                                    "uin_str2" => {
+                                        receiver = templ.value.parse().unwrap_or_default()
+                                    }
+                                    _ => {}
                                 }
                             }
                             if sender != 0 {
                                 self.handler
-                                    .handle(QEvent::FriendPoke(FriendPokeEvent {
+                                    .handle(QEvent::GroupPoke(GroupPokeEvent {
                                         client: self.clone(),
-                                        inner: FriendPoke { sender, receiver },
+                                        inner: GroupPoke {
+                                            group_code,
+                                            sender,
+                                            receiver,
+                                        },
                                     }))
                                     .await;
                             }
                         }
-                        0x27 => {
-                            let s27 =
-                                pb::msgtype0x210::SubMsg0x27Body::decode(&*msg.v_protobuf).unwrap();
-                            for mod_info in s27.mod_infos {
-                                if let Some(mod_group_profile) = mod_info.mod_group_profile {
-                                    for profile_info in mod_group_profile.group_profile_infos {
-                                        if profile_info.field.unwrap_or_default() != 1 {
-                                            continue;
-                                        }
-                                        self.handler
-                                            .handle(QEvent::GroupNameUpdate(GroupNameUpdateEvent {
-                                                client: self.clone(),
-                                                inner: GroupNameUpdate {
-                                                    group_code: mod_group_profile
-                                                        .group_code
-                                                        .unwrap_or_default()
-                                                        as i64,
-                                                    operator_uin: mod_group_profile
-                                                        .cmd_uin
-                                                        .unwrap_or_default()
-                                                        as i64,
-                                                    group_name: String::from_utf8_lossy(
-                                                        profile_info.value(),
-                                                    )
-                                                    .into_owned(),
-                                                },
-                                            }))
-                                            .await;
+                        // TODO 一些没什么用的 event 暂时没写
+                    }
+                    _ => {}
+                }
+            }
+            528 => {
+                let mut v_msg = info.v_msg;
+                let msg: jce::MsgType0x210 = jcers::from_buf(&mut v_msg)?;
+                match msg.sub_msg_type {
+                    0x8A | 0x8B => {
+                        let s8a = pb::Sub8A::decode(&*msg.v_protobuf)?;
+                        for m in s8a.msg_info {
+                            self.handler
+                                .handle(QEvent::FriendMessageRecall(FriendMessageRecallEvent {
+                                    client: self.clone(),
+                                    inner: FriendMessageRecall {
+                                        msg_seq: m.msg_seq,
+                                        friend_uin: m.from_uin,
+                                        time: m.msg_time,
+                                    },
+                                }))
+                                .await;
+                        }
+                    }
+                    0xB3 => {
+                        let msg_add_frd_notify = pb::SubB3::decode(&*msg.v_protobuf)?;
+                        if let Some(f) = msg_add_frd_notify.msg_add_frd_notify {
+                            self.handler
+                                .handle(QEvent::NewFriend(NewFriendEvent {
+                                    client: self.clone(),
+                                    inner: FriendInfo {
+                                        uin: f.uin,
+                                        nick: f.nick,
+                                        ..Default::default()
+                                    },
+                                }))
+                                .await;
+                        }
+                    }
+                    0xD4 => {
+                        let d4 = pb::SubD4::decode(&*msg.v_protobuf)?;
+                        self.handler
+                            .handle(QEvent::GroupLeave(GroupLeaveEvent {
+                                client: self.clone(),
+                                inner: GroupLeave {
+                                    group_code: d4.uin,
+                                    member_uin: self.uin().await,
+                                    operator_uin: None,
+                                },
+                            }))
+                            .await;
+                    }
+                    0x122 | 0x123 => {
+                        let t = pb::notify::GeneralGrayTipInfo::decode(&*msg.v_protobuf)?;
+                        let mut sender: i64 = 0;
+                        let mut receiver: i64 = 0;
+                        for templ in t.msg_templ_param {
+                            if templ.name == "uin_str1" {
+                                sender = templ.value.parse().unwrap_or_default()
+                            } else if templ.name == "uin_str2" {
+                                receiver = templ.value.parse().unwrap_or_default()
+                            }
+                        }
+                        if sender != 0 {
+                            self.handler
+                                .handle(QEvent::FriendPoke(FriendPokeEvent {
+                                    client: self.clone(),
+                                    inner: FriendPoke { sender, receiver },
+                                }))
+                                .await;
+                        }
+                    }
+                    0x27 => {
+                        let s27 = pb::msgtype0x210::SubMsg0x27Body::decode(&*msg.v_protobuf)?;
+                        for mod_info in s27.mod_infos {
+                            if let Some(mod_group_profile) = mod_info.mod_group_profile {
+                                for profile_info in mod_group_profile.group_profile_infos {
+                                    if profile_info.field.unwrap_or_default() != 1 {
+                                        continue;
                                     }
+                                    self.handler
+                                        .handle(QEvent::GroupNameUpdate(GroupNameUpdateEvent {
+                                            client: self.clone(),
+                                            inner: GroupNameUpdate {
+                                                group_code: mod_group_profile
+                                                    .group_code
+                                                    .unwrap_or_default()
+                                                    as i64,
+                                                operator_uin: mod_group_profile
+                                                    .cmd_uin
+                                                    .unwrap_or_default()
+                                                    as i64,
+                                                group_name: String::from_utf8_lossy(
+                                                    profile_info.value(),
+                                                )
+                                                .into_owned(),
+                                            },
+                                        }))
+                                        .await;
                                 }
-                                if let Some(del_friend) = mod_info.del_friend {
-                                    for uin in del_friend.uins {
-                                        self.handler
-                                            .handle(QEvent::DeleteFriend(DeleteFriendEvent {
+                            }
+                            if let Some(profile) = mod_info.mod_group_member_profile {
+                                for info in profile.group_member_profile_infos {
+                                    // 1: 群名片
+                                    if info.field.unwrap_or_default() != 1 {
+                                        continue;
+                                    }
+                                    let group_code = profile.group_code.unwrap_or_default() as i64;
+                                    let member_uin = profile.uin.unwrap_or_default() as i64;
+                                    let new_card =
+                                        String::from_utf8_lossy(info.value()).into_owned();
+                                    self.set_member_card(
+                                        (group_code, member_uin),
+                                        new_card.clone(),
+                                    )
+                                    .await;
+                                    self.handler
+                                        .handle(QEvent::MemberCardUpdate(MemberCardUpdateEvent {
+                                            client: self.clone(),
+                                            inner: MemberCardUpdate {
+                                                group_code,
+                                                member_uin,
+                                                old_card: None,
+                                                new_card,
+                                            },
+                                        }))
+                                        .await;
+                                }
+                            }
+                            if let Some(profile) = mod_info.mod_profile {
+                                let uin = profile.uin.unwrap_or_default() as i64;
+                                for info in profile.profile_infos {
+                                    // 20002: 昵称
+                                    if info.field.unwrap_or_default() != 20002 {
+                                        continue;
+                                    }
+                                    let nickname =
+                                        String::from_utf8_lossy(info.value()).into_owned();
+                                    if uin == self.uin().await {
+                                        self.account_info.write().await.nickname = nickname;
+                                        continue;
+                                    }
+                                    self.handler
+                                        .handle(QEvent::FriendNicknameUpdate(
+                                            FriendNicknameUpdateEvent {
                                                 client: self.clone(),
-                                                inner: DeleteFriend { uin: uin as i64 },
-                                            }))
-                                            .await;
+                                                inner: FriendNicknameUpdate { uin, nickname },
+                                            },
+                                        ))
+                                        .await;
+                                }
+                            }
+                            if let Some(remark) = mod_info.mod_friend_remark {
+                                for rmk in remark.frd_rmk {
+                                    // 带群号的是群备注
+                                    if rmk.group_code.unwrap_or_default() != 0 {
+                                        continue;
                                     }
+                                    self.handler
+                                        .handle(QEvent::FriendRemarkUpdate(
+                                            FriendRemarkUpdateEvent {
+                                                client: self.clone(),
+                                                inner: FriendRemarkUpdate {
+                                                    uin: rmk.fuin.unwrap_or_default() as i64,
+                                                    remark: String::from_utf8_lossy(rmk.rmk_name())
+                                                        .into_owned(),
+                                                },
+                                            },
+                                        ))
+                                        .await;
+                                }
+                            }
+                            if let Some(del_friend) = mod_info.del_friend {
+                                for uin in del_friend.uins {
+                                    self.handler
+                                        .handle(QEvent::DeleteFriend(DeleteFriendEvent {
+                                            client: self.clone(),
+                                            inner: DeleteFriend { uin: uin as i64 },
+                                        }))
+                                        .await;
                                 }
                             }
                         }
-                        0x44 => {
-                            // group sync
-                            // friend sync
+                    }
+                    0x115 => {
+                        let s115 = pb::Sub115::decode(&*msg.v_protobuf)?;
+                        if let Some(item) = s115.msg_notify_item {
+                            self.handler
+                                .handle(QEvent::FriendInputStatus(FriendInputStatusEvent {
+                                    client: self.clone(),
+                                    inner: FriendInputStatus {
+                                        uin: s115.from_uin,
+                                        typing: item.event_type == 1,
+                                    },
+                                }))
+                                .await;
                         }
-                        _ => {}
                     }
+                    0x44 => {
+                        // group sync
+                        // friend sync
+                    }
+                    _ => {}
                 }
-                _ => {}
             }
+            _ => {}
         }
+        Ok(())
     }
 
     async fn push_req_exists(&self, info: &jce::PushMessageInfo) -> bool {
@@ -444,8 +640,7 @@ impl Client {
     }
 
     pub(crate) async fn process_sid_ticket_expired(self: &Arc<Self>, seq: i32) -> RQResult<()> {
-        self.request_change_sig(Some(3554528)).await?;
-        self.register_client().await?;
+        self.refresh_sig(Some(3554528)).await?;
         self.send_sid_ticket_expired_response(seq).await?;
         Ok(())
     }
@@ -1,8 +1,9 @@
 use std::sync::Arc;
 
 use ricq_core::jce;
+use ricq_core::structs::{OtherClientInfo, OtherClientStatusChange};
 
-use crate::client::event::MSFOfflineEvent;
+use crate::client::event::{MSFOfflineEvent, OtherClientStatusChangeEvent};
 use crate::client::{Client, NetworkStatus};
 use crate::handler::QEvent;
 
@@ -23,4 +24,44 @@ impl Client {
             }))
             .await;
     }
+
+    // 其他客户端上线/下线，status 1 上线，2 下线
+    pub(crate) async fn process_msf_login_notify(
+        self: &Arc<Self>,
+        notify: jce::SvcReqMSFLoginNotify,
+    ) {
+        let online = match notify.status {
+            1 => true,
+            2 => false,
+            _ => return,
+        };
+        {
+            let mut clients = self.online_clients.write().await;
+            clients.retain(|c| c.app_id != notify.app_id);
+            if online {
+                clients.push(OtherClientInfo {
+                    app_id: notify.app_id,
+                    instance_id: 0,
+                    sub_platform: notify.title.clone(),
+                    device_kind: notify.info.clone(),
+                });
+            }
+        }
+        self.handler
+            .handle(QEvent::OtherClientStatusChange(
+                OtherClientStatusChangeEvent {
+                    client: self.clone(),
+                    inner: OtherClientStatusChange {
+                        app_id: notify.app_id,
+                        platform: notify.platform,
+                        title: notify.title,
+                        info: notify.info,
+                        product_type: notify.product_type,
+                        client_type: notify.client_type,
+                        online,
+                    },
+                },
+            ))
+            .await;
+    }
 }
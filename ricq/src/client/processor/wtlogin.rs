@@ -16,6 +16,11 @@ impl Client {
             .write()
             .await
             .process_login_response(login_response);
+        if matches!(login_response, LoginResponse::Success(_)) {
+            if let Err(err) = self.save_session().await {
+                tracing::warn!("failed to save session: {}", err);
+            }
+        }
         self.handler.handle(QEvent::Login(self.uin().await)).await;
     }
 
@@ -0,0 +1,282 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use tokio::io::{self, AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::client::tcp::tcp_connect_timeout;
+
+/// 代理认证信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyAuth {
+    pub username: String,
+    pub password: String,
+}
+
+impl ProxyAuth {
+    pub fn new(username: impl Into<String>, password: impl Into<String>) -> Self {
+        Self {
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+}
+
+/// 代理配置，用于连接服务器和 highway 上传
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Proxy {
+    /// SOCKS5 代理
+    Socks5 {
+        addr: SocketAddr,
+        auth: Option<ProxyAuth>,
+    },
+    /// HTTP CONNECT 代理
+    HttpConnect {
+        addr: SocketAddr,
+        auth: Option<ProxyAuth>,
+    },
+}
+
+impl Proxy {
+    /// 通过代理连接 target，timeout 包括连接代理和握手的时间
+    pub async fn connect(&self, target: SocketAddr, timeout: Duration) -> io::Result<TcpStream> {
+        tokio::time::timeout(timeout, async {
+            match self {
+                Proxy::Socks5 { addr, auth } => {
+                    let mut stream = tcp_connect_timeout(*addr, timeout).await?;
+                    socks5_handshake(&mut stream, target, auth.as_ref()).await?;
+                    Ok(stream)
+                }
+                Proxy::HttpConnect { addr, auth } => {
+                    let mut stream = tcp_connect_timeout(*addr, timeout).await?;
+                    http_connect_handshake(&mut stream, target, auth.as_ref()).await?;
+                    Ok(stream)
+                }
+            }
+        })
+        .await
+        .map_err(io::Error::from)
+        .flatten()
+    }
+}
+
+/// 可选代理连接，没有代理时直连
+pub(crate) async fn connect_with_proxy(
+    proxy: Option<&Proxy>,
+    target: SocketAddr,
+    timeout: Duration,
+) -> io::Result<TcpStream> {
+    match proxy {
+        Some(proxy) => proxy.connect(target, timeout).await,
+        None => tcp_connect_timeout(target, timeout).await,
+    }
+}
+
+fn proxy_error(msg: impl Into<String>) -> io::Error {
+    io::Error::other(msg.into())
+}
+
+// RFC 1928 / RFC 1929
+async fn socks5_handshake(
+    stream: &mut TcpStream,
+    target: SocketAddr,
+    auth: Option<&ProxyAuth>,
+) -> io::Result<()> {
+    // 用户名、密码的长度各占 1 字节
+    if let Some(auth) = auth {
+        if auth.username.len() > 255 || auth.password.len() > 255 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "socks5: username or password longer than 255 bytes",
+            ));
+        }
+    }
+    if auth.is_some() {
+        stream.write_all(&[0x05, 0x02, 0x00, 0x02]).await?;
+    } else {
+        stream.write_all(&[0x05, 0x01, 0x00]).await?;
+    }
+    let mut buf = [0u8; 2];
+    stream.read_exact(&mut buf).await?;
+    if buf[0] != 0x05 {
+        return Err(proxy_error("socks5: invalid version"));
+    }
+    match (buf[1], auth) {
+        (0x00, _) => {}
+        (0x02, Some(auth)) => {
+            let mut req = vec![0x01, auth.username.len() as u8];
+            req.extend_from_slice(auth.username.as_bytes());
+            req.push(auth.password.len() as u8);
+            req.extend_from_slice(auth.password.as_bytes());
+            stream.write_all(&req).await?;
+            stream.read_exact(&mut buf).await?;
+            if buf[1] != 0x00 {
+                return Err(proxy_error("socks5: authentication failed"));
+            }
+        }
+        _ => return Err(proxy_error("socks5: no acceptable auth method")),
+    }
+
+    let mut req = vec![0x05, 0x01, 0x00];
+    match target {
+        SocketAddr::V4(addr) => {
+            req.push(0x01);
+            req.extend_from_slice(&addr.ip().octets());
+        }
+        SocketAddr::V6(addr) => {
+            req.push(0x04);
+            req.extend_from_slice(&addr.ip().octets());
+        }
+    }
+    req.extend_from_slice(&target.port().to_be_bytes());
+    stream.write_all(&req).await?;
+
+    let mut head = [0u8; 4];
+    stream.read_exact(&mut head).await?;
+    if head[1] != 0x00 {
+        return Err(proxy_error(format!(
+            "socks5: connect failed, reply {}",
+            head[1]
+        )));
+    }
+    // 跳过 BND.ADDR 和 BND.PORT
+    let addr_len = match head[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => stream.read_u8().await? as usize,
+        _ => return Err(proxy_error("socks5: invalid address type")),
+    };
+    let mut bound = vec![0u8; addr_len + 2];
+    stream.read_exact(&mut bound).await?;
+    Ok(())
+}
+
+async fn http_connect_handshake(
+    stream: &mut TcpStream,
+    target: SocketAddr,
+    auth: Option<&ProxyAuth>,
+) -> io::Result<()> {
+    let mut req = format!("CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n");
+    if let Some(auth) = auth {
+        let credential = base64::encode(format!("{}:{}", auth.username, auth.password));
+        req.push_str(&format!("Proxy-Authorization: Basic {credential}\r\n"));
+    }
+    req.push_str("\r\n");
+    stream.write_all(req.as_bytes()).await?;
+
+    // 逐字节读取响应头，避免读走后续数据
+    let mut resp = Vec::new();
+    while !resp.ends_with(b"\r\n\r\n") {
+        if resp.len() > 8192 {
+            return Err(proxy_error("http connect: response header too long"));
+        }
+        resp.push(stream.read_u8().await?);
+    }
+    let resp = String::from_utf8_lossy(&resp);
+    let status = resp
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .unwrap_or_default();
+    if status != "200" {
+        return Err(proxy_error(format!(
+            "http connect: unexpected status {}",
+            resp.lines().next().unwrap_or_default()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_socks5_with_auth() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut s, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4];
+            s.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf, [0x05, 0x02, 0x00, 0x02]);
+            s.write_all(&[0x05, 0x02]).await.unwrap();
+            let mut buf = [0u8; 11];
+            s.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"\x01\x04user\x04pass");
+            s.write_all(&[0x01, 0x00]).await.unwrap();
+            let mut buf = [0u8; 10];
+            s.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf, [0x05, 0x01, 0x00, 0x01, 1, 2, 3, 4, 0x1f, 0x90]);
+            s.write_all(&[0x05, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0])
+                .await
+                .unwrap();
+            s.write_all(b"ok").await.unwrap();
+        });
+        let proxy = Proxy::Socks5 {
+            addr,
+            auth: Some(ProxyAuth::new("user", "pass")),
+        };
+        let mut stream = proxy
+            .connect(([1, 2, 3, 4], 8080).into(), Duration::from_secs(5))
+            .await
+            .unwrap();
+        let mut buf = [0u8; 2];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ok");
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_socks5_auth_too_long() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut s, _) = listener.accept().await.unwrap();
+            let mut buf = Vec::new();
+            let _ = s.read_to_end(&mut buf).await;
+            // 不应发送任何握手数据
+            assert!(buf.is_empty());
+        });
+        let proxy = Proxy::Socks5 {
+            addr,
+            auth: Some(ProxyAuth::new("u".repeat(256), "pass")),
+        };
+        let err = proxy
+            .connect(([1, 2, 3, 4], 8080).into(), Duration::from_secs(5))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_http_connect_rejected() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut s, _) = listener.accept().await.unwrap();
+            let mut buf = Vec::new();
+            while !buf.ends_with(b"\r\n\r\n") {
+                buf.push(s.read_u8().await.unwrap());
+            }
+            let req = String::from_utf8_lossy(&buf);
+            assert!(req.starts_with("CONNECT 1.2.3.4:8080 HTTP/1.1\r\n"));
+            assert!(req.contains("Proxy-Authorization: Basic dXNlcjpwYXNz\r\n"));
+            s.write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n")
+                .await
+                .unwrap();
+        });
+        let proxy = Proxy::HttpConnect {
+            addr,
+            auth: Some(ProxyAuth::new("user", "pass")),
+        };
+        let result = proxy
+            .connect(([1, 2, 3, 4], 8080).into(), Duration::from_secs(5))
+            .await;
+        assert!(result.is_err());
+        server.await.unwrap();
+    }
+}
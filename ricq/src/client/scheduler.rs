@@ -0,0 +1,387 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tokio::sync::oneshot;
+
+use crate::{RQError, RQResult};
+
+/// 发送优先级，数值越大越先发送
+///
+/// 登录、心跳等协议包不经过发送队列，不受限速影响
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum SendPriority {
+    Low = 0,
+    #[default]
+    Normal = 1,
+    High = 2,
+}
+
+/// 限速的对象
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SendTarget {
+    Group(i64),
+    Friend(i64),
+}
+
+/// 令牌桶配置，每秒补充 rate 个，最多积攒 burst 个
+///
+/// rate 不是正数或 burst 为 0 时不限速
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub rate: f64,
+    pub burst: u32,
+}
+
+impl RateLimit {
+    pub fn new(rate: f64, burst: u32) -> Self {
+        Self { rate, burst }
+    }
+
+    /// 不限速
+    pub fn is_unlimited(&self) -> bool {
+        // NaN 也视为不限速
+        self.rate.is_nan() || self.rate <= 0.0 || self.burst == 0
+    }
+}
+
+/// 发送队列配置
+#[derive(Debug, Clone, PartialEq)]
+pub struct SendSchedulerConfig {
+    /// 所有消息共用的限速
+    pub global: RateLimit,
+    /// 每个群/好友单独的限速
+    pub per_target: RateLimit,
+    /// 排队上限，超过后返回 `RQError::SendQueueFull`
+    pub max_queue: usize,
+}
+
+impl Default for SendSchedulerConfig {
+    fn default() -> Self {
+        Self {
+            global: RateLimit::new(5.0, 10),
+            per_target: RateLimit::new(1.0, 3),
+            max_queue: 256,
+        }
+    }
+}
+
+/// 发送队列状态
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SendQueueMetrics {
+    /// 正在排队的消息数
+    pub queued: usize,
+    /// 各优先级排队数，下标为 `SendPriority as usize`
+    pub queued_by_priority: [usize; 3],
+    /// 已放行的消息数
+    pub sent: u64,
+    /// 因队列已满被拒绝的消息数
+    pub rejected: u64,
+}
+
+struct Bucket {
+    tokens: f64,
+    limit: RateLimit,
+    last: Instant,
+}
+
+impl Bucket {
+    fn new(limit: RateLimit, now: Instant) -> Self {
+        Self {
+            tokens: limit.burst as f64,
+            limit,
+            last: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.limit.rate).min(self.limit.burst as f64);
+        self.last = now;
+    }
+
+    fn ready(&self) -> bool {
+        self.limit.is_unlimited() || self.tokens >= 1.0
+    }
+
+    fn is_full(&self) -> bool {
+        self.limit.is_unlimited() || self.tokens >= self.limit.burst as f64
+    }
+
+    fn take(&mut self) {
+        if !self.limit.is_unlimited() {
+            self.tokens -= 1.0;
+        }
+    }
+
+    /// 距离下一个令牌的时间
+    fn time_to_token(&self) -> Duration {
+        if self.ready() {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64((1.0 - self.tokens) / self.limit.rate)
+    }
+}
+
+struct Waiter {
+    target: Option<SendTarget>,
+    priority: SendPriority,
+    tx: Option<oneshot::Sender<()>>,
+}
+
+struct State {
+    global: Bucket,
+    targets: HashMap<SendTarget, Bucket>,
+    /// key: (优先级取反, 入队序号)，按顺序放行
+    waiters: BTreeMap<(u8, u64), Waiter>,
+    next_id: u64,
+    metrics: SendQueueMetrics,
+}
+
+/// 消息发送队列，按优先级放行，同时受全局和单个目标的令牌桶限制
+pub struct SendScheduler {
+    config: SendSchedulerConfig,
+    state: Mutex<State>,
+}
+
+impl SendScheduler {
+    pub fn new(config: SendSchedulerConfig) -> Self {
+        let now = Instant::now();
+        Self {
+            state: Mutex::new(State {
+                global: Bucket::new(config.global, now),
+                targets: HashMap::new(),
+                waiters: BTreeMap::new(),
+                next_id: 0,
+                metrics: Default::default(),
+            }),
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &SendSchedulerConfig {
+        &self.config
+    }
+
+    pub fn metrics(&self) -> SendQueueMetrics {
+        self.state.lock().unwrap().metrics.clone()
+    }
+
+    /// 等待发送许可，队列已满时立即返回 `Err(RQError::SendQueueFull)`
+    ///
+    /// 取消（drop）该 Future 会退出排队
+    pub async fn acquire(
+        &self,
+        target: Option<SendTarget>,
+        priority: SendPriority,
+    ) -> RQResult<()> {
+        let (tx, mut rx) = oneshot::channel();
+        let key = {
+            let mut state = self.state.lock().unwrap();
+            if state.waiters.len() >= self.config.max_queue {
+                state.metrics.rejected += 1;
+                return Err(RQError::SendQueueFull);
+            }
+            let key = (SendPriority::High as u8 - priority as u8, state.next_id);
+            state.next_id += 1;
+            state.waiters.insert(
+                key,
+                Waiter {
+                    target,
+                    priority,
+                    tx: Some(tx),
+                },
+            );
+            state.metrics.queued += 1;
+            state.metrics.queued_by_priority[priority as usize] += 1;
+            key
+        };
+        let _guard = WaiterGuard {
+            scheduler: self,
+            key,
+        };
+        loop {
+            let wait = self.pump();
+            tokio::select! {
+                result = &mut rx => {
+                    return result.map_err(|_| RQError::Other("send scheduler closed".into()));
+                }
+                _ = tokio::time::sleep(wait) => {}
+            }
+        }
+    }
+
+    /// 按顺序放行可以发送的消息，返回下次检查前需要等待的时间
+    fn pump(&self) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let State {
+            global,
+            targets,
+            waiters,
+            metrics,
+            ..
+        } = &mut *state;
+        global.refill(now);
+        let mut wait = Duration::MAX;
+        let mut granted = Vec::new();
+        for (key, waiter) in waiters.iter_mut() {
+            if !global.ready() {
+                wait = wait.min(global.time_to_token());
+                break;
+            }
+            if let Some(target) = waiter.target {
+                let bucket = targets
+                    .entry(target)
+                    .or_insert_with(|| Bucket::new(self.config.per_target, now));
+                bucket.refill(now);
+                if !bucket.ready() {
+                    wait = wait.min(bucket.time_to_token());
+                    continue;
+                }
+                bucket.take();
+            }
+            global.take();
+            if let Some(tx) = waiter.tx.take() {
+                tx.send(()).ok();
+            }
+            granted.push(*key);
+        }
+        for key in granted {
+            if let Some(waiter) = waiters.remove(&key) {
+                metrics.queued -= 1;
+                metrics.queued_by_priority[waiter.priority as usize] -= 1;
+                metrics.sent += 1;
+            }
+        }
+        // 令牌已满的目标不需要保留
+        if targets.len() > 1024 {
+            targets.retain(|_, bucket| {
+                bucket.refill(now);
+                !bucket.is_full()
+            });
+        }
+        wait.clamp(Duration::from_millis(1), Duration::from_secs(1))
+    }
+}
+
+/// 退出排队时移除记录
+struct WaiterGuard<'a> {
+    scheduler: &'a SendScheduler,
+    key: (u8, u64),
+}
+
+impl Drop for WaiterGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.scheduler.state.lock() {
+            if let Some(waiter) = state.waiters.remove(&self.key) {
+                state.metrics.queued -= 1;
+                state.metrics.queued_by_priority[waiter.priority as usize] -= 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rate_limit_and_queue_full() {
+        let scheduler = SendScheduler::new(SendSchedulerConfig {
+            global: RateLimit::new(100.0, 100),
+            per_target: RateLimit::new(20.0, 2),
+            max_queue: 2,
+        });
+        let target = Some(SendTarget::Group(1));
+        let start = Instant::now();
+        for _ in 0..4 {
+            scheduler
+                .acquire(target, SendPriority::Normal)
+                .await
+                .unwrap();
+        }
+        // burst 2 个，之后每 50ms 一个
+        assert!(start.elapsed() >= Duration::from_millis(90));
+        // 其他目标不受影响
+        let start = Instant::now();
+        scheduler
+            .acquire(Some(SendTarget::Friend(1)), SendPriority::Normal)
+            .await
+            .unwrap();
+        assert!(start.elapsed() < Duration::from_millis(40));
+
+        let scheduler = Arc::new(scheduler);
+        let mut pending = Vec::new();
+        for _ in 0..2 {
+            let s = scheduler.clone();
+            pending.push(tokio::spawn(async move {
+                s.acquire(target, SendPriority::Low).await
+            }));
+        }
+        tokio::task::yield_now().await;
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert_eq!(scheduler.metrics().queued, 2);
+        assert!(matches!(
+            scheduler.acquire(target, SendPriority::High).await,
+            Err(RQError::SendQueueFull)
+        ));
+        for p in pending {
+            p.await.unwrap().unwrap();
+        }
+        let metrics = scheduler.metrics();
+        assert_eq!(metrics.queued, 0);
+        assert_eq!(metrics.sent, 7);
+        assert_eq!(metrics.rejected, 1);
+    }
+
+    #[tokio::test]
+    async fn test_unlimited() {
+        let scheduler = SendScheduler::new(SendSchedulerConfig {
+            global: RateLimit::new(0.0, 1),
+            per_target: RateLimit::new(1.0, 0),
+            max_queue: 16,
+        });
+        let result = tokio::time::timeout(Duration::from_millis(100), async {
+            for _ in 0..100 {
+                scheduler
+                    .acquire(Some(SendTarget::Group(1)), SendPriority::Normal)
+                    .await
+                    .unwrap();
+            }
+        })
+        .await;
+        assert!(result.is_ok());
+        assert_eq!(scheduler.metrics().sent, 100);
+    }
+
+    #[tokio::test]
+    async fn test_priority() {
+        let scheduler = Arc::new(SendScheduler::new(SendSchedulerConfig {
+            global: RateLimit::new(20.0, 1),
+            per_target: RateLimit::new(100.0, 100),
+            max_queue: 16,
+        }));
+        scheduler.acquire(None, SendPriority::Normal).await.unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        for priority in [SendPriority::Low, SendPriority::Normal, SendPriority::High] {
+            let s = scheduler.clone();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                s.acquire(None, priority).await.unwrap();
+                tx.send(priority).unwrap();
+            });
+        }
+        tokio::task::yield_now().await;
+        let mut order = Vec::new();
+        for _ in 0..3 {
+            order.push(rx.recv().await.unwrap());
+        }
+        assert_eq!(
+            order,
+            vec![SendPriority::High, SendPriority::Normal, SendPriority::Low]
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+use ricq_core::command::wtlogin::LoginResponse;
+use ricq_core::protocol::sig::Sig;
+use ricq_core::Token;
+
+use crate::client::event::{SigRefreshed, SigRefreshedEvent};
+use crate::handler::QEvent;
+use crate::{Client, RQError, RQResult};
+
+/// 自动刷新 sig 配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SigRefreshConfig {
+    /// 两次检查的最长间隔，最小为 1 秒
+    pub check_interval: Duration,
+    /// 在 s_key 过期前多久刷新
+    pub s_key_margin: Duration,
+    /// D2 使用多久后刷新
+    pub d2_max_age: Duration,
+}
+
+impl Default for SigRefreshConfig {
+    fn default() -> Self {
+        Self {
+            check_interval: Duration::from_secs(600),
+            s_key_margin: Duration::from_secs(1800),
+            d2_max_age: Duration::from_secs(86400),
+        }
+    }
+}
+
+impl SigRefreshConfig {
+    const MIN_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+    /// 距离下次需要刷新的时间，已经需要刷新时返回 0
+    pub fn refresh_delay(&self, sig: &Sig, now: i64) -> Duration {
+        let secs = |d: Duration| i64::try_from(d.as_secs()).unwrap_or(i64::MAX);
+        let mut due = sig.login_time.saturating_add(secs(self.d2_max_age));
+        if sig.s_key_expired_time > 0 {
+            due = due.min(
+                sig.s_key_expired_time
+                    .saturating_sub(secs(self.s_key_margin)),
+            );
+        }
+        Duration::from_secs(due.saturating_sub(now).max(0) as u64)
+    }
+
+    // 避免 check_interval 为 0 时空转
+    fn check_interval(&self) -> Duration {
+        self.check_interval.max(Self::MIN_CHECK_INTERVAL)
+    }
+}
+
+impl Client {
+    /// 换 sig 并重新注册客户端，成功后外发 `QEvent::SigRefreshed`
+    pub async fn refresh_sig(self: &Arc<Self>, main_sig_map: Option<u32>) -> RQResult<Token> {
+        match self.request_change_sig(main_sig_map).await? {
+            LoginResponse::Success(_) => {}
+            other => return Err(RQError::Other(format!("failed to refresh sig, {other:?}"))),
+        }
+        self.register_client().await?;
+        let token = self.gen_token().await;
+        self.handler
+            .handle(QEvent::SigRefreshed(SigRefreshedEvent {
+                client: self.clone(),
+                inner: SigRefreshed {
+                    token: token.clone(),
+                },
+            }))
+            .await;
+        Ok(token)
+    }
+
+    /// 定时检查 s_key 过期时间和 D2 使用时长，快过期时主动刷新
+    ///
+    /// 该方法会阻塞到下线或关闭自动刷新，通常 spawn 使用
+    pub async fn do_sig_refresh(self: &Arc<Self>) {
+        self.sig_refresh_enabled.store(true, Ordering::SeqCst);
+        while self.online.load(Ordering::SeqCst) {
+            let Some(config) = *self.sig_refresh.read().await else {
+                break;
+            };
+            let now = UNIX_EPOCH.elapsed().unwrap().as_secs() as i64;
+            let delay = config.refresh_delay(&self.engine.read().await.transport.sig, now);
+            if !delay.is_zero() {
+                tokio::time::sleep(delay.min(config.check_interval())).await;
+                continue;
+            }
+            tracing::info!("sig is about to expire, refreshing");
+            if let Err(err) = self.refresh_sig(None).await {
+                tracing::warn!("failed to refresh sig: {}", err);
+            }
+            // 避免服务端返回的过期时间过短时连续刷新
+            tokio::time::sleep(config.check_interval()).await;
+        }
+        self.sig_refresh_enabled.store(false, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refresh_delay() {
+        let config = SigRefreshConfig {
+            check_interval: Duration::from_secs(60),
+            s_key_margin: Duration::from_secs(100),
+            d2_max_age: Duration::from_secs(1000),
+        };
+        let sig = Sig {
+            login_time: 10000,
+            s_key_expired_time: 10500,
+            ..Default::default()
+        };
+        assert_eq!(config.refresh_delay(&sig, 10000), Duration::from_secs(400));
+        assert_eq!(config.refresh_delay(&sig, 10400), Duration::ZERO);
+        assert_eq!(config.refresh_delay(&sig, 20000), Duration::ZERO);
+
+        let sig = Sig {
+            login_time: 10000,
+            ..Default::default()
+        };
+        assert_eq!(config.refresh_delay(&sig, 10000), Duration::from_secs(1000));
+
+        // 过大的时长不会溢出
+        let config = SigRefreshConfig {
+            check_interval: Duration::ZERO,
+            s_key_margin: Duration::MAX,
+            d2_max_age: Duration::MAX,
+        };
+        assert_eq!(
+            config.refresh_delay(&sig, 10000).as_secs(),
+            i64::MAX as u64 - 10000
+        );
+        let sig = Sig {
+            login_time: 10000,
+            s_key_expired_time: 10500,
+            ..Default::default()
+        };
+        assert_eq!(config.refresh_delay(&sig, 10000), Duration::ZERO);
+        assert_eq!(config.check_interval(), Duration::from_secs(1));
+    }
+}
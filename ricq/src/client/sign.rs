@@ -0,0 +1,290 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde::Deserialize;
+
+use ricq_core::hex::{decode_hex, encode_hex};
+use ricq_core::protocol::packet::SsoSign;
+
+use crate::client::http::{http_get, http_post};
+use crate::{RQError, RQResult};
+
+/// 默认需要 SSO 签名的 command
+pub const DEFAULT_SIGN_COMMANDS: &[&str] = &[
+    "wtlogin.login",
+    "wtlogin.exchange_emp",
+    "wtlogin.trans_emp",
+    "StatSvc.register",
+    "MessageSvc.PbSendMsg",
+    "MultiMsg.ApplyUp",
+    "ProfileService.getGroupInfoReq",
+    "friendlist.GetTroopListReqV2",
+    "friendlist.addFriend",
+    "OidbSvc.0x89a_0",
+    "OidbSvc.0x8a0_0",
+    "OidbSvc.0x8fc_2",
+    "OidbSvc.0x55c_1",
+    "OidbSvc.0x570_8",
+];
+
+/// 数据包签名，默认实现不签名
+///
+/// 登录时用于生成 tlv 0x544，`should_sign` 返回 true 的包发送前会调用 `sso_sign`
+#[async_trait]
+pub trait SignProvider: Send + Sync {
+    /// tlv 0x544，data 形如 `810_9`，返回 None 时不写入该 tlv
+    async fn energy(&self, _uin: i64, _data: &str, _salt: Bytes) -> RQResult<Option<Bytes>> {
+        Ok(None)
+    }
+
+    /// SSO 包签名，返回 None 时不签名
+    async fn sso_sign(
+        &self,
+        _uin: i64,
+        _seq: i32,
+        _command: &str,
+        _body: Bytes,
+    ) -> RQResult<Option<SsoSign>> {
+        Ok(None)
+    }
+
+    /// 是否需要对该 command 签名
+    fn should_sign(&self, command: &str) -> bool {
+        DEFAULT_SIGN_COMMANDS.contains(&command)
+    }
+}
+
+/// 不签名
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopSignProvider;
+
+impl SignProvider for NoopSignProvider {}
+
+/// 通过 HTTP 签名服务签名
+///
+/// - `POST /sign`，body 为表单 `uin=&qua=&cmd=&seq=&buffer=`，返回 `{"code":0,"data":{"sign":"","token":"","extra":""}}`
+/// - `GET /custom_energy?uin=&data=&salt=` 返回 `{"code":0,"data":""}`
+///
+/// 二进制数据均为 hex
+#[derive(Debug, Clone)]
+pub struct HttpSignProvider {
+    pub addr: SocketAddr,
+    /// 协议版本标识，例如 `V1_AND_SQ_8.9.63_4194_YYB_D`
+    pub qua: String,
+    pub timeout: Duration,
+}
+
+#[derive(Deserialize)]
+struct SignResponse<T> {
+    code: i32,
+    #[serde(default)]
+    msg: String,
+    data: Option<T>,
+}
+
+#[derive(Deserialize)]
+struct SignData {
+    #[serde(default)]
+    sign: String,
+    #[serde(default)]
+    token: String,
+    #[serde(default)]
+    extra: String,
+}
+
+impl HttpSignProvider {
+    pub fn new(addr: SocketAddr, qua: impl Into<String>) -> Self {
+        Self {
+            addr,
+            qua: qua.into(),
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    async fn get<T: for<'de> Deserialize<'de>>(&self, path: &str) -> RQResult<T> {
+        let body = http_get(None, self.addr, path, self.timeout).await?;
+        parse_sign_response(&body)
+    }
+
+    async fn post_form<T: for<'de> Deserialize<'de>>(&self, path: &str, form: &str) -> RQResult<T> {
+        let body = http_post(
+            None,
+            self.addr,
+            path,
+            "application/x-www-form-urlencoded",
+            form.as_bytes(),
+            self.timeout,
+        )
+        .await?;
+        parse_sign_response(&body)
+    }
+}
+
+fn parse_sign_response<T: for<'de> Deserialize<'de>>(body: &[u8]) -> RQResult<T> {
+    let resp: SignResponse<T> =
+        serde_json::from_slice(body).map_err(|e| RQError::Decode(format!("sign server: {e}")))?;
+    if resp.code != 0 {
+        return Err(RQError::Other(format!(
+            "sign server error {}: {}",
+            resp.code, resp.msg
+        )));
+    }
+    resp.data
+        .ok_or_else(|| RQError::Decode("sign server: data is none".into()))
+}
+
+#[async_trait]
+impl SignProvider for HttpSignProvider {
+    async fn energy(&self, uin: i64, data: &str, salt: Bytes) -> RQResult<Option<Bytes>> {
+        let path = format!(
+            "/custom_energy?uin={}&data={}&salt={}",
+            uin,
+            url_encode(data),
+            encode_hex(&salt)
+        );
+        let energy: String = self.get(&path).await?;
+        Ok(Some(hex_bytes(&energy)?))
+    }
+
+    async fn sso_sign(
+        &self,
+        uin: i64,
+        seq: i32,
+        command: &str,
+        body: Bytes,
+    ) -> RQResult<Option<SsoSign>> {
+        let form = format!(
+            "uin={}&qua={}&cmd={}&seq={}&buffer={}",
+            uin,
+            url_encode(&self.qua),
+            url_encode(command),
+            seq,
+            encode_hex(&body)
+        );
+        let data: SignData = self.post_form("/sign", &form).await?;
+        Ok(Some(SsoSign {
+            sign: hex_bytes(&data.sign)?,
+            token: hex_bytes(&data.token)?,
+            extra: hex_bytes(&data.extra)?,
+        }))
+    }
+}
+
+fn hex_bytes(s: &str) -> RQResult<Bytes> {
+    if !s.is_ascii() || !s.len().is_multiple_of(2) {
+        return Err(RQError::Decode("sign server: invalid hex".into()));
+    }
+    decode_hex(s)
+        .map(Bytes::from)
+        .map_err(|e| RQError::Decode(format!("sign server: invalid hex: {e}")))
+}
+
+fn url_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{b:02X}")),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    // 本地模拟签名服务，返回请求行和 body 供断言
+    async fn serve_once(listener: &TcpListener, body: &str) -> (String, String) {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut req = Vec::new();
+        let mut buf = vec![0; 4096];
+        let header_end = loop {
+            let n = stream.read(&mut buf).await.unwrap();
+            req.extend_from_slice(&buf[..n]);
+            if let Some(pos) = req.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+        };
+        let header = String::from_utf8_lossy(&req[..header_end]).into_owned();
+        let content_length = header
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+            .map(|(_, value)| value.trim().parse().unwrap())
+            .unwrap_or(0);
+        while req.len() < header_end + content_length {
+            let n = stream.read(&mut buf).await.unwrap();
+            req.extend_from_slice(&buf[..n]);
+        }
+        let resp = format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        stream.write_all(resp.as_bytes()).await.unwrap();
+        let mut request_line = header.split_whitespace();
+        let line = format!(
+            "{} {}",
+            request_line.next().unwrap_or_default(),
+            request_line.next().unwrap_or_default()
+        );
+        (
+            line,
+            String::from_utf8_lossy(&req[header_end..]).into_owned(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_http_sign_provider() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let provider = HttpSignProvider::new(listener.local_addr().unwrap(), "V1_AND_SQ");
+
+        let ((line, form), sign) = tokio::join!(
+            serve_once(
+                &listener,
+                r#"{"code":0,"data":{"sign":"0102","token":"03","extra":""}}"#
+            ),
+            provider.sso_sign(
+                10000,
+                7,
+                "MessageSvc.PbSendMsg",
+                Bytes::from_static(&[0xab])
+            )
+        );
+        assert_eq!(line, "POST /sign");
+        assert_eq!(
+            form,
+            "uin=10000&qua=V1_AND_SQ&cmd=MessageSvc.PbSendMsg&seq=7&buffer=ab"
+        );
+        let sign = sign.unwrap().unwrap();
+        assert_eq!(sign.sign.as_ref(), &[1, 2]);
+        assert_eq!(sign.token.as_ref(), &[3]);
+        assert!(sign.extra.is_empty());
+
+        let ((line, _), energy) = tokio::join!(
+            serve_once(&listener, r#"{"code":0,"data":"aabb"}"#),
+            provider.energy(10000, "810_9", Bytes::from_static(&[1]))
+        );
+        assert_eq!(line, "GET /custom_energy?uin=10000&data=810_9&salt=01");
+        assert_eq!(energy.unwrap().unwrap().as_ref(), &[0xaa, 0xbb]);
+
+        let (_, err) = tokio::join!(
+            serve_once(&listener, r#"{"code":1,"msg":"not registered"}"#),
+            provider.energy(10000, "810_9", Bytes::new())
+        );
+        assert!(err.is_err());
+    }
+}
@@ -1,4 +1,8 @@
-use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::client::{SendSchedulerConfig, SigRefreshConfig, SignProvider};
+use crate::ext::session::SessionStore;
 
 use ricq_core::protocol::{
     device::Device,
@@ -6,10 +10,30 @@ use ricq_core::protocol::{
     version::{get_version, Protocol},
 };
 
-#[derive(Debug)]
+#[derive(derivative::Derivative)]
+#[derivative(Debug)]
 pub struct Config {
     pub device: Device,
     pub version: Version,
+    /// send_and_wait 默认超时时间
+    pub request_timeout: Duration,
+    /// 消息发送队列，None 表示不限速
+    pub send_scheduler: Option<SendSchedulerConfig>,
+    /// 服务端推送解析失败时是否外发 `QEvent::DecodeError`
+    pub report_decode_error: bool,
+    /// 本账号在其他设备发送的消息外发为 `QEvent::SelfGroupMessage`/`QEvent::SelfFriendMessage`，
+    /// 而不是 `QEvent::GroupMessage`/`QEvent::FriendMessage`；
+    /// 其他设备发送的好友文件只在开启时外发为 `QEvent::FriendFileMessage`；
+    /// 语音没有对应的 Self 事件，不论是否开启都外发为 `QEvent::FriendAudioMessage`/`QEvent::GroupAudioMessage`
+    pub self_message_events: bool,
+    /// 数据包签名，None 表示不签名
+    #[derivative(Debug = "ignore")]
+    pub sign_provider: Option<Arc<dyn SignProvider>>,
+    /// 自动刷新 sig，None 表示不自动刷新
+    pub sig_refresh: Option<SigRefreshConfig>,
+    /// 会话存储，登录成功和刷新 token 后自动保存
+    #[derivative(Debug = "ignore")]
+    pub session_store: Option<Arc<dyn SessionStore>>,
 }
 
 impl Default for Config {
@@ -17,12 +41,70 @@ impl Default for Config {
         Self {
             device: Device::random(),
             version: get_version(Protocol::IPad),
+            request_timeout: crate::client::DEFAULT_REQUEST_TIMEOUT,
+            send_scheduler: None,
+            report_decode_error: false,
+            self_message_events: false,
+            sign_provider: None,
+            sig_refresh: Some(SigRefreshConfig::default()),
+            session_store: None,
         }
     }
 }
 
 impl Config {
     pub fn new(device: Device, version: Version) -> Self {
-        Self { device, version }
+        Self {
+            device,
+            version,
+            request_timeout: crate::client::DEFAULT_REQUEST_TIMEOUT,
+            send_scheduler: None,
+            report_decode_error: false,
+            self_message_events: false,
+            sign_provider: None,
+            sig_refresh: Some(SigRefreshConfig::default()),
+            session_store: None,
+        }
+    }
+
+    /// 使用指定的协议版本，可以通过 `Version::load` 从协议文件加载
+    pub fn with_version(mut self, version: Version) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    pub fn with_send_scheduler(mut self, config: SendSchedulerConfig) -> Self {
+        self.send_scheduler = Some(config);
+        self
+    }
+
+    pub fn with_report_decode_error(mut self, report: bool) -> Self {
+        self.report_decode_error = report;
+        self
+    }
+
+    pub fn with_self_message_events(mut self, enabled: bool) -> Self {
+        self.self_message_events = enabled;
+        self
+    }
+
+    pub fn with_sig_refresh(mut self, config: Option<SigRefreshConfig>) -> Self {
+        self.sig_refresh = config;
+        self
+    }
+
+    pub fn with_session_store(mut self, store: Arc<dyn SessionStore>) -> Self {
+        self.session_store = Some(store);
+        self
+    }
+
+    pub fn with_sign_provider(mut self, provider: Arc<dyn SignProvider>) -> Self {
+        self.sign_provider = Some(provider);
+        self
     }
 }
@@ -9,6 +9,7 @@ pub async fn after_login(client: &Arc<Client>) {
         tracing::error!("failed to register client: {}", err)
     }
     start_heartbeat(client.clone()).await;
+    start_sig_refresh(client.clone()).await;
     if let Err(err) = client.refresh_status().await {
         tracing::error!("failed to refresh status: {}", err)
     }
@@ -22,3 +23,12 @@ pub async fn start_heartbeat(client: Arc<Client>) {
         });
     }
 }
+
+/// 如果当前未启动自动刷新 sig，spawn 开始定时检查
+pub async fn start_sig_refresh(client: Arc<Client>) {
+    if !client.sig_refresh_enabled.load(Ordering::Relaxed) {
+        tokio::spawn(async move {
+            client.do_sig_refresh().await;
+        });
+    }
+}
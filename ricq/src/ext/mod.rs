@@ -2,3 +2,4 @@ pub mod common;
 pub mod image;
 pub mod login;
 pub mod reconnect;
+pub mod session;
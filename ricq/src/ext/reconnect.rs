@@ -1,16 +1,102 @@
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
+use rand::Rng;
 use tokio::io::{AsyncRead, AsyncWrite};
 
 use ricq_core::command::wtlogin::LoginResponse;
 
+use crate::client::event::{
+    ReconnectGaveUp, ReconnectGaveUpEvent, Reconnected, ReconnectedEvent, Reconnecting,
+    ReconnectingEvent,
+};
 use crate::client::net::Connector;
 use crate::client::NetworkStatus;
 use crate::ext::common::after_login;
+use crate::handler::QEvent;
 use crate::{Client, RQError, RQResult};
 
+/// 重连策略，等待时间按指数增长，并加入随机抖动
+#[derive(Clone)]
+pub struct ReconnectPolicy {
+    /// 第一次重连前等待的时间
+    pub initial_interval: Duration,
+    /// 等待时间上限
+    pub max_interval: Duration,
+    /// 每次失败后等待时间的倍数
+    pub multiplier: f64,
+    /// 随机抖动比例，0.2 表示在 ±20% 内浮动
+    pub jitter: f64,
+    /// 从掉线开始的最长重连时间，超过后放弃
+    pub max_elapsed_time: Option<Duration>,
+    /// 每次掉线最多尝试的次数，超过后放弃
+    pub max_attempts: Option<usize>,
+    /// 主凭证登录失败后使用的凭证，通常是 token 失效后改用密码
+    pub fallback: Option<Credential>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_interval: Duration::from_secs(1),
+            max_interval: Duration::from_secs(300),
+            multiplier: 2.0,
+            jitter: 0.2,
+            max_elapsed_time: None,
+            max_attempts: None,
+            fallback: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// 固定间隔，最多尝试 max_attempts 次
+    pub fn fixed(interval: Duration, max_attempts: usize) -> Self {
+        Self {
+            initial_interval: interval,
+            max_interval: interval,
+            multiplier: 1.0,
+            jitter: 0.0,
+            max_elapsed_time: None,
+            max_attempts: Some(max_attempts),
+            fallback: None,
+        }
+    }
+
+    pub fn with_fallback(mut self, fallback: Credential) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// 第 attempt 次（从 1 开始）重连前的等待时间，不含抖动
+    pub fn backoff(&self, attempt: usize) -> Duration {
+        let exp = attempt.saturating_sub(1).min(i32::MAX as usize) as i32;
+        let secs = self.initial_interval.as_secs_f64() * self.multiplier.max(1.0).powi(exp);
+        // 溢出或 NaN 时使用上限
+        Duration::try_from_secs_f64(secs.min(self.max_interval.as_secs_f64()))
+            .map_or(self.max_interval, |d| d.min(self.max_interval))
+    }
+
+    /// 第 attempt 次重连前的等待时间，含抖动
+    pub fn delay(&self, attempt: usize) -> Duration {
+        let backoff = self.backoff(attempt);
+        // NaN 或非正数时不抖动
+        if !self.jitter.is_finite() || self.jitter <= 0.0 {
+            return backoff;
+        }
+        let jitter = self.jitter.min(1.0);
+        let factor = rand::thread_rng().gen_range(1.0 - jitter..=1.0 + jitter);
+        Duration::try_from_secs_f64(backoff.as_secs_f64() * factor).unwrap_or(backoff)
+    }
+
+    /// 是否应该放弃重连，attempt 为即将进行的尝试
+    pub fn exhausted(&self, attempt: usize, elapsed: Duration) -> bool {
+        self.max_attempts.is_some_and(|max| attempt > max)
+            || self.max_elapsed_time.is_some_and(|max| elapsed >= max)
+    }
+}
+
 /// 自动重连，在掉线后使用，会阻塞到重连结束
 pub async fn auto_reconnect<T: AsyncRead + AsyncWrite + 'static + Send>(
     client: Arc<Client>,
@@ -19,7 +105,27 @@ pub async fn auto_reconnect<T: AsyncRead + AsyncWrite + 'static + Send>(
     max: usize,
     connector: impl Connector<T>,
 ) {
-    let mut count = 0;
+    auto_reconnect_with_policy(
+        client,
+        credential,
+        ReconnectPolicy::fixed(interval, max + 1),
+        connector,
+    )
+    .await
+}
+
+/// 按 policy 自动重连，在掉线后使用，会阻塞到重连结束
+///
+/// 重连过程会触发 `QEvent::Reconnecting`、`QEvent::Reconnected` 和 `QEvent::ReconnectGaveUp`
+pub async fn auto_reconnect_with_policy<T: AsyncRead + AsyncWrite + 'static + Send>(
+    client: Arc<Client>,
+    mut credential: Credential,
+    policy: ReconnectPolicy,
+    connector: impl Connector<T>,
+) {
+    let mut attempt = 0;
+    let mut start = Instant::now();
+    let mut use_fallback = false;
     loop {
         // 如果不是网络原因掉线，不重连（服务端强制下线/被踢下线/用户手动停止）
         if client.get_status() != (NetworkStatus::NetworkOffline as u8) {
@@ -30,44 +136,86 @@ pub async fn auto_reconnect<T: AsyncRead + AsyncWrite + 'static + Send>(
             break;
         }
         client.stop(NetworkStatus::NetworkOffline);
-        tracing::error!("client will reconnect after {} seconds", interval.as_secs());
-        tokio::time::sleep(interval).await;
-        let stream = if let Ok(stream) = connector.connect(&client).await {
-            count = 0;
-            stream
-        } else {
-            count += 1;
-            if count > max {
-                tracing::error!("reconnect_count: {}, break!", count);
-                break;
+        attempt += 1;
+        if policy.exhausted(attempt, start.elapsed()) {
+            tracing::error!("reconnect_count: {}, break!", attempt - 1);
+            client
+                .handler
+                .handle(QEvent::ReconnectGaveUp(ReconnectGaveUpEvent {
+                    client: client.clone(),
+                    inner: ReconnectGaveUp {
+                        attempts: attempt - 1,
+                        elapsed: start.elapsed(),
+                    },
+                }))
+                .await;
+            break;
+        }
+        let delay = policy.delay(attempt);
+        client
+            .handler
+            .handle(QEvent::Reconnecting(ReconnectingEvent {
+                client: client.clone(),
+                inner: Reconnecting { attempt, delay },
+            }))
+            .await;
+        tracing::error!(
+            "client will reconnect after {} ms, attempt: {}",
+            delay.as_millis(),
+            attempt
+        );
+        tokio::time::sleep(delay).await;
+        let stream = match connector.connect(&client).await {
+            Ok(stream) => stream,
+            Err(err) => {
+                tracing::error!("failed to connect: {}", err);
+                continue;
             }
-            continue;
         };
         let c = client.clone();
         let handle = tokio::spawn(async move { c.start(stream).await });
         tokio::task::yield_now().await; // 等一下，确保连上了
-        if let Err(err) = fast_login(&client, &credential).await {
+        let current = match (&policy.fallback, use_fallback) {
+            (Some(fallback), true) => fallback,
+            _ => &credential,
+        };
+        if let Err(err) = fast_login(&client, current).await {
             // token 可能过期了
             tracing::error!("failed to fast_login: {}", err);
             client.stop(NetworkStatus::NetworkOffline);
-            count += 1;
-            if count > max {
-                tracing::error!("reconnect_count: {}, break!", count);
-                break;
+            if policy.fallback.is_some() && !use_fallback {
+                tracing::warn!("fast_login will use fallback credential");
+                use_fallback = true;
             }
             continue;
         }
         tracing::info!("succeed to reconnect");
+        client
+            .handler
+            .handle(QEvent::Reconnected(ReconnectedEvent {
+                client: client.clone(),
+                inner: Reconnected { attempt },
+            }))
+            .await;
+        // 登录成功后刷新 token，下次掉线优先使用新的 token
+        if matches!(credential, Credential::Token(_)) {
+            credential = Credential::Token(client.gen_token().await);
+            use_fallback = false;
+        }
+        attempt = 0;
         after_login(&client).await;
         handle.await.ok();
+        start = Instant::now();
     }
 }
 
+#[derive(Clone)]
 pub struct Password {
     pub uin: i64,
     pub password: String,
 }
 
+#[derive(Clone)]
 pub enum Credential {
     Token(ricq_core::Token),
     Password(Password),
@@ -114,3 +262,50 @@ pub async fn fast_login(client: &Arc<Client>, credential: &Credential) -> RQResu
         Credential::Password(password) => password.fast_login(client).await,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let policy = ReconnectPolicy {
+            initial_interval: Duration::from_secs(1),
+            max_interval: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: 0.5,
+            max_elapsed_time: Some(Duration::from_secs(60)),
+            max_attempts: Some(5),
+            fallback: None,
+        };
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(3), Duration::from_secs(4));
+        assert_eq!(policy.backoff(5), Duration::from_secs(10));
+        assert_eq!(policy.backoff(usize::MAX), Duration::from_secs(10));
+        for _ in 0..100 {
+            let delay = policy.delay(3);
+            assert!(delay >= Duration::from_secs(2) && delay <= Duration::from_secs(6));
+        }
+        assert!(!policy.exhausted(5, Duration::from_secs(59)));
+        assert!(policy.exhausted(6, Duration::ZERO));
+        assert!(policy.exhausted(1, Duration::from_secs(60)));
+
+        let policy = ReconnectPolicy {
+            max_interval: Duration::MAX,
+            multiplier: 1e10,
+            ..Default::default()
+        };
+        assert_eq!(policy.backoff(1000), Duration::MAX);
+        for attempt in [1, 10, 100, 1000, usize::MAX] {
+            let _ = policy.delay(attempt);
+        }
+
+        for jitter in [f64::NAN, f64::INFINITY, -1.0] {
+            let policy = ReconnectPolicy {
+                jitter,
+                ..Default::default()
+            };
+            assert_eq!(policy.delay(1), policy.backoff(1));
+        }
+    }
+}
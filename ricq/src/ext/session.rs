@@ -0,0 +1,257 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use argon2::Argon2;
+use async_trait::async_trait;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+
+use ricq_core::protocol::device::Device;
+use ricq_core::protocol::version::Version;
+use ricq_core::Token;
+
+use crate::{Client, Config, RQError, RQResult};
+
+/// 登录会话，token 需要和签发时的设备、协议一起使用，否则会触发设备锁验证
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub token: Token,
+    pub device: Device,
+    pub version: Version,
+}
+
+impl Session {
+    pub async fn from_client(client: &Client) -> Self {
+        Self {
+            token: client.gen_token().await,
+            device: client.device().await,
+            version: client.version().await,
+        }
+    }
+
+    /// 使用会话中的设备和协议生成 Config，创建 Client 后调用 `token_login(session.token)` 恢复登录
+    pub fn config(&self) -> Config {
+        Config::new(self.device.clone(), self.version.clone())
+    }
+}
+
+/// 会话持久化，设置到 Client 后登录成功、刷新 token 后会自动保存
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    /// 没有保存过会话时返回 None
+    async fn load(&self) -> RQResult<Option<Session>>;
+
+    async fn save(&self, session: &Session) -> RQResult<()>;
+}
+
+const MAGIC: &[u8; 4] = b"RQS1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+/// 加密保存到文件
+///
+/// 文件格式为 `magic | salt | nonce | ciphertext`，密钥由 secret 和 salt 经 Argon2id 派生，
+/// 使用 XChaCha20-Poly1305 加密。写入时先写临时文件再 rename，不会留下写了一半的文件
+pub struct FileSessionStore {
+    path: PathBuf,
+    secret: Vec<u8>,
+    /// 缓存派生的密钥 (salt, key)，避免每次保存都重新计算
+    key: std::sync::Mutex<Option<([u8; SALT_LEN], [u8; 32])>>,
+}
+
+impl FileSessionStore {
+    pub fn new(path: impl AsRef<Path>, secret: impl AsRef<[u8]>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            secret: secret.as_ref().to_vec(),
+            key: Default::default(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    async fn derive_key(
+        &self,
+        salt: Option<[u8; SALT_LEN]>,
+    ) -> RQResult<([u8; SALT_LEN], [u8; 32])> {
+        if let Some((cached_salt, key)) = *self.key.lock().unwrap() {
+            if salt.is_none_or(|salt| salt == cached_salt) {
+                return Ok((cached_salt, key));
+            }
+        }
+        let salt = salt.unwrap_or_else(|| {
+            let mut salt = [0; SALT_LEN];
+            rand::thread_rng().fill_bytes(&mut salt);
+            salt
+        });
+        let secret = self.secret.clone();
+        let key = tokio::task::spawn_blocking(move || {
+            let mut key = [0; 32];
+            Argon2::default()
+                .hash_password_into(&secret, &salt, &mut key)
+                .map(|_| key)
+        })
+        .await
+        .map_err(|e| RQError::Other(format!("session key: {e}")))?
+        .map_err(|e| RQError::Other(format!("session key: {e}")))?;
+        *self.key.lock().unwrap() = Some((salt, key));
+        Ok((salt, key))
+    }
+
+    fn encrypt(salt: &[u8; SALT_LEN], key: &[u8; 32], plaintext: &[u8]) -> RQResult<Vec<u8>> {
+        let mut nonce = [0; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let mut out = MAGIC.to_vec();
+        out.extend_from_slice(salt);
+        let ciphertext = XChaCha20Poly1305::new(key.into())
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext,
+                    aad: &out,
+                },
+            )
+            .map_err(|_| RQError::Other("failed to encrypt session".into()))?;
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&ciphertext);
+        Ok(out)
+    }
+
+    // 临时文件名带 pid 和计数器，避免并发保存时互相覆盖
+    async fn write_atomic(&self, data: &[u8]) -> RQResult<()> {
+        static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(format!(
+            ".{}.{}.tmp",
+            std::process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let tmp = PathBuf::from(tmp);
+        let result = async {
+            let mut options = tokio::fs::OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            options.mode(0o600);
+            let mut file = options.open(&tmp).await?;
+            file.write_all(data).await?;
+            file.sync_all().await?;
+            drop(file);
+            tokio::fs::rename(&tmp, &self.path).await
+        }
+        .await;
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(&tmp).await;
+        }
+        Ok(result?)
+    }
+}
+
+#[async_trait]
+impl SessionStore for FileSessionStore {
+    async fn load(&self) -> RQResult<Option<Session>> {
+        let data = match tokio::fs::read(&self.path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if data.len() < MAGIC.len() + SALT_LEN + NONCE_LEN || !data.starts_with(MAGIC) {
+            return Err(RQError::Decode("invalid session file".into()));
+        }
+        let (aad, rest) = data.split_at(MAGIC.len() + SALT_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        let salt: [u8; SALT_LEN] = aad[MAGIC.len()..].try_into().unwrap();
+        let (_, key) = self.derive_key(Some(salt)).await?;
+        let plaintext = XChaCha20Poly1305::new(&key.into())
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad,
+                },
+            )
+            .map_err(|_| {
+                RQError::Decode("failed to decrypt session, wrong secret or corrupted file".into())
+            })?;
+        serde_json::from_slice(&plaintext)
+            .map(Some)
+            .map_err(|e| RQError::Decode(format!("session: {e}")))
+    }
+
+    async fn save(&self, session: &Session) -> RQResult<()> {
+        let plaintext =
+            serde_json::to_vec(session).map_err(|e| RQError::Other(format!("session: {e}")))?;
+        let (salt, key) = self.derive_key(None).await?;
+        let data = Self::encrypt(&salt, &key, &plaintext)?;
+        self.write_atomic(&data).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ricq_core::protocol::version::{get_version, Protocol};
+
+    use super::*;
+
+    fn session() -> Session {
+        Session {
+            token: Token {
+                uin: 10000,
+                d2: vec![1, 2, 3],
+                d2key: vec![4; 16],
+                tgt: vec![5],
+                srm_token: vec![],
+                t133: vec![],
+                encrypted_a1: vec![],
+                out_packet_session_id: vec![],
+                tgtgt_key: vec![6; 16],
+                wt_session_ticket_key: vec![],
+            },
+            device: Device::from_seed(10000),
+            version: get_version(Protocol::AndroidWatch),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_session_store() {
+        let path = std::env::temp_dir().join(format!("ricq-session-{}.bin", rand::random::<u64>()));
+        let store = FileSessionStore::new(&path, "secret");
+        assert!(store.load().await.unwrap().is_none());
+
+        store.save(&session()).await.unwrap();
+        let data = std::fs::read(&path).unwrap();
+        assert!(data.starts_with(MAGIC));
+        assert!(!data.windows(5).any(|w| w == b"10000"));
+
+        let loaded = FileSessionStore::new(&path, "secret")
+            .load()
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded.token.d2, vec![1, 2, 3]);
+        assert_eq!(loaded.device.imei, session().device.imei);
+        assert_eq!(loaded.version.app_id, session().version.app_id);
+
+        assert!(FileSessionStore::new(&path, "wrong").load().await.is_err());
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_save() {
+        let dir = std::env::temp_dir().join(format!("ricq-session-{}", rand::random::<u64>()));
+        std::fs::create_dir(&dir).unwrap();
+        let path = dir.join("session.bin");
+        let store = FileSessionStore::new(&path, "secret");
+        let session = session();
+        let results = futures_util::future::join_all((0..8).map(|_| store.save(&session))).await;
+        assert!(results.iter().all(|r| r.is_ok()));
+        assert_eq!(store.load().await.unwrap().unwrap().token.d2, vec![1, 2, 3]);
+        // 不留下临时文件
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
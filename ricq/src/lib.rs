@@ -5,6 +5,8 @@
 pub mod client;
 mod config;
 pub mod ext;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
 pub mod structs;
 
 pub use client::handler;
@@ -0,0 +1,147 @@
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use ricq_core::binary::{BinaryReader, BinaryWriter};
+use ricq_core::crypto::{qqtea_decrypt, qqtea_encrypt};
+use ricq_core::protocol::packet::{EncryptType, Packet, PacketType};
+use ricq_core::{RQError, RQResult};
+
+/// 单个连接使用的密钥，连接时从 Client 复制，登录成功后更新 d2key
+#[derive(Debug, Clone, Default)]
+pub(super) struct SessionKeys {
+    pub initial_share_key: Bytes,
+    pub tgtgt_key: Bytes,
+    pub d2key: Bytes,
+}
+
+/// 解析客户端发出的包（`Transport::encode_packet` 的逆过程），wtlogin 包会去掉 oicq 外壳
+pub(super) fn decode_request(mut r: Bytes, keys: &SessionKeys) -> RQResult<Packet> {
+    let packet_type = PacketType::from_i32(r.get_i32())?;
+    let encrypt_type = EncryptType::from_u8(r.get_u8())?;
+    let mut seq_id = 0;
+    match packet_type {
+        PacketType::Simple => seq_id = r.get_i32(),
+        PacketType::Login => {
+            let d2_len = r.get_i32() as usize - 4;
+            r.advance(d2_len);
+        }
+    }
+    r.get_u8();
+    let uin = r.read_string()?.parse().unwrap_or_default();
+
+    let mut body = match encrypt_type {
+        EncryptType::NoEncrypt => r,
+        EncryptType::D2Key => Bytes::from(qqtea_decrypt(&r, &keys.d2key)?),
+        EncryptType::EmptyKey => Bytes::from(qqtea_decrypt(&r, &[0; 16])?),
+    };
+    let head_len = body.get_i32() as usize - 4;
+    if head_len > body.remaining() {
+        return Err(RQError::PacketDropped);
+    }
+    let mut head = body.copy_to_bytes(head_len);
+    if packet_type == PacketType::Login {
+        seq_id = head.get_i32();
+        head.advance(4 + 4 + 12); // app_id, sub_app_id, 12 bytes
+        let tgt_len = head.get_i32() as usize - 4;
+        head.advance(tgt_len);
+    }
+    let command_name = head.read_string()?;
+
+    let body_len = (body.get_i32() as usize - 4).min(body.remaining());
+    let mut body = body.copy_to_bytes(body_len);
+    if command_name.starts_with("wtlogin.") {
+        body = decode_oicq_request(body, keys)?;
+    }
+    Ok(Packet {
+        packet_type,
+        encrypt_type,
+        seq_id,
+        body,
+        command_name,
+        uin,
+        ..Default::default()
+    })
+}
+
+/// 编码服务器回包（`Transport::decode_packet` 的逆过程），EmptyKey 的包会加上 oicq 外壳
+pub(super) fn encode_response(pkt: Packet, keys: &SessionKeys) -> Bytes {
+    let mut body = pkt.body;
+    if pkt.encrypt_type == EncryptType::EmptyKey {
+        body = encode_oicq_response(pkt.uin, &body, &keys.initial_share_key);
+    }
+
+    let mut head = BytesMut::new();
+    head.put_i32(pkt.seq_id);
+    head.put_i32(0); // ret_code
+    head.write_string(&pkt.message);
+    head.write_string(&pkt.command_name);
+    head.put_i32(4); // session_id
+    head.put_i32(0); // compress_flag
+
+    let mut frame = BytesMut::new();
+    frame.put_i32(head.len() as i32 + 4);
+    frame.put_slice(&head);
+    frame.put_i32(body.len() as i32 + 4);
+    frame.put_slice(&body);
+    let frame = match pkt.encrypt_type {
+        EncryptType::NoEncrypt => frame.freeze(),
+        EncryptType::D2Key => Bytes::from(qqtea_encrypt(&frame, &keys.d2key)),
+        EncryptType::EmptyKey => Bytes::from(qqtea_encrypt(&frame, &[0; 16])),
+    };
+
+    let mut w = BytesMut::new();
+    w.put_u32(pkt.packet_type.value());
+    w.put_u8(pkt.encrypt_type.value() as u8);
+    w.put_u8(0x00);
+    w.write_string(&pkt.uin.to_string());
+    w.put_slice(&frame);
+    w.freeze()
+}
+
+// oicq::Codec::encode 的逆过程
+fn decode_oicq_request(mut r: Bytes, keys: &SessionKeys) -> RQResult<Bytes> {
+    let flag = r.get_u8();
+    if flag != 2 {
+        return Err(RQError::UnknownFlag(flag));
+    }
+    r.advance(2 + 2 + 2 + 2 + 4); // len, version, command, 1, uin
+    r.get_u8();
+    let method = r.get_u8();
+    r.get_u8();
+    r.advance(12);
+    let key = match method {
+        0x87 => {
+            r.advance(2 + 16 + 2 + 2); // 0x02 0x01, random_key, 0x0131, public_key_ver
+            let public_key_len = r.get_u16() as usize;
+            r.advance(public_key_len);
+            keys.initial_share_key.clone()
+        }
+        0x45 => {
+            r.advance(2);
+            let random_key = r.copy_to_bytes(16);
+            r.advance(4);
+            random_key
+        }
+        _ => return Err(RQError::UnknownEncryptType),
+    };
+    let len = r.remaining().saturating_sub(1);
+    Ok(Bytes::from(qqtea_decrypt(&r[..len], &key)?))
+}
+
+// oicq::Codec::decode 的逆过程，encrypt_type 固定为 0
+fn encode_oicq_response(uin: i64, body: &[u8], initial_share_key: &[u8]) -> Bytes {
+    let mut w = BytesMut::new();
+    w.put_u8(0x02);
+    w.put_u16(0);
+    w.put_u16(8001);
+    w.put_u16(0x0810);
+    w.put_u16(1);
+    w.put_u32(uin as u32);
+    w.put_u8(0);
+    w.put_u8(0); // encrypt_type
+    w.put_u8(0);
+    w.encrypt_and_write(initial_share_key, body);
+    w.put_u8(0x03);
+    let len = w.len();
+    w[1..3].as_mut().put_u16(len as u16);
+    w.freeze()
+}
@@ -0,0 +1,1494 @@
+//! 进程内模拟服务器，用于离线测试 Client
+//!
+//! `MockServer` 实现了 [`Connector`]，通过内存管道与 Client 通信。
+//! 默认会应答 wtlogin / StatSvc.register / Heartbeat.Alive / MessageSvc.PbSendMsg /
+//! MessageSvc.PbGetMsg / MessageSvc.PbDeleteMsg，
+//! 可以用 [`MockServer::on`] 覆盖或添加其他命令的应答，用 [`MockServer::push`] 主动推送。
+//!
+//! ```ignore
+//! let server = MockServer::new();
+//! let client = Arc::new(Client::new(device, version, handler));
+//! let stream = server.connect(&client).await?;
+//! let c = client.clone();
+//! tokio::spawn(async move { c.start(stream).await });
+//! client.password_login(10000, "password").await?;
+//! ```
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::UNIX_EPOCH;
+
+use async_trait::async_trait;
+use bytes::{BufMut, Bytes, BytesMut};
+use futures_util::{SinkExt, StreamExt};
+use prost::Message;
+use tokio::io::{self, DuplexStream};
+use tokio::sync::broadcast;
+use tokio_util::codec::LengthDelimitedCodec;
+
+use ricq_core::command::common::PbToBytes;
+use ricq_core::crypto::qqtea_encrypt;
+use ricq_core::msg::MessageChain;
+use ricq_core::pb;
+use ricq_core::protocol::packet::{EncryptType, Packet};
+
+use crate::client::Connector;
+use crate::jce;
+use crate::Client;
+
+use codec::SessionKeys;
+
+mod codec;
+
+/// 命令应答函数，参数为解码后的请求包，返回 None 表示不回包
+pub type MockHandler = Arc<dyn Fn(&Packet) -> Option<Bytes> + Send + Sync>;
+
+/// 进程内模拟服务器
+#[derive(Clone, Default)]
+pub struct MockServer {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    handlers: RwLock<HashMap<String, MockHandler>>,
+    received: Mutex<Vec<Packet>>,
+    pushes: Pushes,
+    push_seq: AtomicI32,
+    group_seq: AtomicI32,
+    friend_seq: AtomicI32,
+    // 等待 MessageSvc.PbGetMsg 拉取的消息
+    sync_messages: Mutex<Vec<pb::msg::Message>>,
+}
+
+struct Pushes(broadcast::Sender<(String, Bytes)>);
+
+impl Default for Pushes {
+    fn default() -> Self {
+        Self(broadcast::channel(1024).0)
+    }
+}
+
+impl MockServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置命令应答，覆盖默认应答。wtlogin 命令返回的 body 会自动加上 oicq 外壳
+    pub fn on<F>(&self, command: &str, handler: F)
+    where
+        F: Fn(&Packet) -> Option<Bytes> + Send + Sync + 'static,
+    {
+        self.inner
+            .handlers
+            .write()
+            .unwrap()
+            .insert(command.to_owned(), Arc::new(handler));
+    }
+
+    /// 收到的所有客户端包（已解密，wtlogin 包已去掉 oicq 外壳）
+    pub fn received(&self) -> Vec<Packet> {
+        self.inner.received.lock().unwrap().clone()
+    }
+
+    /// 收到的指定命令的客户端包
+    pub fn received_by_command(&self, command: &str) -> Vec<Packet> {
+        self.received()
+            .into_iter()
+            .filter(|p| p.command_name == command)
+            .collect()
+    }
+
+    /// 向所有已连接的客户端推送，返回收到推送的连接数
+    pub fn push(&self, command: &str, body: Bytes) -> usize {
+        self.inner
+            .pushes
+            .0
+            .send((command.to_owned(), body))
+            .unwrap_or_default()
+    }
+
+    /// 推送好友消息：通过 MessageSvc.PushNotify 通知 Client 拉取，返回消息 seq
+    pub fn push_friend_message(
+        &self,
+        from_uin: i64,
+        to_uin: i64,
+        message_chain: MessageChain,
+    ) -> i32 {
+        let seq = self.inner.friend_seq.fetch_add(1, Ordering::Relaxed) + 1;
+        let msg = friend_message_pb(from_uin, to_uin, seq, rand::random(), message_chain.into());
+        self.push_sync_message(to_uin, msg);
+        seq
+    }
+
+    /// 推送群临时会话消息，同 [`MockServer::push_friend_message`]
+    pub fn push_temp_message(
+        &self,
+        group_code: i64,
+        from_uin: i64,
+        to_uin: i64,
+        message_chain: MessageChain,
+    ) -> i32 {
+        let seq = self.inner.friend_seq.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut msg =
+            friend_message_pb(from_uin, to_uin, seq, rand::random(), message_chain.into());
+        if let Some(head) = msg.head.as_mut() {
+            head.msg_type = Some(141);
+            head.c2c_tmp_msg_head = Some(pb::msg::C2cTempMessageHead {
+                group_code: Some(group_code),
+                ..Default::default()
+            });
+        }
+        self.push_sync_message(to_uin, msg);
+        seq
+    }
+
+    fn push_sync_message(&self, to_uin: i64, msg: pb::msg::Message) {
+        self.inner.sync_messages.lock().unwrap().push(msg);
+        self.push("MessageSvc.PushNotify", push_notify_packet(to_uin));
+    }
+
+    /// 推送群消息 OnlinePush.PbPushGroupMsg，返回消息 seq
+    pub fn push_group_message(
+        &self,
+        group_code: i64,
+        from_uin: i64,
+        message_chain: MessageChain,
+    ) -> i32 {
+        let seq = self.next_group_seq();
+        self.push(
+            "OnlinePush.PbPushGroupMsg",
+            group_message_packet(
+                group_code,
+                from_uin,
+                seq,
+                rand::random(),
+                message_chain.into(),
+            ),
+        );
+        seq
+    }
+
+    fn next_group_seq(&self) -> i32 {
+        self.inner.group_seq.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    async fn serve(self, stream: DuplexStream, mut keys: SessionKeys) {
+        let (mut write_half, mut read_half) = LengthDelimitedCodec::builder()
+            .length_field_length(4)
+            .length_adjustment(-4)
+            .new_framed(stream)
+            .split();
+        let mut pushes = self.inner.pushes.0.subscribe();
+        loop {
+            let output = tokio::select! {
+                input = read_half.next() => {
+                    let Some(Ok(input)) = input else { break };
+                    let pkt = match codec::decode_request(input.freeze(), &keys) {
+                        Ok(pkt) => pkt,
+                        Err(err) => {
+                            tracing::warn!("mock server failed to decode packet: {}", err);
+                            continue;
+                        }
+                    };
+                    self.inner.received.lock().unwrap().push(pkt.clone());
+                    match self.reply(&pkt, &mut keys) {
+                        Some(body) => Packet {
+                            encrypt_type: if pkt.command_name.starts_with("wtlogin.") {
+                                EncryptType::EmptyKey
+                            } else {
+                                EncryptType::NoEncrypt
+                            },
+                            body,
+                            ..pkt
+                        },
+                        None => continue,
+                    }
+                }
+                push = pushes.recv() => {
+                    let Ok((command_name, body)) = push else { break };
+                    Packet {
+                        seq_id: -self.inner.push_seq.fetch_add(1, Ordering::Relaxed) - 1,
+                        command_name,
+                        body,
+                        ..Default::default()
+                    }
+                }
+            };
+            if write_half
+                .send(codec::encode_response(output, &keys))
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+    }
+
+    fn reply(&self, pkt: &Packet, keys: &mut SessionKeys) -> Option<Bytes> {
+        let handler = self
+            .inner
+            .handlers
+            .read()
+            .unwrap()
+            .get(&pkt.command_name)
+            .cloned();
+        if let Some(handler) = handler {
+            return handler(pkt);
+        }
+        match pkt.command_name.as_ref() {
+            "wtlogin.login" | "wtlogin.exchange_emp" => Some(login_success(pkt, keys)),
+            "StatSvc.register" => Some(register_response("")),
+            "Heartbeat.Alive" => Some(Bytes::new()),
+            "MessageSvc.PbGetMsg" => {
+                let messages = std::mem::take(&mut *self.inner.sync_messages.lock().unwrap());
+                Some(
+                    pb::msg::GetMessageResponse {
+                        result: Some(0),
+                        sync_flag: Some(2),
+                        msg_rsp_type: Some(0),
+                        uin_pair_msgs: vec![pb::msg::UinPairMessage {
+                            messages,
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    }
+                    .to_bytes(),
+                )
+            }
+            "MessageSvc.PbDeleteMsg" => Some(Bytes::new()),
+            "MessageSvc.PbSendMsg" => {
+                self.echo_group_message(pkt);
+                Some(
+                    pb::msg::SendMessageResponse {
+                        result: Some(0),
+                        err_msg: None,
+                    }
+                    .to_bytes(),
+                )
+            }
+            _ => None,
+        }
+    }
+
+    // 服务器会把自己发的群消息推送回来，Client 依靠它获取 seq
+    fn echo_group_message(&self, pkt: &Packet) {
+        let Ok(req) = pb::msg::SendMessageRequest::decode(&*pkt.body) else {
+            return;
+        };
+        if let Some(pb::msg::RoutingHead {
+            routing_head: Some(pb::msg::routing_head::RoutingHead::Grp(grp)),
+        }) = req.routing_head
+        {
+            let elems = req
+                .msg_body
+                .and_then(|b| b.rich_text)
+                .map(|r| r.elems)
+                .unwrap_or_default();
+            self.push(
+                "OnlinePush.PbPushGroupMsg",
+                group_message_packet(
+                    grp.group_code.unwrap_or_default(),
+                    pkt.uin,
+                    self.next_group_seq(),
+                    req.msg_rand.unwrap_or_default(),
+                    elems,
+                ),
+            );
+        }
+    }
+}
+
+#[async_trait]
+impl Connector<DuplexStream> for MockServer {
+    async fn connect(&self, client: &Client) -> io::Result<DuplexStream> {
+        let keys = {
+            let engine = client.engine.read().await;
+            SessionKeys {
+                initial_share_key: engine.transport.oicq_codec.ecdh.initial_share_key.clone(),
+                tgtgt_key: engine.transport.sig.tgtgt_key.clone(),
+                d2key: engine.transport.sig.d2key.clone(),
+            }
+        };
+        let (local, remote) = tokio::io::duplex(64 * 1024);
+        tokio::spawn(self.clone().serve(remote, keys));
+        Ok(local)
+    }
+}
+
+// wtlogin 登录成功，签发新的 d2/d2key
+fn login_success(pkt: &Packet, keys: &mut SessionKeys) -> Bytes {
+    let sub_command = u16::from_be_bytes([pkt.body[0], pkt.body[1]]);
+    let encrypt_key = if sub_command == 11 {
+        md5::compute(&keys.d2key).to_vec()
+    } else {
+        keys.tgtgt_key.to_vec()
+    };
+    let d2key = Bytes::from(rand::random::<[u8; 16]>().to_vec());
+
+    let mut t119 = BytesMut::new();
+    t119.put_u16(6);
+    put_tlv(&mut t119, 0x143, format!("mock-d2-{}", pkt.uin).as_bytes());
+    put_tlv(&mut t119, 0x305, &d2key);
+    put_tlv(&mut t119, 0x10a, format!("mock-tgt-{}", pkt.uin).as_bytes());
+    put_tlv(&mut t119, 0x134, &rand::random::<[u8; 16]>());
+    put_tlv(&mut t119, 0x120, b"@mockskey");
+    put_tlv(&mut t119, 0x11a, &{
+        let nick = b"mock";
+        let mut w = BytesMut::new();
+        w.put_u16(0); // face
+        w.put_u8(18); // age
+        w.put_u8(0); // gender
+        w.put_u8(nick.len() as u8);
+        w.put_slice(nick);
+        w
+    });
+
+    let mut w = BytesMut::new();
+    w.put_u16(sub_command);
+    w.put_u8(0); // status
+    w.put_u16(1);
+    put_tlv(&mut w, 0x119, &qqtea_encrypt(&t119, &encrypt_key));
+    keys.d2key = d2key;
+    w.freeze()
+}
+
+/// StatSvc.register 回包，result 非空表示注册失败
+pub fn register_response(result: &str) -> Bytes {
+    let resp = jce::SvcRespRegister {
+        result: result.to_owned(),
+        ..Default::default()
+    };
+    let mut b = BytesMut::new();
+    b.put_slice(&[0x0A]);
+    b.put_slice(&jcers::JcePut::freeze(resp));
+    b.put_slice(&[0x0B]);
+    let buf = jce::RequestDataVersion2 {
+        map: HashMap::from([(
+            "SvcRespRegister".to_string(),
+            HashMap::from([("QQService.SvcRespRegister".to_string(), b.freeze())]),
+        )]),
+    };
+    let pkt = jce::RequestPacket {
+        i_version: 3,
+        s_servant_name: "PushService".to_string(),
+        s_func_name: "SvcRespRegister".to_string(),
+        s_buffer: jcers::JcePut::freeze(buf),
+        ..Default::default()
+    };
+    jcers::JcePut::freeze(pkt)
+}
+
+/// OnlinePush.ReqPush 推送
+pub fn online_push_req_packet(uin: i64, msg_infos: Vec<jce::PushMessageInfo>) -> Bytes {
+    let mut w = jcers::JceMut::new();
+    w.put_i64(uin, 0);
+    w.put_list(msg_infos, 2);
+    let mut b = BytesMut::new();
+    b.put_slice(&[0x0A]);
+    b.put_slice(&w.freeze());
+    b.put_slice(&[0x0B]);
+    let buf = jce::RequestDataVersion2 {
+        map: HashMap::from([(
+            "req".to_string(),
+            HashMap::from([("OnlinePushPack.SvcReqPushMsg".to_string(), b.freeze())]),
+        )]),
+    };
+    let pkt = jce::RequestPacket {
+        i_version: 3,
+        s_servant_name: "OnlinePush".to_string(),
+        s_func_name: "SvcReqPushMsg".to_string(),
+        s_buffer: jcers::JcePut::freeze(buf),
+        ..Default::default()
+    };
+    jcers::JcePut::freeze(pkt)
+}
+
+/// MessageSvc.PushNotify 推送，不带消息内容
+fn push_notify_packet(uin: i64) -> Bytes {
+    let notify = jce::RequestPushNotify {
+        uin,
+        msg_type: 166,
+        ..Default::default()
+    };
+    let mut b = BytesMut::new();
+    b.put_slice(&[0x0A]);
+    b.put_slice(&jcers::JcePut::freeze(notify));
+    b.put_slice(&[0x0B]);
+    let buf = jce::RequestDataVersion2 {
+        map: HashMap::from([(
+            "req_PushNotify".to_string(),
+            HashMap::from([("PushNotifyPack.RequestPushNotify".to_string(), b.freeze())]),
+        )]),
+    };
+    let pkt = jce::RequestPacket {
+        i_version: 3,
+        s_servant_name: "PushNotifyService".to_string(),
+        s_func_name: "PushNotify".to_string(),
+        s_buffer: jcers::JcePut::freeze(buf),
+        ..Default::default()
+    };
+    let mut w = BytesMut::new();
+    w.put_u32(0);
+    w.put_slice(&jcers::JcePut::freeze(pkt));
+    w.freeze()
+}
+
+fn friend_message_pb(
+    from_uin: i64,
+    to_uin: i64,
+    seq: i32,
+    rand: i32,
+    elems: Vec<pb::msg::Elem>,
+) -> pb::msg::Message {
+    pb::msg::Message {
+        head: Some(pb::msg::MessageHead {
+            from_uin: Some(from_uin),
+            to_uin: Some(to_uin),
+            msg_type: Some(166),
+            msg_seq: Some(seq),
+            msg_uid: Some(seq as i64),
+            msg_time: Some(UNIX_EPOCH.elapsed().unwrap().as_secs() as i32),
+            from_nick: Some("mock friend".into()),
+            ..Default::default()
+        }),
+        content: None,
+        body: Some(pb::msg::MessageBody {
+            rich_text: Some(pb::msg::RichText {
+                attr: Some(pb::msg::Attr {
+                    random: Some(rand),
+                    ..Default::default()
+                }),
+                elems,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+    }
+}
+
+fn group_message_packet(
+    group_code: i64,
+    from_uin: i64,
+    seq: i32,
+    rand: i32,
+    elems: Vec<pb::msg::Elem>,
+) -> Bytes {
+    pb::msg::PushMessagePacket {
+        message: Some(group_message_pb(group_code, from_uin, seq, rand, elems)),
+        ..Default::default()
+    }
+    .to_bytes()
+}
+
+fn group_message_pb(
+    group_code: i64,
+    from_uin: i64,
+    seq: i32,
+    rand: i32,
+    elems: Vec<pb::msg::Elem>,
+) -> pb::msg::Message {
+    pb::msg::Message {
+        head: Some(pb::msg::MessageHead {
+            from_uin: Some(from_uin),
+            msg_seq: Some(seq),
+            msg_time: Some(UNIX_EPOCH.elapsed().unwrap().as_secs() as i32),
+            group_info: Some(pb::msg::GroupInfo {
+                group_code: Some(group_code),
+                group_name: Some(b"mock group".to_vec()),
+                group_card: Some(Vec::new()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        content: Some(pb::msg::ContentHead {
+            pkg_num: Some(1),
+            pkg_index: Some(0),
+            div_seq: Some(seq),
+            ..Default::default()
+        }),
+        body: Some(pb::msg::MessageBody {
+            rich_text: Some(pb::msg::RichText {
+                attr: Some(pb::msg::Attr {
+                    random: Some(rand),
+                    ..Default::default()
+                }),
+                elems,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+    }
+}
+
+fn put_tlv(w: &mut BytesMut, tag: u16, value: &[u8]) {
+    w.put_u16(tag);
+    w.put_u16(value.len() as u16);
+    w.put_slice(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use ricq_core::msg::elem::Text;
+    use ricq_core::structs::GroupMessage;
+    use sha1::{Digest, Sha1};
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::client::NetworkStatus;
+    use crate::handler::QEvent;
+    use crate::version::Protocol;
+    use crate::RQError;
+    use crate::{Config, Device, LoginResponse};
+
+    type Events = mpsc::UnboundedReceiver<QEvent>;
+
+    /// 启动连接到 MockServer 的客户端，未登录
+    async fn setup_with_config(config: Config) -> (Arc<Client>, MockServer, Events) {
+        let server = MockServer::new();
+        let (tx, rx) = mpsc::unbounded_channel();
+        let client = Arc::new(Client::new_with_config(config, tx));
+        let stream = server.connect(&client).await.unwrap();
+        let c = client.clone();
+        tokio::spawn(async move { c.start(stream).await });
+        tokio::task::yield_now().await;
+        (client, server, rx)
+    }
+
+    async fn setup() -> (Arc<Client>, MockServer, Events) {
+        setup_with_config(Config::new(Device::random(), Protocol::IPad.into())).await
+    }
+
+    /// 等待第一个 f 返回 Some 的事件
+    async fn wait_event<T>(rx: &mut Events, mut f: impl FnMut(QEvent) -> Option<T>) -> T {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Some(t) = f(rx.recv().await.expect("event channel closed")) {
+                    return t;
+                }
+            }
+        })
+        .await
+        .expect("wait event timeout")
+    }
+
+    #[tokio::test]
+    async fn test_login_and_group_message() {
+        let (client, server, mut rx) = setup().await;
+
+        let resp = client.password_login(10000, "password").await.unwrap();
+        assert!(matches!(resp, LoginResponse::Success(_)));
+        assert_eq!(client.account_info.read().await.nickname, "mock");
+        client.register_client().await.unwrap();
+        assert!(client.online.load(Ordering::Relaxed));
+        client.heartbeat().await.unwrap();
+
+        let receipt = client
+            .send_group_message(1234, MessageChain::new(Text::new("hello".into())))
+            .await
+            .unwrap();
+        assert_ne!(receipt.seqs, vec![0]);
+        let sent = server.received_by_command("MessageSvc.PbSendMsg");
+        assert_eq!(sent.len(), 1);
+        let req = pb::msg::SendMessageRequest::decode(&*sent[0].body).unwrap();
+        assert_eq!(req.msg_rand, receipt.rands.first().copied());
+
+        server.push_group_message(1234, 20000, MessageChain::new(Text::new("hi".into())));
+        let event = wait_event(&mut rx, |e| match e {
+            QEvent::GroupMessage(e) => Some(e),
+            _ => None,
+        })
+        .await;
+        assert_eq!(event.inner.group_code, 1234);
+        assert_eq!(event.inner.from_uin, 20000);
+        assert_eq!(event.inner.elements.to_string().trim(), "hi");
+    }
+
+    #[tokio::test]
+    async fn test_scripted_handler() {
+        let (client, server, _rx) = setup().await;
+        server.on("StatSvc.register", |_| Some(register_response("denied")));
+
+        client.password_login(10000, "password").await.unwrap();
+        assert!(client.register_client().await.is_err());
+        assert_eq!(server.received_by_command("StatSvc.register").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout_and_disconnect() {
+        let (client, server, _rx) = setup().await;
+        server.on("Heartbeat.Alive", |_| None);
+
+        let result = client
+            .with_request_timeout(Duration::from_millis(100), client.heartbeat())
+            .await;
+        assert!(matches!(result, Err(RQError::Timeout)));
+
+        let c = client.clone();
+        let pending = tokio::spawn(async move { c.heartbeat().await });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        client.stop(NetworkStatus::Stop);
+        let result = tokio::time::timeout(Duration::from_secs(1), pending)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(result, Err(RQError::Network)));
+    }
+
+    #[tokio::test]
+    async fn test_get_group_messages() {
+        let (client, server, _rx) = setup().await;
+        server.on("MessageSvc.PbGetGroupMsg", |pkt| {
+            let req = pb::msg::GetGroupMsgReq::decode(&*pkt.body).ok()?;
+            let (begin, end) = (req.begin_seq? as i32, req.end_seq? as i32);
+            // seq 2 是撤回的消息，seq 3、4 是同一条消息的两个分片，seq 6 缺少第二个分片
+            let msg = |seq: i32, text: &str, pkg: Option<(i32, i32)>| {
+                let mut m = group_message_pb(
+                    1234,
+                    20000,
+                    seq,
+                    seq,
+                    MessageChain::new(Text::new(text.into())).into(),
+                );
+                if let Some((pkg_num, pkg_index)) = pkg {
+                    m.content = Some(pb::msg::ContentHead {
+                        pkg_num: Some(pkg_num),
+                        pkg_index: Some(pkg_index),
+                        div_seq: Some(100 + seq - pkg_index),
+                        ..Default::default()
+                    });
+                }
+                if seq == 2 {
+                    m.body = None;
+                }
+                m
+            };
+            let all = vec![
+                msg(1, "first", None),
+                msg(2, "recalled", None),
+                msg(3, "long ", Some((2, 0))),
+                msg(4, "message", Some((2, 1))),
+                msg(5, "last", None),
+                msg(6, "broken", Some((2, 0))),
+            ];
+            Some(
+                pb::msg::GetGroupMsgResp {
+                    result: Some(0),
+                    group_code: Some(1234),
+                    return_begin_seq: Some(begin as u64),
+                    return_end_seq: Some(end as u64),
+                    msg: all
+                        .into_iter()
+                        .filter(|m| {
+                            let seq = m.head.as_ref().unwrap().msg_seq.unwrap();
+                            begin <= seq && seq <= end
+                        })
+                        .collect(),
+                    ..Default::default()
+                }
+                .to_bytes(),
+            )
+        });
+        client.password_login(10000, "password").await.unwrap();
+
+        let text = |m: &GroupMessage| {
+            m.elements
+                .to_string()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let msgs = client.get_group_messages(1234, 1, 5).await.unwrap();
+        assert_eq!(msgs.len(), 3);
+        assert_eq!(msgs[1].seqs, vec![3, 4]);
+        assert_eq!(text(&msgs[1]), "long message");
+        // 范围截断的分片消息会获取其他分片
+        let msgs = client.get_group_messages(1234, 4, 4).await.unwrap();
+        assert_eq!(msgs[0].seqs, vec![3, 4]);
+        assert!(client.get_group_messages(1234, 5, 6).await.is_err());
+
+        let reply = client.get_group_message(1234, 5).await.unwrap().unwrap();
+        assert_eq!(text(&reply), "last");
+        // seq 指向第二个分片
+        let reply = client.get_group_message(1234, 4).await.unwrap().unwrap();
+        assert_eq!(reply.seqs, vec![3, 4]);
+        assert_eq!(text(&reply), "long message");
+        assert!(client.get_group_message(1234, 6).await.is_err());
+        assert!(client.get_group_message(1234, 7).await.unwrap().is_none());
+
+        let mut pager = client.group_message_pager(1234, 1, 5).page_size(3);
+        let mut pages = Vec::new();
+        while let Some(page) = pager.next_page().await.unwrap() {
+            pages.push(page.into_iter().map(|m| text(&m)).collect::<Vec<_>>());
+        }
+        assert_eq!(
+            pages,
+            vec![
+                vec!["first".to_string()],
+                vec!["long message".into(), "last".into()]
+            ]
+        );
+
+        // 一页中只有不完整的分片消息时扩大范围
+        let mut pager = client.group_message_pager(1234, 1, 5).page_size(1);
+        let mut pages = Vec::new();
+        while let Some(page) = pager.next_page().await.unwrap() {
+            pages.push(page.into_iter().map(|m| text(&m)).collect::<Vec<_>>());
+        }
+        assert_eq!(
+            pages,
+            vec![
+                vec!["first".to_string()],
+                vec![],
+                vec!["long message".into()],
+                vec!["last".into()]
+            ]
+        );
+
+        // 无法获取全部分片时返回错误并跳过这条消息
+        let mut pager = client.group_message_pager(1234, 6, 7).page_size(1);
+        assert!(pager.next_page().await.is_err());
+        assert_eq!(pager.next_seq(), 7);
+        assert_eq!(pager.next_page().await.unwrap().unwrap().len(), 0);
+        assert!(pager.next_page().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_self_group_message() {
+        let config =
+            Config::new(Device::random(), Protocol::IPad.into()).with_self_message_events(true);
+        let (client, server, mut rx) = setup_with_config(config).await;
+        client.password_login(10000, "password").await.unwrap();
+
+        // 本客户端发送的消息只用于回执，不产生事件
+        client
+            .send_group_message(1234, MessageChain::new(Text::new("mine".into())))
+            .await
+            .unwrap();
+        // 其他设备发送的消息
+        server.push_group_message(1234, 10000, MessageChain::new(Text::new("phone".into())));
+        let event = wait_event(&mut rx, |e| match e {
+            QEvent::SelfGroupMessage(e) => Some(e),
+            QEvent::GroupMessage(e) => panic!("unexpected group message {e:?}"),
+            _ => None,
+        })
+        .await;
+        assert_eq!(event.inner.from_uin, 10000);
+        assert_eq!(event.inner.elements.to_string().trim(), "phone");
+    }
+
+    #[tokio::test]
+    async fn test_self_group_message_default() {
+        let (client, server, mut rx) = setup().await;
+        client.password_login(10000, "password").await.unwrap();
+
+        // 默认不区分其他设备发送的消息
+        server.push_group_message(1234, 10000, MessageChain::new(Text::new("phone".into())));
+        let event = wait_event(&mut rx, |e| match e {
+            QEvent::GroupMessage(e) => Some(e),
+            QEvent::SelfGroupMessage(e) => panic!("unexpected self group message {e:?}"),
+            _ => None,
+        })
+        .await;
+        assert_eq!(event.inner.from_uin, 10000);
+        assert_eq!(event.inner.elements.to_string().trim(), "phone");
+    }
+
+    #[tokio::test]
+    async fn test_self_friend_message() {
+        let config =
+            Config::new(Device::random(), Protocol::IPad.into()).with_self_message_events(true);
+        let (client, server, mut rx) = setup_with_config(config).await;
+        client.password_login(10000, "password").await.unwrap();
+
+        server.push_friend_message(20000, 10000, MessageChain::new(Text::new("hi".into())));
+        let event = wait_event(&mut rx, |e| match e {
+            QEvent::FriendMessage(e) => Some(e),
+            QEvent::SelfFriendMessage(e) => panic!("unexpected self friend message {e:?}"),
+            _ => None,
+        })
+        .await;
+        assert_eq!(event.inner.from_uin, 20000);
+        assert_eq!(event.inner.elements.to_string().trim(), "hi");
+
+        // 其他设备发给好友的消息
+        server.push_friend_message(10000, 20000, MessageChain::new(Text::new("phone".into())));
+        let event = wait_event(&mut rx, |e| match e {
+            QEvent::SelfFriendMessage(e) => Some(e),
+            QEvent::FriendMessage(e) => panic!("unexpected friend message {e:?}"),
+            _ => None,
+        })
+        .await;
+        assert_eq!(event.inner.from_uin, 10000);
+        assert_eq!(event.inner.target, 20000);
+        assert_eq!(event.inner.elements.to_string().trim(), "phone");
+    }
+
+    #[tokio::test]
+    async fn test_self_friend_audio_message() {
+        let config =
+            Config::new(Device::random(), Protocol::IPad.into()).with_self_message_events(true);
+        let (client, server, mut rx) = setup_with_config(config).await;
+        client.password_login(10000, "password").await.unwrap();
+
+        // 其他设备发给好友的语音
+        let mut msg = friend_message_pb(10000, 20000, 1, rand::random(), Vec::new());
+        if let Some(rich_text) = msg.body.as_mut().and_then(|b| b.rich_text.as_mut()) {
+            rich_text.ptt = Some(pb::msg::Ptt {
+                file_name: Some("a.amr".into()),
+                ..Default::default()
+            });
+        }
+        server.push_sync_message(10000, msg);
+        let event = wait_event(&mut rx, |e| match e {
+            QEvent::FriendAudioMessage(e) => Some(e),
+            _ => None,
+        })
+        .await;
+        assert_eq!(event.inner.from_uin, 10000);
+        assert_eq!(event.inner.target, 20000);
+    }
+
+    #[tokio::test]
+    async fn test_group_message_reply() {
+        let (client, server, mut rx) = setup().await;
+        client.password_login(10000, "password").await.unwrap();
+
+        server.push_group_message(1234, 20000, MessageChain::new(Text::new("ping".into())));
+        let event = wait_event(&mut rx, |e| match e {
+            QEvent::GroupMessage(e) => Some(e),
+            _ => None,
+        })
+        .await;
+        event
+            .reply_with_at(MessageChain::new(Text::new("pong".into())))
+            .await
+            .unwrap();
+
+        let sent = server.received_by_command("MessageSvc.PbSendMsg");
+        let req = pb::msg::SendMessageRequest::decode(&*sent[0].body).unwrap();
+        let chain = MessageChain::from(req.msg_body.unwrap().rich_text.unwrap().elems);
+        let reply = chain.reply().unwrap();
+        assert_eq!(reply.reply_seq, event.inner.seqs[0]);
+        assert_eq!(reply.rand, event.inner.rands[0]);
+        assert_eq!(reply.sender, 20000);
+        assert_eq!(reply.elements.to_string().trim(), "ping");
+        assert_eq!(chain.mentions(), vec![20000]);
+        assert_eq!(chain.plain_text(), " pong");
+    }
+
+    // 解析发出的 PbSendMsg，返回 routing_head 和消息链
+    fn sent_message(server: &MockServer) -> (pb::msg::routing_head::RoutingHead, MessageChain) {
+        let sent = server.received_by_command("MessageSvc.PbSendMsg");
+        let req = pb::msg::SendMessageRequest::decode(&*sent.last().unwrap().body).unwrap();
+        let chain = MessageChain::from(req.msg_body.unwrap().rich_text.unwrap().elems);
+        (req.routing_head.unwrap().routing_head.unwrap(), chain)
+    }
+
+    #[tokio::test]
+    async fn test_friend_message_reply() {
+        let (client, server, mut rx) = setup().await;
+        client.password_login(10000, "password").await.unwrap();
+
+        server.push_friend_message(20000, 10000, MessageChain::new(Text::new("ping".into())));
+        let event = wait_event(&mut rx, |e| match e {
+            QEvent::FriendMessage(e) => Some(e),
+            _ => None,
+        })
+        .await;
+        event
+            .reply(MessageChain::new(Text::new("pong".into())))
+            .await
+            .unwrap();
+
+        let (routing_head, chain) = sent_message(&server);
+        assert!(matches!(
+            routing_head,
+            pb::msg::routing_head::RoutingHead::C2c(pb::msg::C2c {
+                to_uin: Some(20000)
+            })
+        ));
+        let reply = chain.reply().unwrap();
+        assert_eq!(reply.reply_seq, event.inner.seqs[0]);
+        assert_eq!(reply.time, event.inner.time);
+        assert_eq!(reply.sender, 20000);
+        assert_eq!(reply.elements.to_string().trim(), "ping");
+        assert_eq!(chain.plain_text(), "pong");
+    }
+
+    #[tokio::test]
+    async fn test_temp_message_reply() {
+        let (client, server, mut rx) = setup().await;
+        client.password_login(10000, "password").await.unwrap();
+
+        server.push_temp_message(
+            1234,
+            20000,
+            10000,
+            MessageChain::new(Text::new("ping".into())),
+        );
+        let event = wait_event(&mut rx, |e| match e {
+            QEvent::GroupTempMessage(e) => Some(e),
+            _ => None,
+        })
+        .await;
+        assert_eq!(event.inner.group_code, 1234);
+        event
+            .reply(MessageChain::new(Text::new("pong".into())))
+            .await
+            .unwrap();
+
+        let (routing_head, chain) = sent_message(&server);
+        let pb::msg::routing_head::RoutingHead::GrpTmp(tmp) = routing_head else {
+            panic!("unexpected routing head {routing_head:?}");
+        };
+        assert_eq!(tmp.to_uin, Some(20000));
+        assert_eq!(tmp.group_uin, Some(ricq_core::common::group_code2uin(1234)));
+        let reply = chain.reply().unwrap();
+        assert_eq!(reply.reply_seq, event.inner.seqs[0]);
+        assert_eq!(reply.time, event.inner.time);
+        assert_eq!(reply.sender, 20000);
+        assert_eq!(reply.elements.to_string().trim(), "ping");
+        assert_eq!(chain.plain_text(), "pong");
+    }
+
+    #[tokio::test]
+    async fn test_member_card_update() {
+        let (client, server, mut rx) = setup().await;
+        client.password_login(10000, "password").await.unwrap();
+
+        for (seq, card) in [(1, "old"), (2, "old"), (3, "new")] {
+            let mut msg = group_message_pb(1234, 20000, seq, seq, Vec::new());
+            if let Some(info) = msg.head.as_mut().and_then(|h| h.group_info.as_mut()) {
+                info.group_card = Some(card.as_bytes().to_vec());
+            }
+            server.push(
+                "OnlinePush.PbPushGroupMsg",
+                pb::msg::PushMessagePacket {
+                    message: Some(msg),
+                    ..Default::default()
+                }
+                .to_bytes(),
+            );
+        }
+        let mut updates = Vec::new();
+        let mut messages = 0;
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while messages < 3 {
+                match rx.recv().await {
+                    Some(QEvent::GroupMessage(_)) => messages += 1,
+                    Some(QEvent::MemberCardUpdate(e)) => updates.push(e.inner),
+                    _ => {}
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].member_uin, 20000);
+        assert_eq!(updates[0].old_card.as_deref(), Some("old"));
+        assert_eq!(updates[0].new_card, "new");
+    }
+
+    #[tokio::test]
+    async fn test_friend_input_status() {
+        let (client, server, mut rx) = setup().await;
+        client.password_login(10000, "password").await.unwrap();
+
+        for (seq, event_type) in [(1, 1), (2, 2)] {
+            let s115 = pb::Sub115 {
+                from_uin: 20000,
+                to_uin: 10000,
+                msg_notify_item: Some(pb::Sub115NotifyItem {
+                    event_type,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            let v_msg = jcers::JcePut::freeze(jce::MsgType0x210 {
+                sub_msg_type: 0x115,
+                v_protobuf: s115.to_bytes(),
+            });
+            let info = jce::PushMessageInfo {
+                from_uin: 20000,
+                msg_type: 528,
+                msg_seq: seq,
+                msg_uid: seq as i64,
+                msg_time: UNIX_EPOCH.elapsed().unwrap().as_secs() as i64,
+                v_msg,
+                ..Default::default()
+            };
+            server.push(
+                "OnlinePush.ReqPush",
+                online_push_req_packet(10000, vec![info]),
+            );
+            let status = wait_event(&mut rx, |e| match e {
+                QEvent::FriendInputStatus(e) => Some(e.inner),
+                _ => None,
+            })
+            .await;
+            assert_eq!(status.uin, 20000);
+            assert_eq!(status.typing, event_type == 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decode_error() {
+        let (client, server, mut rx) = setup_with_config(
+            Config::new(Device::random(), Protocol::IPad.into()).with_report_decode_error(true),
+        )
+        .await;
+        client.password_login(10000, "password").await.unwrap();
+
+        // 截断的群成员退出推送
+        let body = pb::msg::TransMsgInfo {
+            from_uin: Some(1234),
+            msg_type: Some(34),
+            msg_data: Some(vec![0, 0, 1]),
+            ..Default::default()
+        }
+        .to_bytes();
+        server.push("OnlinePush.PbPushTransMsg", body.clone());
+        let err = wait_event(&mut rx, |e| match e {
+            QEvent::DecodeError(e) => Some(e.inner),
+            _ => None,
+        })
+        .await;
+        assert_eq!(err.command, "OnlinePush.PbPushTransMsg");
+        assert_eq!(err.raw, body);
+    }
+
+    #[tokio::test]
+    async fn test_session_auto_save() {
+        use crate::ext::session::{Session, SessionStore};
+
+        #[derive(Default)]
+        struct MemoryStore(std::sync::Mutex<Vec<Session>>);
+
+        #[async_trait::async_trait]
+        impl SessionStore for MemoryStore {
+            async fn load(&self) -> crate::RQResult<Option<Session>> {
+                Ok(self.0.lock().unwrap().last().cloned())
+            }
+
+            async fn save(&self, session: &Session) -> crate::RQResult<()> {
+                self.0.lock().unwrap().push(session.clone());
+                Ok(())
+            }
+        }
+
+        let store = Arc::new(MemoryStore::default());
+        let (client, _server, _rx) = setup_with_config(
+            Config::new(Device::from_seed(10000), Protocol::AndroidWatch.into())
+                .with_session_store(store.clone()),
+        )
+        .await;
+
+        client.password_login(10000, "password").await.unwrap();
+        let session = store.load().await.unwrap().unwrap();
+        assert_eq!(session.token.uin, 10000);
+        assert_eq!(session.token.d2, client.gen_token().await.d2);
+        assert_eq!(session.device.imei, Device::from_seed(10000).imei);
+
+        client.request_change_sig(None).await.unwrap();
+        assert_eq!(store.0.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_sig_refresh() {
+        let (client, server, mut rx) = setup_with_config(
+            Config::new(Device::random(), Protocol::IPad.into()).with_sig_refresh(Some(
+                crate::client::SigRefreshConfig {
+                    check_interval: Duration::from_millis(50),
+                    s_key_margin: Duration::ZERO,
+                    d2_max_age: Duration::from_secs(3600),
+                },
+            )),
+        )
+        .await;
+        client.password_login(10000, "password").await.unwrap();
+        client.register_client().await.unwrap();
+
+        // s_key 已过期
+        client.engine.write().await.transport.sig.s_key_expired_time = 1;
+        crate::ext::common::start_sig_refresh(client.clone()).await;
+        let event = wait_event(&mut rx, |e| match e {
+            QEvent::SigRefreshed(e) => Some(e.inner),
+            _ => None,
+        })
+        .await;
+        assert_eq!(event.token.uin, 10000);
+        assert_eq!(server.received_by_command("wtlogin.exchange_emp").len(), 1);
+        assert_eq!(server.received_by_command("StatSvc.register").len(), 2);
+        assert!(client.engine.read().await.transport.sig.s_key_expired_time > 1);
+        client.set_sig_refresh(None).await;
+    }
+
+    type HighwayFrames = Arc<Mutex<Vec<(pb::ReqDataHighwayHead, Bytes)>>>;
+
+    // 模拟 highway 服务器，记录收到的帧并逐个应答成功
+    async fn spawn_highway() -> (std::net::SocketAddr, HighwayFrames) {
+        use crate::client::highway::codec::HighwayCodec;
+        use crate::client::highway::HighwayFrame;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let frames = HighwayFrames::default();
+        let recorded = frames.clone();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = tokio_util::codec::Framed::new(stream, HighwayCodec);
+            while let Some(Ok(frame)) = stream.next().await {
+                let head = pb::ReqDataHighwayHead::decode(&*frame.head).unwrap();
+                recorded.lock().unwrap().push((head, frame.body));
+                let rsp = pb::RspDataHighwayHead::default();
+                let reply = HighwayFrame {
+                    head: rsp.to_bytes(),
+                    body: Bytes::new(),
+                };
+                if stream.send(reply).await.is_err() {
+                    break;
+                }
+            }
+        });
+        (addr, frames)
+    }
+
+    // 检查 highway 帧：先 echo，再按顺序分块上传整个文件
+    fn assert_highway_upload(frames: &HighwayFrames, command_id: i32, data: &[u8]) {
+        let frames = frames.lock().unwrap();
+        let echo = frames[0].0.msg_basehead.as_ref().unwrap();
+        assert_eq!(echo.command, "PicUp.Echo");
+        assert_eq!(frames.len(), 1 + data.len().div_ceil(256 * 1024));
+        let md5 = md5::compute(data).to_vec();
+        let mut offset = 0;
+        for (head, body) in &frames[1..] {
+            assert_eq!(head.msg_basehead.as_ref().unwrap().command_id, command_id);
+            let seghead = head.msg_seghead.as_ref().unwrap();
+            assert_eq!(seghead.filesize, data.len() as i64);
+            assert_eq!(seghead.dataoffset, offset as i64);
+            assert_eq!(seghead.file_md5, md5);
+            assert_eq!(&data[offset..offset + body.len()], &body[..]);
+            offset += body.len();
+        }
+        assert_eq!(offset, data.len());
+    }
+
+    fn oidb_response(body: Bytes) -> Bytes {
+        pb::oidb::OidbssoPkg {
+            bodybuffer: body.to_vec(),
+            ..Default::default()
+        }
+        .to_bytes()
+    }
+
+    fn test_file_data() -> Vec<u8> {
+        (0..600 * 1024).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[tokio::test]
+    async fn test_upload_group_file() {
+        let (client, server, _rx) = setup().await;
+        client.password_login(10000, "password").await.unwrap();
+        let (addr, frames) = spawn_highway().await;
+        server.on("OidbSvc.0x6d6_0", move |_| {
+            let rsp = pb::oidb::D6d6RspBody {
+                upload_file_rsp: Some(pb::oidb::UploadFileRspBody {
+                    ret_code: Some(0),
+                    upload_ip: Some(addr.ip().to_string()),
+                    upload_port: Some(addr.port() as i32),
+                    file_id: Some("/abc".into()),
+                    bus_id: Some(102),
+                    check_key: Some(vec![1, 2, 3]),
+                    bool_file_exist: Some(false),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            Some(oidb_response(rsp.to_bytes()))
+        });
+        server.on("OidbSvc.0x6d9_4", |_| {
+            let rsp = pb::oidb::D6d9RspBody {
+                feeds_info_rsp: Some(pb::oidb::FeedsRspBody {
+                    ret_code: Some(0),
+                    ..Default::default()
+                }),
+            };
+            Some(oidb_response(rsp.to_bytes()))
+        });
+
+        let data = test_file_data();
+        let upload = client
+            .upload_group_file(1234, "/", "a.bin", std::io::Cursor::new(data.clone()))
+            .await
+            .unwrap();
+        assert_eq!(upload.file_id, "/abc");
+        assert_eq!(upload.bus_id, 102);
+
+        let sent = server.received_by_command("OidbSvc.0x6d6_0");
+        let pkg = pb::oidb::OidbssoPkg::decode(&*sent[0].body).unwrap();
+        let req = pb::oidb::D6d6ReqBody::decode(&*pkg.bodybuffer)
+            .unwrap()
+            .upload_file_req
+            .unwrap();
+        assert_eq!(req.int64_file_size(), data.len() as i64);
+        assert_eq!(req.md5(), md5::compute(&data).0);
+        assert_eq!(req.sha(), Sha1::digest(&data).to_vec());
+        assert_highway_upload(&frames, 71, &data);
+
+        let sent = server.received_by_command("OidbSvc.0x6d9_4");
+        let pkg = pb::oidb::OidbssoPkg::decode(&*sent[0].body).unwrap();
+        let req = pb::oidb::D6d9ReqBody::decode(&*pkg.bodybuffer)
+            .unwrap()
+            .feeds_info_req
+            .unwrap();
+        assert_eq!(req.group_code(), 1234);
+        assert_eq!(req.feeds_info_list[0].file_id(), "/abc");
+    }
+
+    #[tokio::test]
+    async fn test_send_friend_file() {
+        let (client, server, _rx) = setup().await;
+        client.password_login(10000, "password").await.unwrap();
+        let (addr, frames) = spawn_highway().await;
+        server.on(
+            "OfflineFilleHandleSvr.pb_ftn_CMD_REQ_APPLY_UPLOAD_V3-1700",
+            move |_| {
+                let rsp = pb::cmd0x346::C346RspBody {
+                    apply_upload_rsp_v3: Some(pb::cmd0x346::ApplyUploadRspV3 {
+                        upload_ip: addr.ip().to_string(),
+                        upload_port: addr.port() as i32,
+                        uuid: b"uuid".to_vec(),
+                        upload_key: vec![1, 2, 3],
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                };
+                Some(rsp.to_bytes())
+            },
+        );
+        server.on(
+            "OfflineFilleHandleSvr.pb_ftn_CMD_REQ_UPLOAD_SUCC-800",
+            |_| {
+                let rsp = pb::cmd0x346::C346RspBody {
+                    upload_succ_rsp: Some(Default::default()),
+                    ..Default::default()
+                };
+                Some(rsp.to_bytes())
+            },
+        );
+
+        let data = test_file_data();
+        client
+            .send_friend_file(20000, "a.bin", std::io::Cursor::new(data.clone()))
+            .await
+            .unwrap();
+
+        let sent =
+            server.received_by_command("OfflineFilleHandleSvr.pb_ftn_CMD_REQ_APPLY_UPLOAD_V3-1700");
+        let req = pb::cmd0x346::C346ReqBody::decode(&*sent[0].body)
+            .unwrap()
+            .apply_upload_req_v3
+            .unwrap();
+        assert_eq!(req.recver_uin, 20000);
+        assert_eq!(req.file_size, data.len() as i64);
+        // 小于 10 MiB 时和整个文件的 md5 相同
+        assert_eq!(req.bytes_10m_md5, md5::compute(&data).0);
+        assert_eq!(req.sha, Sha1::digest(&data).to_vec());
+        assert_highway_upload(&frames, 69, &data);
+
+        let sent = server.received_by_command("MessageSvc.PbSendMsg");
+        let req = pb::msg::SendMessageRequest::decode(&*sent[0].body).unwrap();
+        let content = req.msg_body.unwrap().msg_content.unwrap();
+        let file = pb::msg::SubMsgType0x4Body::decode(&*content)
+            .unwrap()
+            .not_online_file
+            .unwrap();
+        assert_eq!(file.file_uuid(), b"uuid");
+        assert_eq!(file.file_name(), b"a.bin");
+        assert_eq!(file.file_size(), data.len() as i64);
+    }
+
+    fn friend_file_message_pb(from_uin: i64, to_uin: i64) -> pb::msg::Message {
+        let mut msg = friend_message_pb(from_uin, to_uin, 1, 0, Vec::new());
+        if let Some(head) = msg.head.as_mut() {
+            head.msg_type = Some(529);
+            head.c2c_cmd = Some(4);
+        }
+        let content = pb::msg::SubMsgType0x4Body {
+            not_online_file: Some(pb::msg::NotOnlineFile {
+                file_name: Some(b"a.bin".to_vec()),
+                file_size: Some(1024),
+                file_uuid: Some(b"uuid".to_vec()),
+                file_md5: Some(vec![1; 16]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        msg.body = Some(pb::msg::MessageBody {
+            msg_content: Some(content.encode_to_vec()),
+            ..Default::default()
+        });
+        msg
+    }
+
+    #[tokio::test]
+    async fn test_friend_file_message() {
+        let (client, server, mut rx) = setup().await;
+        client.password_login(10000, "password").await.unwrap();
+
+        server.push_sync_message(10000, friend_file_message_pb(20000, 10000));
+
+        let event = wait_event(&mut rx, |e| match e {
+            QEvent::FriendFileMessage(e) => Some(e),
+            _ => None,
+        })
+        .await;
+        assert_eq!(event.inner.from_uin, 20000);
+        assert_eq!(event.inner.target, 10000);
+        assert_eq!(event.inner.file.name, "a.bin");
+        assert_eq!(event.inner.file.size, 1024);
+        assert_eq!(event.inner.file.uuid, b"uuid");
+        assert_eq!(event.inner.file.md5, vec![1; 16]);
+    }
+
+    #[tokio::test]
+    async fn test_self_friend_file_message() {
+        let config =
+            Config::new(Device::random(), Protocol::IPad.into()).with_self_message_events(true);
+        let (client, server, mut rx) = setup_with_config(config).await;
+        client.password_login(10000, "password").await.unwrap();
+
+        // 其他设备发给好友的文件
+        server.push_sync_message(10000, friend_file_message_pb(10000, 20000));
+        let event = wait_event(&mut rx, |e| match e {
+            QEvent::FriendFileMessage(e) => Some(e),
+            _ => None,
+        })
+        .await;
+        assert_eq!(event.inner.from_uin, 10000);
+        assert_eq!(event.inner.target, 20000);
+    }
+
+    #[tokio::test]
+    async fn test_send_message_failed() {
+        let (client, server, _rx) = setup().await;
+        client.password_login(10000, "password").await.unwrap();
+        server.on("MessageSvc.PbSendMsg", |_| {
+            Some(
+                pb::msg::SendMessageResponse {
+                    result: Some(55),
+                    err_msg: Some("blocked".into()),
+                }
+                .to_bytes(),
+            )
+        });
+        let err = client
+            .send_friend_message(20000, MessageChain::new(Text::new("hi".into())))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RQError::Other(msg) if msg.contains("55")));
+    }
+
+    // 模拟长消息下载服务器，返回加密后的 PbMultiMsgTransmit
+    async fn spawn_multi_msg_download(data: Vec<u8>, key: [u8; 16]) -> std::net::SocketAddr {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let body = qqtea_encrypt(
+            &pb::longmsg::LongRspBody {
+                msg_down_rsp: vec![pb::longmsg::LongMsgDownRsp {
+                    msg_content: data,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }
+            .to_bytes(),
+            &key,
+        );
+        let mut content = BytesMut::new();
+        content.put_u8(40);
+        content.put_i32(0);
+        content.put_i32(body.len() as i32);
+        content.put_slice(&body);
+        content.put_u8(41);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut req = Vec::new();
+            while !req.ends_with(b"\r\n\r\n") {
+                let mut buf = [0; 1024];
+                let n = stream.read(&mut buf).await.unwrap();
+                assert_ne!(n, 0);
+                req.extend_from_slice(&buf[..n]);
+            }
+            assert!(req.starts_with(b"GET /long HTTP/1.1\r\n"));
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n",
+                content.len()
+            );
+            stream.write_all(head.as_bytes()).await.unwrap();
+            stream.write_all(&content).await.unwrap();
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_long_group_message() {
+        use ricq_core::command::multi_msg::{
+            gen_long_message_template, long_message_chain, MessageNode, MultiMsgTarget,
+        };
+        use ricq_core::common::RQAddr;
+
+        let (client, server, mut rx) = setup().await;
+        client.password_login(10000, "password").await.unwrap();
+
+        let data = client.engine.read().await.calculate_multi_msg_data(
+            vec![MessageNode {
+                sender_id: 20000,
+                time: 0,
+                sender_name: "sender".into(),
+                elements: MessageChain::new(Text::new("a very long message".into())),
+            }
+            .into()],
+            MultiMsgTarget::Group(1234),
+        );
+        let key = [7; 16];
+        let addr = RQAddr::from(spawn_multi_msg_download(data, key).await);
+        server.on("MultiMsg.ApplyDown", move |_| {
+            Some(
+                pb::multimsg::MultiRspBody {
+                    multimsg_applydown_rsp: vec![pb::multimsg::MultiMsgApplyDownRsp {
+                        thumb_down_para: b"/long".to_vec(),
+                        msg_key: key.to_vec(),
+                        down_ip: vec![addr.0],
+                        down_port: vec![addr.1 as u32],
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }
+                .to_bytes(),
+            )
+        });
+
+        let template = gen_long_message_template("res", "file", "a very");
+        server.push_group_message(1234, 20000, long_message_chain(template, "res".into()));
+        let event = wait_event(&mut rx, |e| match e {
+            QEvent::GroupMessage(e) => Some(e),
+            _ => None,
+        })
+        .await;
+        assert_eq!(
+            event.inner.elements.to_string().trim(),
+            "a very long message"
+        );
+        let req = server.received_by_command("MultiMsg.ApplyDown");
+        let req = pb::multimsg::MultiReqBody::decode(&*req[0].body).unwrap();
+        assert_eq!(req.multimsg_applydown_req[0].msg_resid, b"res");
+        assert_eq!(req.bu_type, 1);
+    }
+}